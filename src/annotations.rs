@@ -0,0 +1,267 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum AnnotationValue {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Number(f64),
+    String(String),
+    Enum {
+        #[serde(rename = "#")]
+        symbol: String,
+    },
+    Expression {
+        #[serde(rename = "=")]
+        path: String,
+    },
+    Array(Vec<AnnotationValue>),
+    Record(BTreeMap<String, AnnotationValue>),
+}
+
+impl AnnotationValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            AnnotationValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            AnnotationValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[AnnotationValue]> {
+        match self {
+            AnnotationValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, property: &str) -> Option<&AnnotationValue> {
+        match self {
+            AnnotationValue::Record(record) => record.get(property),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotationName<'a> {
+    pub term: &'a str,
+    pub qualifier: Option<&'a str>,
+}
+
+impl<'a> AnnotationName<'a> {
+    pub fn parse(name: &'a str) -> AnnotationName<'a> {
+        let name = name.strip_prefix('@').unwrap_or(name);
+        match name.find('#') {
+            Some(pos) => AnnotationName {
+                term: &name[..pos],
+                qualifier: Some(&name[pos + 1..]),
+            },
+            None => AnnotationName {
+                term: name,
+                qualifier: None,
+            },
+        }
+    }
+}
+
+// Annotations are stored by their name without the leading `@`,
+// including an optional `#qualifier`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Annotations(BTreeMap<String, AnnotationValue>);
+
+impl Serialize for Annotations {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            self.0
+                .iter()
+                .map(|(name, value)| (format!("@{}", name), value)),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for Annotations {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Annotations, D::Error> {
+        let map = BTreeMap::<String, AnnotationValue>::deserialize(deserializer)?;
+        let mut annotations = Annotations::new();
+        for (name, value) in map {
+            annotations.insert(&name, value);
+        }
+        Ok(annotations)
+    }
+}
+
+impl Annotations {
+    pub fn new() -> Annotations {
+        Annotations(BTreeMap::new())
+    }
+
+    pub fn insert(&mut self, name: &str, value: AnnotationValue) {
+        let name = name.strip_prefix('@').unwrap_or(name);
+        self.0.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AnnotationValue> {
+        self.0.get(name.strip_prefix('@').unwrap_or(name))
+    }
+
+    pub fn get_qualified(&self, term: &str, qualifier: Option<&str>) -> Option<&AnnotationValue> {
+        let term = term.strip_prefix('@').unwrap_or(term);
+        match qualifier {
+            Some(qualifier) => self.0.get(&format!("{}#{}", term, qualifier)),
+            None => self.0.get(term),
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    // A bare annotation like `@readonly` is stored as `true` in CSN.
+    pub fn is_true(&self, name: &str) -> bool {
+        self.get(name).and_then(AnnotationValue::as_bool) == Some(true)
+    }
+
+    pub fn qualifiers<'a>(
+        &'a self,
+        term: &'a str,
+    ) -> impl Iterator<Item = (Option<&'a str>, &'a AnnotationValue)> + 'a {
+        let term = term.strip_prefix('@').unwrap_or(term);
+        self.0.iter().filter_map(move |(name, value)| {
+            let parsed = AnnotationName::parse(name);
+            match parsed.term == term {
+                true => Some((parsed.qualifier, value)),
+                false => None,
+            }
+        })
+    }
+
+    pub fn vocabulary<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a AnnotationValue)> + 'a {
+        let prefix = prefix.strip_prefix('@').unwrap_or(prefix);
+        self.0
+            .iter()
+            .filter(move |(name, _)| {
+                name.strip_prefix(prefix)
+                    .is_some_and(|rest| rest.starts_with('.'))
+            })
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &AnnotationValue)> {
+        self.0.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_annotations() -> Annotations {
+        let input_str = r##"{
+            "@title": "Books",
+            "@readonly": true,
+            "@assert.range": [0, 100],
+            "@UI.LineItem": [{ "Value": { "=": "title" } }],
+            "@UI.LineItem#foo": [{ "Value": { "=": "author" } }],
+            "@UI.TextArrangement": { "#": "TextOnly" },
+            "@restrict": [{ "grant": "READ", "to": "Viewer" }]
+        }"##;
+        serde_json::from_str(input_str).unwrap()
+    }
+
+    #[test]
+    fn serialize_annotations() {
+        let mut annotations = Annotations::new();
+        annotations.insert("@readonly", AnnotationValue::Bool(true));
+        annotations.insert("title", AnnotationValue::String("Books".to_string()));
+        assert_eq!(
+            serde_json::to_string(&annotations).unwrap(),
+            r#"{"@readonly":true,"@title":"Books"}"#
+        );
+    }
+
+    #[test]
+    fn annotation_name() {
+        let name = AnnotationName::parse("@UI.LineItem#foo");
+        assert_eq!(name.term, "UI.LineItem");
+        assert_eq!(name.qualifier, Some("foo"));
+        assert_eq!(AnnotationName::parse("title").qualifier, None);
+    }
+
+    #[test]
+    fn annotation_lookup() {
+        let annotations = get_test_annotations();
+        assert_eq!(annotations.len(), 7);
+        assert_eq!(
+            annotations.get("@title").and_then(|v| v.as_str()),
+            Some("Books")
+        );
+        assert_eq!(
+            annotations.get("title").and_then(|v| v.as_str()),
+            Some("Books")
+        );
+        assert!(annotations.is_true("@readonly"));
+        assert!(!annotations.is_true("@cds.persistence.skip"));
+        assert_eq!(
+            annotations.get("@assert.range"),
+            Some(&AnnotationValue::Array(vec![
+                AnnotationValue::Integer(0),
+                AnnotationValue::Integer(100)
+            ]))
+        );
+        assert_eq!(
+            annotations.get("@UI.TextArrangement"),
+            Some(&AnnotationValue::Enum {
+                symbol: "TextOnly".to_string()
+            })
+        );
+        let grant = annotations.get("@restrict").unwrap().as_array().unwrap()[0].get("grant");
+        assert_eq!(grant.and_then(|v| v.as_str()), Some("READ"));
+    }
+
+    #[test]
+    fn annotation_qualifiers() {
+        let annotations = get_test_annotations();
+        let qualified = annotations
+            .get_qualified("@UI.LineItem", Some("foo"))
+            .unwrap();
+        assert_eq!(
+            qualified.as_array().unwrap()[0].get("Value"),
+            Some(&AnnotationValue::Expression {
+                path: "author".to_string()
+            })
+        );
+        let qualifiers: Vec<Option<&str>> = annotations
+            .qualifiers("UI.LineItem")
+            .map(|(qualifier, _)| qualifier)
+            .collect();
+        assert_eq!(qualifiers, vec![None, Some("foo")]);
+        let ui: Vec<&str> = annotations
+            .vocabulary("@UI")
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(
+            ui,
+            vec!["UI.LineItem", "UI.LineItem#foo", "UI.TextArrangement"]
+        );
+    }
+}
@@ -0,0 +1,843 @@
+// Parses CQL query strings like `SELECT from Books { ID, title } where
+// stock > 10` into queries.
+use crate::expr::{
+    invalid_function, is_function_name, BinaryOp, Expr, Literal, COMPARISON, NEG, NOT,
+};
+use crate::query::{all_columns, Column, Nulls, OrderBy, SortOrder, SELECT};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub description: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.description, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Word(String),
+    // `"name"` or `![name]`, never a keyword.
+    Quoted(String),
+    Number(String),
+    String(String),
+    Symbol(&'static str),
+    End,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: Kind,
+    line: usize,
+    column: usize,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Kind::Word(word) | Kind::Number(word) => f.write_str(word),
+            Kind::Quoted(name) => write!(f, "\"{}\"", name),
+            Kind::String(s) => write!(f, "'{}'", s),
+            Kind::Symbol(symbol) => f.write_str(symbol),
+            Kind::End => f.write_str("end of input"),
+        }
+    }
+}
+
+const SYMBOLS: [&str; 21] = [
+    "<=", ">=", "<>", "!=", "==", "||", ",", ".", "(", ")", "[", "]", "{", "}", "*", "=", "<", ">",
+    "+", "-", "/",
+];
+
+// Words which end an expression or a path.
+const RESERVED: [&str; 18] = [
+    "select",
+    "from",
+    "where",
+    "group",
+    "having",
+    "order",
+    "limit",
+    "offset",
+    "as",
+    "and",
+    "or",
+    "in",
+    "like",
+    "is",
+    "between",
+    "asc",
+    "desc",
+    "excluding",
+];
+
+fn tokenize(cql: &str) -> Result<Vec<Token>, ParseError> {
+    let chars: Vec<char> = cql.chars().collect();
+    let mut tokens = vec![];
+    let (mut i, mut line, mut column) = (0, 1, 1);
+    let error = |line, column, description: &str| ParseError {
+        line,
+        column,
+        description: description.to_string(),
+    };
+    while i < chars.len() {
+        let (start, start_line, start_column) = (i, line, column);
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let kind = if c.is_whitespace() {
+            None
+        } else if c == '-' && next == Some('-') {
+            while i + 1 < chars.len() && chars[i + 1] != '\n' {
+                i += 1;
+            }
+            None
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            while i + 1 < chars.len()
+                && (chars[i + 1].is_alphanumeric() || "_$".contains(chars[i + 1]))
+            {
+                i += 1;
+            }
+            Some(Kind::Word(chars[start..=i].iter().collect()))
+        } else if c.is_ascii_digit() {
+            while i + 1 < chars.len() && chars[i + 1].is_ascii_digit() {
+                i += 1;
+            }
+            if chars.get(i + 1) == Some(&'.') && chars.get(i + 2).is_some_and(char::is_ascii_digit)
+            {
+                i += 2;
+                while i + 1 < chars.len() && chars[i + 1].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            // An exponent like `e-3`.
+            if matches!(chars.get(i + 1), Some('e' | 'E')) {
+                let sign = matches!(chars.get(i + 2), Some('+' | '-')) as usize;
+                if chars.get(i + 2 + sign).is_some_and(char::is_ascii_digit) {
+                    i += 2 + sign;
+                    while i + 1 < chars.len() && chars[i + 1].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            Some(Kind::Number(chars[start..=i].iter().collect()))
+        } else if c == '\'' || c == '"' || (c == '!' && next == Some('[')) {
+            // Quotes are escaped by doubling them.
+            let close = match c {
+                '!' => {
+                    i += 1;
+                    ']'
+                }
+                quote => quote,
+            };
+            let mut value = String::new();
+            loop {
+                i += 1;
+                match chars.get(i) {
+                    None => return Err(error(start_line, start_column, "Unterminated literal")),
+                    Some(&ch) if ch == close && chars.get(i + 1) == Some(&close) => {
+                        value.push(ch);
+                        i += 1;
+                    }
+                    Some(&ch) if ch == close => break,
+                    Some(&ch) => value.push(ch),
+                }
+            }
+            match c {
+                '\'' => Some(Kind::String(value)),
+                _ => Some(Kind::Quoted(value)),
+            }
+        } else {
+            let symbol = SYMBOLS.iter().find(|symbol| {
+                symbol
+                    .chars()
+                    .enumerate()
+                    .all(|(j, ch)| chars.get(i + j) == Some(&ch))
+            });
+            match symbol {
+                Some(symbol) => {
+                    i += symbol.len() - 1;
+                    Some(Kind::Symbol(symbol))
+                }
+                None => {
+                    let description = format!("Unexpected character {}", c);
+                    return Err(error(line, column, &description));
+                }
+            }
+        };
+        for &ch in &chars[start..=i] {
+            match ch {
+                '\n' => {
+                    line += 1;
+                    column = 1;
+                }
+                _ => column += 1,
+            }
+        }
+        i += 1;
+        if let Some(kind) = kind {
+            tokens.push(Token {
+                kind,
+                line: start_line,
+                column: start_column,
+            });
+        }
+    }
+    tokens.push(Token {
+        kind: Kind::End,
+        line,
+        column,
+    });
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position]
+    }
+
+    fn peek_at(&self, offset: usize) -> &Kind {
+        let position = (self.position + offset).min(self.tokens.len() - 1);
+        &self.tokens[position].kind
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.position].clone();
+        if self.position + 1 < self.tokens.len() {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error_at(token: &Token, description: String) -> ParseError {
+        ParseError {
+            line: token.line,
+            column: token.column,
+            description,
+        }
+    }
+
+    fn unexpected(&self) -> ParseError {
+        Parser::error_at(self.peek(), format!("Unexpected {}", self.peek().kind))
+    }
+
+    fn keyword(&self) -> Option<String> {
+        match &self.peek().kind {
+            Kind::Word(word) => Some(word.to_ascii_lowercase()),
+            _ => None,
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        self.keyword().as_deref() == Some(keyword)
+    }
+
+    fn is_symbol(&self, symbol: &'static str) -> bool {
+        self.peek().kind == Kind::Symbol(symbol)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.next();
+        }
+        found
+    }
+
+    fn eat_symbol(&mut self, symbol: &'static str) -> bool {
+        let found = self.is_symbol(symbol);
+        if found {
+            self.next();
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        match self.eat_keyword(keyword) {
+            true => Ok(()),
+            false => Err(self.expected(&keyword.to_ascii_uppercase())),
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &'static str) -> Result<(), ParseError> {
+        match self.eat_symbol(symbol) {
+            true => Ok(()),
+            false => Err(self.expected(symbol)),
+        }
+    }
+
+    fn expected(&self, expected: &str) -> ParseError {
+        let description = format!("Expected {}, found {}", expected, self.peek().kind);
+        Parser::error_at(self.peek(), description)
+    }
+
+    fn name(&mut self) -> Result<String, ParseError> {
+        match self.peek().kind.clone() {
+            Kind::Quoted(name) => {
+                self.next();
+                Ok(name)
+            }
+            Kind::Word(word) if !RESERVED.contains(&word.to_ascii_lowercase().as_str()) => {
+                self.next();
+                Ok(word)
+            }
+            _ => Err(self.expected("a name")),
+        }
+    }
+
+    // Path segments up to an inline `.{`.
+    fn path(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut path = vec![self.name()?];
+        while self.is_symbol(".") && matches!(self.peek_at(1), Kind::Word(_) | Kind::Quoted(_)) {
+            self.next();
+            path.push(self.name()?);
+        }
+        Ok(path)
+    }
+
+    fn alias(&mut self) -> Result<Option<String>, ParseError> {
+        match self.eat_keyword("as") {
+            true => self.name().map(Some),
+            false => Ok(None),
+        }
+    }
+
+    fn unsigned(&mut self) -> Result<u64, ParseError> {
+        let token = self.next();
+        match &token.kind {
+            Kind::Number(number) => number
+                .parse()
+                .map_err(|_| Parser::error_at(&token, format!("Invalid number {}", number))),
+            other => Err(Parser::error_at(
+                &token,
+                format!("Expected a number, found {}", other),
+            )),
+        }
+    }
+
+    fn select(&mut self) -> Result<SELECT, ParseError> {
+        self.expect_keyword("select")?;
+        let one = self.eat_keyword("one");
+        let distinct = self.eat_keyword("distinct");
+        let columns = match self.is_keyword("from") {
+            true => None,
+            false => Some(self.columns()?),
+        };
+        self.expect_keyword("from")?;
+        let path = self.path()?;
+        let mut select = SELECT::from(&path.join("."));
+        if self.eat_symbol("[") {
+            select = select.filter(self.expr(0)?);
+            self.expect_symbol("]")?;
+        }
+        select.alias = self.alias()?;
+        select.one = one;
+        select.distinct = distinct;
+        select.columns = match (columns, self.is_symbol("{")) {
+            (Some(_), true) => return Err(self.unexpected()),
+            (Some(columns), false) => columns,
+            (None, true) => self.projection()?.unwrap_or_default(),
+            (None, false) => vec![],
+        };
+        if self.eat_keyword("excluding") {
+            self.expect_symbol("{")?;
+            loop {
+                select.excluding.push(self.name()?);
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+            self.expect_symbol("}")?;
+        }
+        if self.eat_keyword("where") {
+            select = select.filter(self.expr(0)?);
+        }
+        if self.eat_keyword("group") {
+            self.expect_keyword("by")?;
+            loop {
+                select = select.group_by(self.expr(0)?);
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+        if self.eat_keyword("having") {
+            select = select.having(self.expr(0)?);
+        }
+        if self.eat_keyword("order") {
+            self.expect_keyword("by")?;
+            loop {
+                select = select.order_by(self.order_by()?);
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+        if self.eat_keyword("limit") {
+            let rows = self.unsigned()?;
+            let offset = match self.eat_keyword("offset") {
+                true => self.unsigned()?,
+                false => 0,
+            };
+            select = select.limit(rows, offset);
+        } else if self.eat_keyword("offset") {
+            select = select.offset(self.unsigned()?);
+        }
+        match self.peek().kind {
+            Kind::End => Ok(select),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn order_by(&mut self) -> Result<OrderBy, ParseError> {
+        let expr = self.expr(0)?;
+        let sort = match self.keyword().as_deref() {
+            Some("asc") => Some(SortOrder::Asc),
+            Some("desc") => Some(SortOrder::Desc),
+            _ => None,
+        };
+        if sort.is_some() {
+            self.next();
+        }
+        let nulls = match self.eat_keyword("nulls") {
+            true if self.eat_keyword("first") => Some(Nulls::First),
+            true if self.eat_keyword("last") => Some(Nulls::Last),
+            true => return Err(self.expected("FIRST or LAST")),
+            false => None,
+        };
+        Ok(OrderBy { expr, sort, nulls })
+    }
+
+    // `{ ... }`, where `{ * }` and `{ }` stand for all elements.
+    fn projection(&mut self) -> Result<Option<Vec<Column>>, ParseError> {
+        if !self.eat_symbol("{") {
+            return Ok(None);
+        }
+        let columns = match self.is_symbol("}") {
+            true => vec![],
+            false => self.columns()?,
+        };
+        self.expect_symbol("}")?;
+        Ok(Some(all_columns(columns)))
+    }
+
+    fn columns(&mut self) -> Result<Vec<Column>, ParseError> {
+        let mut columns = vec![];
+        loop {
+            columns.extend(self.column()?);
+            if !self.eat_symbol(",") {
+                return Ok(columns);
+            }
+        }
+    }
+
+    // A column, an expand `books[stock > 0] as b { title }` or the columns
+    // of an inline `author.{ name }`.
+    fn column(&mut self) -> Result<Vec<Column>, ParseError> {
+        if self.eat_symbol("*") {
+            return Ok(vec![Column::new(Expr::Star)]);
+        }
+        let expr = self.expr(0)?;
+        let path = match &expr {
+            Expr::Ref(path) => path.clone(),
+            _ => return Ok(vec![self.column_alias(Column::new(expr))?]),
+        };
+        if self.is_symbol(".") && self.peek_at(1) == &Kind::Symbol("{") {
+            self.next();
+            let start = self.peek().clone();
+            let columns = self.projection()?.unwrap_or_default();
+            if columns.is_empty() {
+                return Err(Parser::error_at(
+                    &start,
+                    "Cannot inline all elements".to_string(),
+                ));
+            }
+            return Ok(columns
+                .into_iter()
+                .map(|column| inline(&path, column))
+                .collect());
+        }
+        let filter = match self.is_symbol("[") {
+            true => {
+                let start = self.next();
+                let filter = self.expr(0)?;
+                self.expect_symbol("]")?;
+                Some((start, filter))
+            }
+            false => None,
+        };
+        let mut column = self.column_alias(Column::new(expr))?;
+        match (self.projection()?, filter) {
+            (Some(expand), filter) => {
+                column.expand = Some(expand);
+                column.filter = filter.map(|(_, filter)| filter);
+            }
+            (None, Some((start, _))) => {
+                let description = "Infix filters are only supported for expands".to_string();
+                return Err(Parser::error_at(&start, description));
+            }
+            (None, None) => {}
+        }
+        Ok(vec![column])
+    }
+
+    fn column_alias(&mut self, mut column: Column) -> Result<Column, ParseError> {
+        column.alias = self.alias()?;
+        Ok(column)
+    }
+
+    fn function(&mut self, name: String) -> Result<Expr, ParseError> {
+        self.expect_symbol("(")?;
+        if self.eat_symbol(")") {
+            return Ok(Expr::func(&name, vec![]));
+        }
+        if name.eq_ignore_ascii_case("count") && self.eat_keyword("distinct") {
+            let expr = self.expr(0)?;
+            self.expect_symbol(")")?;
+            return Ok(Expr::count_distinct(expr));
+        }
+        let mut args = vec![];
+        loop {
+            match self.eat_symbol("*") {
+                true => args.push(Expr::Star),
+                false => args.push(self.expr(0)?),
+            }
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+        self.expect_symbol(")")?;
+        Ok(Expr::func(&name, args))
+    }
+
+    fn prefix(&mut self) -> Result<Expr, ParseError> {
+        let token = self.peek().clone();
+        match &token.kind {
+            Kind::Number(number) => {
+                self.next();
+                // Numbers with a fraction keep their digits as decimals, like
+                // integers which do not fit into an i64.
+                let literal = if number.contains(['e', 'E']) {
+                    number
+                        .parse()
+                        .ok()
+                        .filter(|double: &f64| double.is_finite())
+                        .map(Literal::Double)
+                } else if number.contains('.') {
+                    number.parse().map(Literal::Decimal).ok()
+                } else {
+                    number
+                        .parse()
+                        .map(Literal::Integer)
+                        .or_else(|_| number.parse().map(Literal::Decimal))
+                        .ok()
+                };
+                literal
+                    .map(Expr::Val)
+                    .ok_or_else(|| Parser::error_at(&token, format!("Invalid number {}", number)))
+            }
+            Kind::String(s) => {
+                self.next();
+                Ok(Expr::val(s.as_str()))
+            }
+            Kind::Symbol("-") => {
+                self.next();
+                Ok(self.expr(NEG)?.negate())
+            }
+            Kind::Symbol("(") => {
+                self.next();
+                let mut exprs = vec![self.expr(0)?];
+                while self.eat_symbol(",") {
+                    exprs.push(self.expr(0)?);
+                }
+                self.expect_symbol(")")?;
+                match exprs.len() {
+                    1 => Ok(exprs.remove(0)),
+                    _ => Ok(Expr::List(exprs)),
+                }
+            }
+            Kind::Word(word) => match word.to_ascii_lowercase().as_str() {
+                "null" => {
+                    self.next();
+                    Ok(Expr::null())
+                }
+                "true" | "false" => {
+                    self.next();
+                    Ok(Expr::val(word.eq_ignore_ascii_case("true")))
+                }
+                "not" => {
+                    self.next();
+                    Ok(!self.expr(NOT)?)
+                }
+                _ if self.peek_at(1) == &Kind::Symbol("(") => {
+                    let name = self.name()?;
+                    if !is_function_name(&name) {
+                        let description = invalid_function(&name).description;
+                        return Err(Parser::error_at(&token, description));
+                    }
+                    self.function(name)
+                }
+                _ => Ok(Expr::Ref(self.path()?)),
+            },
+            Kind::Quoted(_) => Ok(Expr::Ref(self.path()?)),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    // The operator at the current token, `not` negating `in`, `like` and
+    // `between`.
+    fn operator(&self) -> Option<(String, bool, u8)> {
+        let operator = match &self.peek().kind {
+            Kind::Word(word) => word.to_ascii_lowercase(),
+            Kind::Symbol(symbol) => symbol.to_string(),
+            _ => return None,
+        };
+        if operator == "not" {
+            return match self.peek_at(1) {
+                Kind::Word(word)
+                    if ["in", "like", "between"].contains(&word.to_ascii_lowercase().as_str()) =>
+                {
+                    Some((word.to_ascii_lowercase(), true, COMPARISON))
+                }
+                _ => None,
+            };
+        }
+        match BinaryOp::parse(&operator) {
+            Some(op) => Some((operator, false, op.precedence())),
+            None if operator == "between" => Some((operator, false, COMPARISON)),
+            None => None,
+        }
+    }
+
+    fn expr(&mut self, min_precedence: u8) -> Result<Expr, ParseError> {
+        let mut left = self.prefix()?;
+        while let Some((operator, negated, precedence)) = self.operator() {
+            if precedence < min_precedence {
+                break;
+            }
+            self.next();
+            if negated {
+                self.next();
+            }
+            let expr = match operator.as_str() {
+                "between" => {
+                    let low = self.expr(COMPARISON + 1)?;
+                    self.expect_keyword("and")?;
+                    let high = self.expr(COMPARISON + 1)?;
+                    left.between(low, high)
+                }
+                // The values of `in` are a list, even a single one.
+                "in" => {
+                    self.expect_symbol("(")?;
+                    let mut items = vec![self.expr(0)?];
+                    while self.eat_symbol(",") {
+                        items.push(self.expr(0)?);
+                    }
+                    self.expect_symbol(")")?;
+                    left.in_list(Expr::List(items))
+                }
+                "is" if self.eat_keyword("not") => {
+                    left.binary(BinaryOp::IsNot, self.expr(precedence + 1)?)
+                }
+                _ => {
+                    let op = BinaryOp::parse(&operator).unwrap();
+                    left.binary(op, self.expr(precedence + 1)?)
+                }
+            };
+            left = match negated {
+                true => !expr,
+                false => expr,
+            };
+        }
+        Ok(left)
+    }
+}
+
+// Prefixes the references of an inlined column with the path of the
+// association, `author.{ name }` is selected as `author.name as author_name`.
+fn inline(path: &[String], column: Column) -> Column {
+    let prefixed =
+        |segments: &[String]| -> Vec<String> { path.iter().chain(segments).cloned().collect() };
+    let alias = match (&column.alias, &column.expr) {
+        (Some(alias), _) => Some(alias.clone()),
+        (None, Expr::Ref(segments)) => Some(prefixed(segments).join("_")),
+        (None, _) => None,
+    };
+    let expr = column
+        .expr
+        .try_map_refs(&mut |segments| Ok::<_, ()>(Expr::Ref(prefixed(segments))))
+        .unwrap();
+    Column {
+        expr,
+        alias: match column.expand {
+            Some(_) => column.alias,
+            None => alias,
+        },
+        ..column
+    }
+}
+
+// Parses a CQL `SELECT`. Columns are given either before `from` or as
+// projection after the source, `SELECT from Books { ID, title }`.
+pub fn parse(cql: &str) -> Result<SELECT, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(cql)?,
+        position: 0,
+    };
+    parser.select()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::CQN;
+
+    #[test]
+    fn parse_select() {
+        let select = parse("SELECT ID, title from Books where stock > 10 order by title").unwrap();
+        assert_eq!(
+            select,
+            SELECT::from("Books")
+                .columns(vec!["ID", "title"])
+                .filter(Expr::col("stock").gt(Expr::val(10)))
+                .order_by(OrderBy {
+                    expr: Expr::col("title"),
+                    sort: None,
+                    nulls: None
+                })
+        );
+
+        let select = parse(
+            "SELECT one distinct from my.Books[stock > 10 or title like 'It''s%'] as B {
+               title as name, author.{ name, ID as authorID }, price * 2 as double,
+               count(*) as count, genre { * }, reviews[rating >= 4] { rating }
+             } where not ID in (1, 2) and descr is not null and -stock not between 1 and 2
+             group by title having count(distinct ID) > 1
+             order by title desc nulls last, \"ID\" limit 10 offset 20",
+        )
+        .unwrap();
+        assert_eq!(select.alias.as_deref(), Some("B"));
+        assert!(select.one && select.distinct);
+        assert_eq!(
+            select.columns[6],
+            Column::expand("reviews", vec![Column::new(Expr::col("rating"))])
+                .filter(Expr::col("rating").ge(Expr::val(4)))
+        );
+        assert_eq!(select.columns[5], Column::expand("genre", vec![]));
+        assert_eq!(
+            select.to_sql().unwrap(),
+            "SELECT DISTINCT title as name,author.name as author_name,author.ID as authorID,price * 2 as double,COUNT(*) as count FROM my.Books as B\n  WHERE (stock > 10 OR title LIKE 'It''s%') AND (NOT ID IN (1, 2) AND descr IS NOT NULL AND NOT -stock BETWEEN 1 AND 2)\n  GROUP BY title\n  HAVING COUNT(DISTINCT ID) > 1\n  ORDER BY title DESC NULLS LAST, ID\n  LIMIT 1 OFFSET 20"
+        );
+
+        let select = parse("SELECT from Books order by title offset 5").unwrap();
+        assert_eq!(
+            select.limit.map(|limit| (limit.rows, limit.offset)),
+            Some((None, 5))
+        );
+
+        let select = parse("SELECT from Books { * } excluding { price, stock }").unwrap();
+        assert_eq!(
+            select,
+            SELECT::from("Books").excluding(vec!["price", "stock"])
+        );
+        assert_eq!(
+            select.to_sql().unwrap_err().to_string(),
+            "Cannot exclude price, stock from * of Books without definitions, resolve the query first"
+        );
+    }
+
+    #[test]
+    fn parse_in() {
+        let select = parse("SELECT from Books where ID in (1) and stock not in (2)").unwrap();
+        assert_eq!(
+            select.filter,
+            Some(
+                Expr::col("ID")
+                    .in_list(Expr::List(vec![Expr::val(1)]))
+                    .and(!Expr::col("stock").in_list(Expr::List(vec![Expr::val(2)])))
+            )
+        );
+        assert_eq!(
+            select.to_sql().unwrap(),
+            "SELECT * FROM Books\n  WHERE ID IN (1) AND NOT stock IN (2)"
+        );
+        let err = parse("SELECT from Books where ID in 1").unwrap_err();
+        assert_eq!(err.description, "Expected (, found 1");
+    }
+
+    #[test]
+    fn parse_numbers() {
+        let select = parse(
+            "SELECT from Books where price = 12.50 and ID = 92233720368547758070 and stock < 1.5e3",
+        )
+        .unwrap();
+        assert_eq!(
+            select.filter,
+            Some(
+                Expr::col("price")
+                    .eq(Expr::Val(Literal::Decimal("12.50".parse().unwrap())))
+                    .and(Expr::col("ID").eq(Expr::Val(Literal::Decimal(
+                        "92233720368547758070".parse().unwrap()
+                    ))))
+                    .and(Expr::col("stock").lt(Expr::val(1500.0)))
+            )
+        );
+        assert_eq!(
+            select.to_sql().unwrap(),
+            "SELECT * FROM Books\n  WHERE price = 12.50 AND ID = 92233720368547758070 AND stock < 1500"
+        );
+        let select = parse("SELECT from Books where price > 2E-2").unwrap();
+        assert_eq!(select.filter, Some(Expr::col("price").gt(Expr::val(0.02))));
+        let err = parse("SELECT from Books where price = 1e999").unwrap_err();
+        assert_eq!(err.description, "Invalid number 1e999");
+        let select = parse("SELECT from Books where stock = - -1").unwrap();
+        assert_eq!(
+            select.to_sql().unwrap(),
+            "SELECT * FROM Books\n  WHERE stock = -(-1)"
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = parse("SELECT title\nfrom Books\n  where stock >").unwrap_err();
+        assert_eq!((err.line, err.column), (3, 16));
+        assert_eq!(
+            err.to_string(),
+            "Unexpected end of input at line 3, column 16"
+        );
+
+        let err = parse("SELECT title Books").unwrap_err();
+        assert_eq!(err.description, "Expected FROM, found Books");
+        assert_eq!((err.line, err.column), (1, 14));
+
+        let err = parse("SELECT author[ID > 1].name from Books").unwrap_err();
+        assert_eq!(
+            err.description,
+            "Infix filters are only supported for expands"
+        );
+        assert_eq!((err.line, err.column), (1, 14));
+
+        let err = parse("SELECT title, $drop(ID) from Books").unwrap_err();
+        assert_eq!(err.description, "Invalid function name \"$drop\"");
+        assert_eq!((err.line, err.column), (1, 15));
+
+        let err = parse("SELECT from Books where title = 'x").unwrap_err();
+        assert_eq!(err.description, "Unterminated literal");
+        assert_eq!((err.line, err.column), (1, 33));
+    }
+}
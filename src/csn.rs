@@ -0,0 +1,1744 @@
+use crate::annotations::{AnnotationValue, Annotations};
+use crate::entities::*;
+use crate::expr::{invalid_function, is_function_name, Expr, Literal, Token};
+use crate::json::Node;
+use crate::query::{Column, SELECT};
+use crate::resolve::QueryErrorKind;
+use crate::values::Decimal;
+use serde::de::{self, DeserializeOwned, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+// The raw CSN structures below borrow from the input wherever possible and are
+// deserialized in a single pass. Values are kept as owned JSON text, which
+// works for readers and `Value`s as well. Type references and views are resolved
+// afterwards on these structures.
+
+// A string which borrows from the input unless it contains escape sequences.
+struct Str<'de>(Cow<'de, str>);
+
+impl<'de> Deserialize<'de> for Str<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Str<'de>, D::Error> {
+        struct StrVisitor;
+
+        impl<'de> Visitor<'de> for StrVisitor {
+            type Value = Str<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string")
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Str<'de>, E> {
+                Ok(Str(Cow::Borrowed(v)))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Str<'de>, E> {
+                Ok(Str(Cow::Owned(v.to_string())))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<Str<'de>, E> {
+                Ok(Str(Cow::Owned(v)))
+            }
+        }
+
+        deserializer.deserialize_str(StrVisitor)
+    }
+}
+
+fn strings<'de>(strs: Vec<Str<'de>>) -> Vec<Cow<'de, str>> {
+    strs.into_iter().map(|s| s.0).collect()
+}
+
+// A JSON object which keeps the order of its properties.
+pub(crate) struct Ordered<'de, T>(Vec<(Cow<'de, str>, T)>);
+
+impl<'de, T> Ordered<'de, T> {
+    fn get(&self, name: &str) -> Option<&T> {
+        self.0
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&str, &T)> {
+        self.0.iter().map(|(key, value)| (key.as_ref(), value))
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Ordered<'de, T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Ordered<'de, T>, D::Error> {
+        struct OrderedVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for OrderedVisitor<T> {
+            type Value = Ordered<'de, T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Ordered<'de, T>, A::Error> {
+                let mut entries = vec![];
+                while let Some(Str(key)) = map.next_key()? {
+                    entries.push((key, map.next_value()?));
+                }
+                Ok(Ordered(entries))
+            }
+        }
+
+        deserializer.deserialize_map(OrderedVisitor(PhantomData))
+    }
+}
+
+pub(crate) struct RawCsn<'de> {
+    definitions: Option<Ordered<'de, RawNode<'de>>>,
+}
+
+impl<'de> Deserialize<'de> for RawCsn<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<RawCsn<'de>, D::Error> {
+        struct CsnVisitor;
+
+        impl<'de> Visitor<'de> for CsnVisitor {
+            type Value = RawCsn<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a CSN object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RawCsn<'de>, A::Error> {
+                let mut definitions = None;
+                while let Some(Str(key)) = map.next_key()? {
+                    match key.as_ref() {
+                        "definitions" => definitions = Some(map.next_value()?),
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(RawCsn { definitions })
+            }
+        }
+
+        deserializer.deserialize_map(CsnVisitor)
+    }
+}
+
+// Definitions, elements, parameters and return types share most of their
+// properties in CSN, so they are read into the same structure.
+#[derive(Default)]
+pub(crate) struct RawNode<'de> {
+    kind: Option<Cow<'de, str>>,
+    type_name: Option<Cow<'de, str>>,
+    key: bool,
+    length: Option<u64>,
+    precision: Option<u32>,
+    scale: Option<u32>,
+    default: Option<Box<RawValue>>,
+    target: Option<Cow<'de, str>>,
+    cardinality: Option<Cardinality>,
+    keys: Option<Vec<ForeignKey>>,
+    on: Option<Vec<OnToken>>,
+    items: Option<Box<RawNode<'de>>>,
+    elements: Option<Ordered<'de, RawNode<'de>>>,
+    params: Option<Ordered<'de, RawNode<'de>>>,
+    returns: Option<Box<RawNode<'de>>>,
+    actions: Option<Ordered<'de, RawNode<'de>>>,
+    query: Option<RawSelect<'de>>,
+    projection: bool,
+    annotations: Vec<(Cow<'de, str>, AnnotationValue)>,
+}
+
+impl<'de> RawNode<'de> {
+    fn query_path(&self) -> &'static str {
+        match self.projection {
+            true => "projection",
+            false => "query.SELECT",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RawNode<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<RawNode<'de>, D::Error> {
+        struct NodeVisitor;
+
+        impl<'de> Visitor<'de> for NodeVisitor {
+            type Value = RawNode<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a CSN definition")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RawNode<'de>, A::Error> {
+                let mut node = RawNode::default();
+                while let Some(Str(key)) = map.next_key()? {
+                    match key.as_ref() {
+                        "kind" => node.kind = Some(map.next_value::<Str>()?.0),
+                        "type" => node.type_name = Some(map.next_value::<Str>()?.0),
+                        "key" => node.key = map.next_value()?,
+                        "length" => node.length = map.next_value()?,
+                        "precision" => node.precision = map.next_value()?,
+                        "scale" => node.scale = map.next_value()?,
+                        "default" => node.default = Some(map.next_value()?),
+                        "target" => node.target = Some(map.next_value::<Str>()?.0),
+                        "cardinality" => node.cardinality = Some(map.next_value()?),
+                        "keys" => node.keys = Some(map.next_value()?),
+                        "on" => node.on = Some(map.next_value()?),
+                        "items" => node.items = Some(map.next_value()?),
+                        "elements" => node.elements = Some(map.next_value()?),
+                        "params" => node.params = Some(map.next_value()?),
+                        "returns" => node.returns = Some(map.next_value()?),
+                        "actions" => node.actions = Some(map.next_value()?),
+                        "query" => node.query = map.next_value::<RawQuery>()?.select,
+                        "projection" => {
+                            node.query = Some(map.next_value()?);
+                            node.projection = true;
+                        }
+                        name if name.starts_with('@') => {
+                            let value = map.next_value()?;
+                            node.annotations.push((key, value));
+                        }
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(node)
+            }
+        }
+
+        deserializer.deserialize_map(NodeVisitor)
+    }
+}
+
+struct RawQuery<'de> {
+    select: Option<RawSelect<'de>>,
+}
+
+impl<'de> Deserialize<'de> for RawQuery<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<RawQuery<'de>, D::Error> {
+        struct QueryVisitor;
+
+        impl<'de> Visitor<'de> for QueryVisitor {
+            type Value = RawQuery<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a CQN query")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RawQuery<'de>, A::Error> {
+                let mut select = None;
+                while let Some(Str(key)) = map.next_key()? {
+                    match key.as_ref() {
+                        "SELECT" => select = Some(map.next_value()?),
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(RawQuery { select })
+            }
+        }
+
+        deserializer.deserialize_map(QueryVisitor)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct RawSelect<'de> {
+    from: Option<RawToken<'de>>,
+    columns: Option<Vec<RawToken<'de>>>,
+    filter: Option<Vec<RawToken<'de>>>,
+    excluding: Vec<Cow<'de, str>>,
+}
+
+impl<'de> Deserialize<'de> for RawSelect<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<RawSelect<'de>, D::Error> {
+        struct SelectVisitor;
+
+        impl<'de> Visitor<'de> for SelectVisitor {
+            type Value = RawSelect<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a CQN SELECT")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RawSelect<'de>, A::Error> {
+                let mut select = RawSelect::default();
+                while let Some(Str(key)) = map.next_key()? {
+                    match key.as_ref() {
+                        "from" => select.from = Some(map.next_value()?),
+                        "columns" => select.columns = Some(map.next_value()?),
+                        "where" => select.filter = Some(map.next_value()?),
+                        "excluding" => select.excluding = strings(map.next_value()?),
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(select)
+            }
+        }
+
+        deserializer.deserialize_map(SelectVisitor)
+    }
+}
+
+pub(crate) enum RawExpr<'de> {
+    Operator(Cow<'de, str>),
+    Ref(Vec<Cow<'de, str>>),
+    // The value with the kind of its `literal` property.
+    Val(Box<RawValue>, Option<Cow<'de, str>>),
+    Xpr(Vec<RawToken<'de>>),
+    Func(Cow<'de, str>, Vec<RawToken<'de>>),
+    List(Vec<RawToken<'de>>),
+    Unsupported,
+}
+
+// A token of a CQN expression, also used for columns and query sources.
+pub(crate) struct RawToken<'de> {
+    expr: RawExpr<'de>,
+    alias: Option<Cow<'de, str>>,
+    cast: Option<Box<RawNode<'de>>>,
+    key: bool,
+}
+
+impl<'de> Deserialize<'de> for RawToken<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<RawToken<'de>, D::Error> {
+        struct TokenVisitor;
+
+        impl<'de> TokenVisitor {
+            fn token(expr: RawExpr<'de>) -> RawToken<'de> {
+                RawToken {
+                    expr,
+                    alias: None,
+                    cast: None,
+                    key: false,
+                }
+            }
+        }
+
+        impl<'de> Visitor<'de> for TokenVisitor {
+            type Value = RawToken<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a CQN expression")
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<RawToken<'de>, E> {
+                Ok(TokenVisitor::token(RawExpr::Operator(Cow::Borrowed(v))))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<RawToken<'de>, E> {
+                Ok(TokenVisitor::token(RawExpr::Operator(Cow::Owned(
+                    v.to_string(),
+                ))))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RawToken<'de>, A::Error> {
+                let mut token = TokenVisitor::token(RawExpr::Unsupported);
+                let (mut reference, mut val, mut xpr, mut func, mut args, mut list) =
+                    (None, None, None, None, None, None);
+                let mut literal = None;
+                while let Some(Str(key)) = map.next_key()? {
+                    match key.as_ref() {
+                        "ref" => reference = Some(strings(map.next_value()?)),
+                        "val" => val = Some(map.next_value()?),
+                        "literal" => literal = Some(map.next_value::<Str>()?.0),
+                        "xpr" => xpr = Some(map.next_value()?),
+                        "func" => func = Some(map.next_value::<Str>()?.0),
+                        "args" => args = Some(map.next_value()?),
+                        "list" => list = Some(map.next_value()?),
+                        "as" => token.alias = Some(map.next_value::<Str>()?.0),
+                        "cast" => token.cast = Some(map.next_value()?),
+                        "key" => token.key = map.next_value()?,
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                token.expr = match (reference, val, xpr, func) {
+                    (_, _, _, Some(func)) => RawExpr::Func(func, args.unwrap_or_default()),
+                    (Some(reference), _, _, _) => RawExpr::Ref(reference),
+                    (_, Some(val), _, _) => RawExpr::Val(val, literal),
+                    (_, _, Some(xpr), _) => RawExpr::Xpr(xpr),
+                    _ => match list {
+                        Some(list) => RawExpr::List(list),
+                        None => RawExpr::Unsupported,
+                    },
+                };
+                Ok(token)
+            }
+        }
+
+        deserializer.deserialize_any(TokenVisitor)
+    }
+}
+
+// Integers which do not fit into an i64 keep their digits as decimals.
+fn literal_of(val: &RawValue, kind: Option<&str>) -> Result<Literal, DeserializationError> {
+    let value: Value = serde_json::from_str(val.get())?;
+    let text = val.get().trim();
+    let literal = match kind {
+        Some(kind) => Literal::from_typed_json(&value, kind),
+        None if value.is_f64() && text.bytes().all(|b| b.is_ascii_digit() || b == b'-') => {
+            text.parse().map(Literal::Decimal)
+        }
+        None => Ok(Literal::from_json(&value)),
+    };
+    literal.map_err(|err| DeserializationError::new(ErrorKind::InvalidValue, &err.to_string()))
+}
+
+fn unsupported(description: &str) -> DeserializationError {
+    DeserializationError::new(
+        ErrorKind::InvalidQuery(QueryErrorKind::UnsupportedQuery),
+        description,
+    )
+}
+
+fn operand_of(token: &RawToken) -> Result<Expr, DeserializationError> {
+    let expr = match &token.expr {
+        RawExpr::Ref(reference) => Expr::Ref(reference.iter().map(|s| s.to_string()).collect()),
+        RawExpr::Val(val, kind) => Expr::Val(literal_of(val, kind.as_deref())?),
+        RawExpr::Xpr(inner) => expr_of(inner)?,
+        RawExpr::Func(func, args) => {
+            if !is_function_name(func) {
+                return Err(unsupported(&invalid_function(func).description));
+            }
+            let args: Result<Vec<Expr>, _> = args.iter().map(operand_of).collect();
+            Expr::Func(func.to_string(), args?)
+        }
+        RawExpr::List(items) => {
+            let items: Result<Vec<Expr>, _> = items.iter().map(operand_of).collect();
+            Expr::List(items?)
+        }
+        RawExpr::Operator(_) | RawExpr::Unsupported => {
+            return Err(unsupported("Unsupported expression"))
+        }
+    };
+    Ok(expr)
+}
+
+fn expr_of(xpr: &[RawToken]) -> Result<Expr, DeserializationError> {
+    let mut tokens = vec![];
+    for token in xpr {
+        tokens.push(match &token.expr {
+            RawExpr::Operator(operator) => Token::Keyword(operator.to_string()),
+            _ => Token::Expr(operand_of(token)?),
+        });
+    }
+    Expr::from_tokens(tokens).map_err(|err| unsupported(&err.description))
+}
+
+// Translates the `query` or `projection` of a CSN view definition.
+pub(crate) fn select_of(query: &RawSelect) -> Result<SELECT, DeserializationError> {
+    let from = match query.from.as_ref().map(|from| &from.expr) {
+        Some(RawExpr::Ref(reference)) => reference.join("."),
+        _ => {
+            return Err(unsupported("Unsupported query source").at("from"));
+        }
+    };
+    let mut select = SELECT::from(&from);
+    for (i, column) in query.columns.iter().flatten().enumerate() {
+        let expr = match &column.expr {
+            RawExpr::Operator(star) if star == "*" => Expr::Star,
+            _ => operand_of(column).map_err(|err| err.at(&format!("columns[{}]", i)))?,
+        };
+        select.columns.push(Column {
+            expr,
+            alias: column.alias.as_ref().map(|alias| alias.to_string()),
+            expand: None,
+            filter: None,
+        });
+    }
+    select.excluding = query.excluding.iter().map(|e| e.to_string()).collect();
+    if let Some(filter) = &query.filter {
+        select.filter = Some(expr_of(filter).map_err(|err| err.at("where"))?);
+    }
+    Ok(select)
+}
+
+// The facets of a type after following all type references.
+struct Facets<'a, 'de> {
+    builtin: &'a str,
+    length: Option<u64>,
+    precision: Option<u32>,
+    scale: Option<u32>,
+    default: Option<&'a RawValue>,
+    target: Option<&'a str>,
+    cardinality: Option<&'a Cardinality>,
+    keys: Option<&'a Vec<ForeignKey>>,
+    on: Option<&'a Vec<OnToken>>,
+    // Set for structured and arrayed types, with the name of the structured
+    // type if there is one.
+    elements: Option<(&'a str, &'a Ordered<'de, RawNode<'de>>)>,
+    items: Option<&'a RawNode<'de>>,
+    annotations: Vec<(&'a str, &'a AnnotationValue)>,
+}
+
+impl<'a, 'de> Facets<'a, 'de> {
+    fn of(node: &'a RawNode<'de>, builtin: &'a str) -> Facets<'a, 'de> {
+        Facets {
+            builtin,
+            length: node.length,
+            precision: node.precision,
+            scale: node.scale,
+            default: node.default.as_deref(),
+            target: node.target.as_deref(),
+            cardinality: node.cardinality.as_ref(),
+            keys: node.keys.as_ref(),
+            on: node.on.as_ref(),
+            elements: node.elements.as_ref().map(|elements| ("", elements)),
+            items: node.items.as_deref(),
+            annotations: node
+                .annotations
+                .iter()
+                .map(|(name, value)| (name.as_ref(), value))
+                .collect(),
+        }
+    }
+
+    // Facets and annotations of the referring node take precedence over the
+    // ones of the referenced type.
+    fn overlay(mut self, node: &'a RawNode<'de>) -> Facets<'a, 'de> {
+        self.length = node.length.or(self.length);
+        self.precision = node.precision.or(self.precision);
+        self.scale = node.scale.or(self.scale);
+        self.default = node.default.as_deref().or(self.default);
+        self.target = node.target.as_deref().or(self.target);
+        self.cardinality = node.cardinality.as_ref().or(self.cardinality);
+        self.keys = node.keys.as_ref().or(self.keys);
+        self.on = node.on.as_ref().or(self.on);
+        if let Some(elements) = &node.elements {
+            self.elements = Some(("", elements));
+        }
+        self.items = node.items.as_deref().or(self.items);
+        for (name, value) in &node.annotations {
+            self.annotations.retain(|(existing, _)| existing != name);
+            self.annotations.push((name.as_ref(), value));
+        }
+        self
+    }
+
+    fn annotations(&self) -> Annotations {
+        let mut annotations = Annotations::new();
+        for (name, value) in &self.annotations {
+            annotations.insert(name, (*value).clone());
+        }
+        annotations
+    }
+}
+
+fn default_of<T: DeserializeOwned>(
+    default: Option<&RawValue>,
+) -> Result<Option<Default<T>>, DeserializationError> {
+    match default {
+        Some(default) => Ok(Some(serde_json::from_str(default.get())?)),
+        None => Ok(None),
+    }
+}
+
+#[derive(Deserialize)]
+struct RawDefault<'a> {
+    #[serde(borrow)]
+    val: &'a RawValue,
+}
+
+// Decimal defaults are taken from the literal text to avoid a detour via f64.
+fn decimal_default_of(
+    default: Option<&RawValue>,
+) -> Result<Option<Default<Decimal>>, DeserializationError> {
+    let default = match default {
+        Some(default) => default,
+        None => return Ok(None),
+    };
+    let raw: RawDefault = serde_json::from_str(default.get())?;
+    let decimal = match raw.val.get().parse::<Decimal>() {
+        Ok(decimal) => decimal,
+        Err(_) => serde_json::from_str(raw.val.get())?,
+    };
+    Ok(Some(Default::Val(decimal)))
+}
+
+fn kind_of(facets: &Facets) -> Result<ElementKind, DeserializationError> {
+    let default = facets.default;
+    let association = || -> Result<AssociationKind, DeserializationError> {
+        Ok(AssociationKind {
+            target: facets
+                .target
+                .ok_or_else(|| {
+                    DeserializationError::new(ErrorKind::InvalidType, "Cannot find target")
+                })?
+                .to_string(),
+            cardinality: facets.cardinality.cloned(),
+            keys: facets.keys.cloned(),
+            on: facets.on.cloned(),
+        })
+    };
+    let kind = match facets.builtin {
+        "cds.UUID" => ElementKind::UUID(PrimitiveKind {
+            default: default_of(default)?,
+        }),
+        "cds.Boolean" => ElementKind::Boolean(PrimitiveKind {
+            default: default_of(default)?,
+        }),
+        "cds.Integer" => ElementKind::Integer(PrimitiveKind {
+            default: default_of(default)?,
+        }),
+        "cds.String" => ElementKind::String(PrimitiveKindString {
+            default: default_of(default)?,
+            length: facets.length,
+        }),
+        "cds.LargeString" => ElementKind::LargeString(PrimitiveKind {
+            default: default_of(default)?,
+        }),
+        "cds.Decimal" => ElementKind::Decimal(PrimitiveKindDecimal {
+            default: decimal_default_of(default)?,
+            precision: facets.precision,
+            scale: facets.scale,
+        }),
+        "cds.Double" => ElementKind::Double(PrimitiveKind {
+            default: default_of(default)?,
+        }),
+        "cds.Int16" => ElementKind::Int16(PrimitiveKind {
+            default: default_of(default)?,
+        }),
+        "cds.Int32" => ElementKind::Int32(PrimitiveKind {
+            default: default_of(default)?,
+        }),
+        "cds.Int64" => ElementKind::Int64(PrimitiveKind {
+            default: default_of(default)?,
+        }),
+        "cds.UInt8" => ElementKind::UInt8(PrimitiveKind {
+            default: default_of(default)?,
+        }),
+        "cds.Date" => ElementKind::Date(PrimitiveKind {
+            default: default_of(default)?,
+        }),
+        "cds.Time" => ElementKind::Time(PrimitiveKind {
+            default: default_of(default)?,
+        }),
+        "cds.DateTime" => ElementKind::DateTime(PrimitiveKind {
+            default: default_of(default)?,
+        }),
+        "cds.Timestamp" => ElementKind::Timestamp(PrimitiveKind {
+            default: default_of(default)?,
+        }),
+        "cds.Binary" => ElementKind::Binary(PrimitiveKindBinary {
+            default: default_of(default)?,
+            length: facets.length,
+        }),
+        "cds.LargeBinary" => ElementKind::LargeBinary(PrimitiveKind {
+            default: default_of(default)?,
+        }),
+        "cds.Association" => ElementKind::Association(association()?),
+        "cds.Composition" => ElementKind::Composition(association()?),
+        other => {
+            return Err(DeserializationError::new(
+                ErrorKind::UnknownType,
+                &format!("Unknown type {}", other),
+            ));
+        }
+    };
+    Ok(kind)
+}
+
+// Builds an `ElementKind` from a single node without type references.
+pub(crate) fn element_kind_of(node: &RawNode) -> Result<ElementKind, DeserializationError> {
+    match &node.type_name {
+        Some(type_name) => kind_of(&Facets::of(node, type_name)),
+        None => Err(DeserializationError::new(
+            ErrorKind::InvalidType,
+            "Cannot find type",
+        )),
+    }
+}
+
+fn declared_type<'a>(node: &'a RawNode) -> Option<&'a str> {
+    match node.type_name.as_deref() {
+        Some(type_name) if !type_name.starts_with("cds.") => Some(type_name),
+        _ => None,
+    }
+}
+
+// Runs `f` for every entry and collects all problems instead of stopping at
+// the first one.
+fn collect<T, U>(
+    entries: impl Iterator<Item = T>,
+    mut f: impl FnMut(T) -> Result<U, DeserializationError>,
+) -> Result<Vec<U>, DeserializationError> {
+    let mut values = vec![];
+    let mut errors = vec![];
+    for entry in entries {
+        match f(entry) {
+            Ok(value) => values.push(value),
+            Err(err) => errors.push(err),
+        }
+    }
+    match DeserializationError::merge(errors) {
+        Some(err) => Err(err),
+        None => Ok(values),
+    }
+}
+
+struct Loader<'a, 'de> {
+    csn_definitions: &'a Ordered<'de, RawNode<'de>>,
+    index: HashMap<&'a str, &'a RawNode<'de>>,
+    definitions: Vec<Definition>,
+    // The structured types whose elements are being loaded.
+    structures: RefCell<Vec<String>>,
+}
+
+impl<'a, 'de> Loader<'a, 'de> {
+    fn new(csn_definitions: &'a Ordered<'de, RawNode<'de>>) -> Loader<'a, 'de> {
+        Loader {
+            csn_definitions,
+            index: csn_definitions.iter().collect(),
+            definitions: vec![],
+            structures: RefCell::new(vec![]),
+        }
+    }
+
+    fn kind_is(node: &RawNode, kind: &str) -> bool {
+        node.kind.as_deref() == Some(kind)
+    }
+
+    // Looks up the node behind a type reference, either a type definition
+    // (`my.Currency`) or an element of another definition (`my.Books:price`).
+    fn lookup_type(&self, type_name: &str) -> Result<&'a RawNode<'de>, DeserializationError> {
+        let (definition_name, element_path) = match type_name.find(':') {
+            Some(pos) => (&type_name[..pos], Some(&type_name[pos + 1..])),
+            None => (type_name, None),
+        };
+        let unknown = || {
+            DeserializationError::new(
+                ErrorKind::UnknownType,
+                &format!("Unknown type {}", type_name),
+            )
+        };
+        let definition = *self.index.get(definition_name).ok_or_else(unknown)?;
+        match element_path {
+            Some(path) => {
+                let mut target = definition;
+                for segment in path.split('.') {
+                    target = target
+                        .elements
+                        .as_ref()
+                        .and_then(|elements| elements.get(segment))
+                        .ok_or_else(unknown)?;
+                }
+                Ok(target)
+            }
+            None if !Loader::kind_is(definition, "type") => Err(DeserializationError::new(
+                ErrorKind::InvalidType,
+                &format!("{} is not a type", type_name),
+            )),
+            None if definition.type_name.is_none()
+                && definition.elements.is_none()
+                && definition.items.is_none() =>
+            {
+                Err(DeserializationError::new(
+                    ErrorKind::InvalidType,
+                    &format!("Cannot find type of {}", type_name),
+                ))
+            }
+            None => Ok(definition),
+        }
+    }
+
+    // Resolves the type of a node transitively down to a built-in type or a
+    // structured or arrayed type.
+    fn resolve_type(
+        &self,
+        node: &'a RawNode<'de>,
+        visiting: &mut Vec<String>,
+    ) -> Result<Facets<'a, 'de>, DeserializationError> {
+        let type_name = match node.type_name.as_deref() {
+            Some(type_name) if type_name.starts_with("cds.") => {
+                return Ok(Facets::of(node, type_name));
+            }
+            Some(type_name) => type_name,
+            None if node.elements.is_some() || node.items.is_some() => {
+                return Ok(Facets::of(node, ""));
+            }
+            None => {
+                return Err(DeserializationError::new(
+                    ErrorKind::InvalidType,
+                    "Cannot find type",
+                ))
+            }
+        };
+        if visiting.iter().any(|visited| visited == type_name) {
+            visiting.push(type_name.to_string());
+            return Err(DeserializationError::new(
+                ErrorKind::CyclicReference,
+                &format!("Cyclic type reference {}", visiting.join(" -> ")),
+            ));
+        }
+        visiting.push(type_name.to_string());
+        let base = self.lookup_type(type_name)?;
+        let mut facets = self.resolve_type(base, visiting)?;
+        visiting.pop();
+        if let Some(("", elements)) = facets.elements {
+            facets.elements = Some((type_name, elements));
+        }
+        Ok(facets.overlay(node))
+    }
+
+    // Structured types may not contain themselves, not even in arrays.
+    fn structure_kind_of(
+        &self,
+        name: &str,
+        facets: &Facets<'a, 'de>,
+    ) -> Result<ElementKind, DeserializationError> {
+        if let Some((type_name, elements)) = facets.elements {
+            let type_name = match type_name {
+                "" => name,
+                type_name => type_name,
+            };
+            let mut structures = self.structures.borrow_mut();
+            if structures.iter().any(|s| s == type_name) {
+                structures.push(type_name.to_string());
+                let description = format!("Cyclic structured type {}", structures.join(" -> "));
+                structures.pop();
+                return Err(DeserializationError::new(
+                    ErrorKind::CyclicReference,
+                    &description,
+                ));
+            }
+            structures.push(type_name.to_string());
+            drop(structures);
+            let elements = self.elements_of(name, elements);
+            self.structures.borrow_mut().pop();
+            return Ok(ElementKind::Structured(StructuredKind {
+                elements: elements?,
+            }));
+        }
+        if let Some(items) = facets.items {
+            let (items, _) = self
+                .scalar_kind_of(name, items)
+                .map_err(|err| err.at("items"))?;
+            return Ok(ElementKind::Array(ArrayKind {
+                items: Box::new(items),
+            }));
+        }
+        kind_of(facets)
+    }
+
+    // Resolves a node to a built-in type. Errors point to its `type`, or to
+    // its `default` if that does not fit the type.
+    fn scalar_kind_of(
+        &self,
+        name: &str,
+        node: &'a RawNode<'de>,
+    ) -> Result<(ElementKind, Facets<'a, 'de>), DeserializationError> {
+        let facets = self
+            .resolve_type(node, &mut vec![name.to_string()])
+            .map_err(|err| err.at("type"))?;
+        if facets.elements.is_some() || facets.items.is_some() {
+            return Ok((self.structure_kind_of(name, &facets)?, facets));
+        }
+        let kind = kind_of(&facets).map_err(|err| match err.kind {
+            ErrorKind::InvalidValue => err.at("default"),
+            _ => err.at("type"),
+        })?;
+        Ok((kind, facets))
+    }
+
+    fn element_of(
+        &self,
+        owner: &str,
+        name: &str,
+        node: &'a RawNode<'de>,
+    ) -> Result<Element, DeserializationError> {
+        let (kind, facets) = self.scalar_kind_of(&format!("{}:{}", owner, name), node)?;
+        Ok(Element {
+            name: name.to_string(),
+            key: node.key,
+            kind,
+            type_name: declared_type(node).map(str::to_string),
+            annotations: facets.annotations(),
+        })
+    }
+
+    fn elements_of(
+        &self,
+        owner: &str,
+        elements: &'a Ordered<'de, RawNode<'de>>,
+    ) -> Result<Vec<Element>, DeserializationError> {
+        collect(elements.iter(), |(name, node)| {
+            self.element_of(owner, name, node)
+                .map_err(|err| err.at(&json_path(&["elements", name])))
+        })
+    }
+
+    fn find_entity(&self, name: &str) -> Option<&Entity> {
+        self.definitions
+            .iter()
+            .find_map(|definition| match definition {
+                Definition::Entity(entity) if entity.name == name => Some(entity),
+                _ => None,
+            })
+    }
+
+    fn check_entity(&self, name: &str) -> Result<(), DeserializationError> {
+        match self.index.get(name) {
+            Some(node) if Loader::kind_is(node, "entity") => Ok(()),
+            _ => Err(DeserializationError::new(
+                ErrorKind::InvalidQuery(QueryErrorKind::UnknownEntity),
+                &format!("Unknown entity {}", name),
+            )),
+        }
+    }
+
+    // Views without elements get them inferred from their source entity.
+    // Sources which are views themselves are inferred first.
+    fn infer_view(
+        &mut self,
+        name: &str,
+        visiting: &mut Vec<String>,
+    ) -> Result<(), DeserializationError> {
+        if self.find_entity(name).is_some() {
+            return Ok(());
+        }
+        let node = self.index[name];
+        let entity = self
+            .infer_view_of(name, node, visiting)
+            .map_err(|err| err.at(&json_path(&["definitions", name])))?;
+        self.definitions.push(Definition::Entity(entity));
+        Ok(())
+    }
+
+    fn infer_view_of(
+        &mut self,
+        name: &str,
+        node: &'a RawNode<'de>,
+        visiting: &mut Vec<String>,
+    ) -> Result<Entity, DeserializationError> {
+        if visiting.iter().any(|visited| visited == name) {
+            visiting.push(name.to_string());
+            return Err(DeserializationError::new(
+                ErrorKind::CyclicReference,
+                &format!("Cyclic view definition {}", visiting.join(" -> ")),
+            ));
+        }
+        let query = match &node.query {
+            Some(query) => query,
+            None => {
+                return Err(DeserializationError::new(
+                    ErrorKind::MissingElements,
+                    "Cannot find elements",
+                ))
+            }
+        };
+        let query_path = node.query_path();
+        let select = select_of(query).map_err(|err| err.at(query_path))?;
+        self.check_entity(&select.from)
+            .map_err(|err| err.at(&format!("{}.from", query_path)))?;
+        visiting.push(name.to_string());
+        self.infer_view(&select.from, visiting)?;
+
+        let source = self.find_entity(&select.from).unwrap().clone();
+        let columns: &[RawToken] = match &query.columns {
+            Some(columns) => columns,
+            None => &[],
+        };
+        let is_star =
+            |column: &RawToken| matches!(&column.expr, RawExpr::Operator(op) if op == "*");
+        let explicit = collect(columns.iter().enumerate(), |(i, column)| {
+            if is_star(column) {
+                return Ok(None);
+            }
+            self.infer_column(name, &source, column, visiting)
+                .map(Some)
+                .map_err(|err| err.at(&format!("{}.columns[{}]", query_path, i)))
+        })?;
+        let explicit: Vec<Element> = explicit.into_iter().flatten().collect();
+        let mut elements = vec![];
+        if columns.is_empty() || columns.iter().any(is_star) {
+            for element in &source.elements {
+                let overridden = explicit.iter().any(|e| e.name == element.name);
+                if !overridden && !query.excluding.iter().any(|e| e == &element.name) {
+                    elements.push(element.clone());
+                }
+            }
+        }
+        elements.extend(explicit);
+        visiting.pop();
+
+        Ok(Entity {
+            name: name.to_string(),
+            elements,
+            annotations: Facets::of(node, "").annotations(),
+            query: Some(select),
+            actions: self.bound_actions_of(name, node)?,
+        })
+    }
+
+    fn infer_column(
+        &mut self,
+        view_name: &str,
+        source: &Entity,
+        column: &'a RawToken<'de>,
+        visiting: &mut Vec<String>,
+    ) -> Result<Element, DeserializationError> {
+        let path = match &column.expr {
+            RawExpr::Ref(path) => Some(path),
+            _ => None,
+        };
+        let alias = column.alias.as_deref();
+        let cannot_infer = |column_name: &str| {
+            DeserializationError::new(
+                ErrorKind::InvalidQuery(QueryErrorKind::UnknownElement),
+                &format!("Cannot infer element {} of {}", column_name, view_name),
+            )
+        };
+
+        if let Some(cast) = &column.cast {
+            let column_name = alias
+                .or_else(|| path.and_then(|path| path.last()).map(|s| s.as_ref()))
+                .ok_or_else(|| cannot_infer("<anonymous>"))?;
+            let mut element = self
+                .element_of(view_name, column_name, cast)
+                .map_err(|err| err.at("cast"))?;
+            element.key = column.key;
+            return Ok(element);
+        }
+
+        let path = path.ok_or_else(|| cannot_infer(alias.unwrap_or("<anonymous>")))?;
+        let column_name = path.join(".");
+        let mut entity = source.clone();
+        for (i, segment) in path.iter().enumerate() {
+            let element = entity
+                .element(segment)
+                .ok_or_else(|| cannot_infer(&column_name))?
+                .clone();
+            if i == path.len() - 1 {
+                let mut element = element;
+                element.name = alias.unwrap_or(segment).to_string();
+                element.key = (path.len() == 1 && element.key) || column.key;
+                return Ok(element);
+            }
+            let target = match &element.kind {
+                ElementKind::Association(a) | ElementKind::Composition(a) => a.target.clone(),
+                _ => return Err(cannot_infer(&column_name)),
+            };
+            self.check_entity(&target)?;
+            self.infer_view(&target, visiting)?;
+            entity = self.find_entity(&target).unwrap().clone();
+        }
+        Err(cannot_infer(&column_name))
+    }
+
+    fn parameter_kind_of(
+        &self,
+        path: &str,
+        node: &'a RawNode<'de>,
+        bound_entity: Option<&str>,
+    ) -> Result<(bool, ParameterKind, Option<String>), DeserializationError> {
+        if let Some(items) = &node.items {
+            let (_, kind, type_name) = self
+                .parameter_kind_of(path, items, bound_entity)
+                .map_err(|err| err.at("items"))?;
+            return Ok((true, kind, type_name));
+        }
+        if let Some(elements) = &node.elements {
+            let elements = self.elements_of(path, elements)?;
+            return Ok((false, ParameterKind::Structured(elements), None));
+        }
+        let type_name = declared_type(node);
+        let target = match type_name {
+            Some("$self") => bound_entity,
+            Some(type_name) => self
+                .index
+                .get(type_name)
+                .filter(|definition| Loader::kind_is(definition, "entity"))
+                .map(|_| type_name),
+            None => None,
+        };
+        if let Some(target) = target {
+            let kind = ParameterKind::Entity(target.to_string());
+            return Ok((false, kind, type_name.map(str::to_string)));
+        }
+        let (kind, _) = self.scalar_kind_of(path, node)?;
+        Ok((
+            false,
+            ParameterKind::Scalar(kind),
+            type_name.map(str::to_string),
+        ))
+    }
+
+    fn action_of(
+        &self,
+        name: &str,
+        node: &'a RawNode<'de>,
+        bound_entity: Option<&str>,
+    ) -> Result<Action, DeserializationError> {
+        let kind = match node.kind.as_deref() {
+            Some("function") => ActionKind::Function,
+            _ => ActionKind::Action,
+        };
+        let params = collect(
+            node.params.iter().flat_map(Ordered::iter),
+            |(param_name, param)| {
+                let path = format!("{}:{}", name, param_name);
+                let (many, kind, type_name) = self
+                    .parameter_kind_of(&path, param, bound_entity)
+                    .map_err(|err| err.at(&json_path(&["params", param_name])))?;
+                Ok(Parameter {
+                    name: param_name.to_string(),
+                    many,
+                    kind,
+                    type_name,
+                    annotations: Facets::of(param, "").annotations(),
+                })
+            },
+        )?;
+        let returns = match &node.returns {
+            Some(returns) => {
+                let path = format!("{}:returns", name);
+                let (many, kind, type_name) = self
+                    .parameter_kind_of(&path, returns, bound_entity)
+                    .map_err(|err| err.at("returns"))?;
+                Some(Returns {
+                    many,
+                    kind,
+                    type_name,
+                })
+            }
+            None => None,
+        };
+        Ok(Action {
+            name: name.to_string(),
+            kind,
+            params,
+            returns,
+            annotations: Facets::of(node, "").annotations(),
+        })
+    }
+
+    fn bound_actions_of(
+        &self,
+        entity_name: &str,
+        node: &'a RawNode<'de>,
+    ) -> Result<Vec<Action>, DeserializationError> {
+        collect(
+            node.actions.iter().flat_map(Ordered::iter),
+            |(action_name, action)| {
+                let path = format!("{}:{}", entity_name, action_name);
+                let mut action = self
+                    .action_of(&path, action, Some(entity_name))
+                    .map_err(|err| err.at(&json_path(&["actions", action_name])))?;
+                action.name = action_name.to_string();
+                Ok(action)
+            },
+        )
+    }
+
+    // Unbound actions and events are attached to the service they are
+    // defined in.
+    fn attach_to_service(&mut self, definition: Definition) {
+        let name = match &definition {
+            Definition::Action(action) => &action.name,
+            Definition::Event(event) => &event.name,
+            _ => return self.definitions.push(definition),
+        };
+        let service = self
+            .definitions
+            .iter_mut()
+            .filter_map(|candidate| match candidate {
+                Definition::Service(service)
+                    if name.starts_with(&service.name)
+                        && name[service.name.len()..].starts_with('.') =>
+                {
+                    Some(service)
+                }
+                _ => None,
+            })
+            .max_by_key(|service| service.name.len());
+        match (service, definition) {
+            (Some(service), Definition::Action(action)) => service.actions.push(action),
+            (Some(service), Definition::Event(event)) => service.events.push(event),
+            (_, definition) => self.definitions.push(definition),
+        }
+    }
+
+    // Returns `None` for views, which are inferred once all other
+    // definitions are loaded.
+    fn definition_of(
+        &self,
+        name: &str,
+        node: &'a RawNode<'de>,
+    ) -> Result<Option<Definition>, DeserializationError> {
+        let annotations = || Facets::of(node, "").annotations();
+        let definition = match node.kind.as_deref() {
+            Some("service") => Definition::Service(Service {
+                name: name.to_string(),
+                annotations: annotations(),
+                actions: vec![],
+                events: vec![],
+            }),
+            Some("type") => {
+                let (kind, facets) = self.scalar_kind_of(name, node)?;
+                Definition::Type(TypeDefinition {
+                    name: name.to_string(),
+                    kind,
+                    type_name: declared_type(node).map(str::to_string),
+                    annotations: facets.annotations(),
+                })
+            }
+            Some("entity") => {
+                let elements = match (&node.elements, &node.query) {
+                    (Some(elements), _) => elements,
+                    (None, Some(_)) => return Ok(None),
+                    (None, None) => {
+                        return Err(DeserializationError::new(
+                            ErrorKind::MissingElements,
+                            "Cannot find elements",
+                        ));
+                    }
+                };
+                let elements = self.elements_of(name, elements);
+                let query = node
+                    .query
+                    .as_ref()
+                    .map(select_of)
+                    .transpose()
+                    .map_err(|err| err.at(node.query_path()));
+                let actions = self.bound_actions_of(name, node);
+                match (elements, query, actions) {
+                    (Ok(elements), Ok(query), Ok(actions)) => Definition::Entity(Entity {
+                        name: name.to_string(),
+                        elements,
+                        annotations: annotations(),
+                        query,
+                        actions,
+                    }),
+                    (elements, query, actions) => {
+                        let errors = vec![elements.err(), query.err(), actions.err()];
+                        let errors = errors.into_iter().flatten().collect();
+                        return Err(DeserializationError::merge(errors).unwrap());
+                    }
+                }
+            }
+            Some("action") | Some("function") => {
+                Definition::Action(self.action_of(name, node, None)?)
+            }
+            Some("event") => {
+                let elements = match &node.elements {
+                    Some(elements) => self.elements_of(name, elements)?,
+                    None => vec![],
+                };
+                Definition::Event(Event {
+                    name: name.to_string(),
+                    elements,
+                    annotations: annotations(),
+                })
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(definition))
+    }
+
+    fn load(mut self) -> Result<Definitions, DeserializationError> {
+        let mut views = vec![];
+        let mut errors = vec![];
+        let mut unbound = vec![];
+        for (name, node) in self.csn_definitions.iter() {
+            match self.definition_of(name, node) {
+                Ok(Some(definition @ Definition::Action(_)))
+                | Ok(Some(definition @ Definition::Event(_))) => unbound.push(definition),
+                Ok(Some(definition)) => self.definitions.push(definition),
+                Ok(None) if Loader::kind_is(node, "entity") => views.push(name),
+                Ok(None) => {}
+                Err(err) => errors.push(err.at(&json_path(&["definitions", name]))),
+            }
+        }
+        for definition in unbound {
+            self.attach_to_service(definition);
+        }
+        for view in views {
+            if let Err(err) = self.infer_view(view, &mut vec![]) {
+                errors.push(err);
+            }
+        }
+        // A broken view is reported once, even if several views depend on it.
+        let mut problems: Vec<DeserializationError> = vec![];
+        for mut err in errors {
+            let additional = std::mem::take(&mut err.additional);
+            for problem in std::iter::once(err).chain(additional) {
+                if !problems.contains(&problem) {
+                    problems.push(problem);
+                }
+            }
+        }
+        match DeserializationError::merge(problems) {
+            Some(err) => Err(err),
+            None => Ok(Definitions {
+                definitions: self.definitions,
+            }),
+        }
+    }
+}
+
+enum Frame {
+    Object(Option<String>),
+    Array(usize),
+}
+
+// Finds the JSON path of the position where parsing stopped by scanning the
+// input up to that position.
+fn path_at(input: &str, line: usize, column: usize) -> String {
+    let line_start: usize = input
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    let bytes = &input.as_bytes()[..(line_start + column).min(input.len())];
+    let mut stack = vec![];
+    let mut expecting_key = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => {
+                stack.push(Frame::Object(None));
+                expecting_key = true;
+            }
+            b'[' => {
+                stack.push(Frame::Array(0));
+                expecting_key = false;
+            }
+            b'}' | b']' => {
+                stack.pop();
+                expecting_key = false;
+            }
+            b',' => match stack.last_mut() {
+                Some(Frame::Object(key)) => {
+                    *key = None;
+                    expecting_key = true;
+                }
+                Some(Frame::Array(index)) => *index += 1,
+                None => {}
+            },
+            b':' => expecting_key = false,
+            b'"' => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                if let (true, Some(Frame::Object(key))) = (expecting_key, stack.last_mut()) {
+                    let raw = &input[start..(i + 1).min(bytes.len())];
+                    let name = serde_json::from_str(raw).unwrap_or_else(|_| raw.to_string());
+                    *key = Some(name);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    let mut path = String::new();
+    for frame in stack {
+        match frame {
+            Frame::Object(Some(key)) if path.is_empty() => path = json_path(&[&key]),
+            Frame::Object(Some(key)) => path = format!("{}.{}", path, json_path(&[&key])),
+            Frame::Object(None) => break,
+            Frame::Array(index) => path = format!("{}[{}]", path, index),
+        }
+    }
+    path
+}
+
+pub(crate) fn json_error(input: &str, err: serde_json::Error) -> DeserializationError {
+    let path = path_at(input, err.line(), err.column());
+    let mut err = DeserializationError::from(err);
+    err.path = path;
+    err
+}
+
+pub(crate) fn load(csn: &RawCsn) -> Result<Definitions, DeserializationError> {
+    let csn_definitions = csn.definitions.as_ref().ok_or_else(|| {
+        DeserializationError::new(ErrorKind::MissingDefinitions, "Cannot find definitions")
+    })?;
+    Loader::new(csn_definitions).load()
+}
+
+fn without_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+fn properties_of<T: Serialize>(value: &T) -> Vec<(String, Node)> {
+    match serde_json::to_value(value).map(without_nulls) {
+        Ok(Value::Object(map)) => map
+            .into_iter()
+            .map(|(name, value)| (name, Node::Value(value)))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn value_of<T: Serialize>(value: &T) -> Node {
+    Node::Value(serde_json::to_value(value).unwrap_or(Value::Null))
+}
+
+// The type and its facets, e.g. `"type": "cds.String", "length": 100`.
+fn kind_to_csn(kind: &ElementKind, type_name: &Option<String>) -> Vec<(String, Node)> {
+    match (kind, type_name) {
+        (ElementKind::Structured(_) | ElementKind::Array(_), Some(type_name)) => {
+            return vec![("type".to_string(), Node::Value(json!(type_name)))];
+        }
+        (ElementKind::Structured(structured), None) => {
+            return vec![(
+                "elements".to_string(),
+                elements_to_csn(&structured.elements),
+            )];
+        }
+        (ElementKind::Array(array), None) => {
+            let items = Node::Object(kind_to_csn(&array.items, &None));
+            return vec![("items".to_string(), items)];
+        }
+        _ => {}
+    }
+    let mut properties = vec![("type".to_string(), Node::Value(json!(kind.type_name())))];
+    properties.extend(facets_of(kind));
+    for (name, node) in properties.iter_mut() {
+        match (name.as_str(), node) {
+            ("type", Node::Value(value)) => {
+                if let Some(type_name) = type_name {
+                    *value = json!(type_name);
+                }
+            }
+            ("cardinality", Node::Value(value)) => *value = without_nulls(value.take()),
+            ("keys", Node::Value(Value::Array(keys))) => {
+                for key in keys.iter_mut() {
+                    *key = without_nulls(key.take());
+                }
+            }
+            _ => {}
+        }
+    }
+    properties
+}
+
+// The properties of a built-in type besides its name.
+fn facets_of(kind: &ElementKind) -> Vec<(String, Node)> {
+    match kind {
+        ElementKind::UUID(kind) | ElementKind::LargeString(kind) => properties_of(kind),
+        ElementKind::LargeBinary(kind) => properties_of(kind),
+        ElementKind::Boolean(kind) => properties_of(kind),
+        ElementKind::Integer(kind) | ElementKind::Int64(kind) => properties_of(kind),
+        ElementKind::String(kind) => properties_of(kind),
+        ElementKind::Decimal(kind) => properties_of(kind),
+        ElementKind::Double(kind) => properties_of(kind),
+        ElementKind::Int16(kind) => properties_of(kind),
+        ElementKind::Int32(kind) => properties_of(kind),
+        ElementKind::UInt8(kind) => properties_of(kind),
+        ElementKind::Date(kind) => properties_of(kind),
+        ElementKind::Time(kind) => properties_of(kind),
+        ElementKind::DateTime(kind) => properties_of(kind),
+        ElementKind::Timestamp(kind) => properties_of(kind),
+        ElementKind::Binary(kind) => properties_of(kind),
+        ElementKind::Association(kind) | ElementKind::Composition(kind) => properties_of(kind),
+        ElementKind::Structured(_) | ElementKind::Array(_) => vec![],
+    }
+}
+
+pub(crate) fn element_kind_to_csn(kind: &ElementKind) -> Node {
+    Node::Object(kind_to_csn(kind, &None))
+}
+
+fn element_to_csn(element: &Element) -> Node {
+    let mut properties = vec![];
+    if element.key {
+        properties.push(("key".to_string(), Node::Value(json!(true))));
+    }
+    properties.extend(kind_to_csn(&element.kind, &element.type_name));
+    properties.extend(properties_of(&element.annotations));
+    Node::Object(properties)
+}
+
+fn elements_to_csn(elements: &[Element]) -> Node {
+    Node::Object(
+        elements
+            .iter()
+            .map(|element| (element.name.clone(), element_to_csn(element)))
+            .collect(),
+    )
+}
+
+fn parameter_to_csn(many: bool, kind: &ParameterKind, type_name: &Option<String>) -> Node {
+    let properties = match kind {
+        ParameterKind::Scalar(kind) => kind_to_csn(kind, type_name),
+        ParameterKind::Entity(target) => {
+            let type_name = type_name.as_ref().unwrap_or(target);
+            vec![("type".to_string(), Node::Value(json!(type_name)))]
+        }
+        ParameterKind::Structured(elements) => {
+            vec![("elements".to_string(), elements_to_csn(elements))]
+        }
+    };
+    match many {
+        true => Node::Object(vec![("items".to_string(), Node::Object(properties))]),
+        false => Node::Object(properties),
+    }
+}
+
+fn action_to_csn(action: &Action) -> Node {
+    let mut properties = vec![("kind".to_string(), value_of(&action.kind))];
+    properties.extend(properties_of(&action.annotations));
+    if !action.params.is_empty() {
+        let params = action.params.iter().map(|param| {
+            let mut node = parameter_to_csn(param.many, &param.kind, &param.type_name);
+            if let Node::Object(properties) = &mut node {
+                properties.extend(properties_of(&param.annotations));
+            }
+            (param.name.clone(), node)
+        });
+        properties.push(("params".to_string(), Node::Object(params.collect())));
+    }
+    if let Some(returns) = &action.returns {
+        let node = parameter_to_csn(returns.many, &returns.kind, &returns.type_name);
+        properties.push(("returns".to_string(), node));
+    }
+    Node::Object(properties)
+}
+
+fn event_to_csn(event: &Event) -> Node {
+    let mut properties = vec![kind_property("event")];
+    properties.extend(properties_of(&event.annotations));
+    properties.push(("elements".to_string(), elements_to_csn(&event.elements)));
+    Node::Object(properties)
+}
+
+fn kind_property(kind: &str) -> (String, Node) {
+    ("kind".to_string(), Node::Value(json!(kind)))
+}
+
+// Actions and events of services are top-level definitions in CSN.
+pub(crate) fn definitions_to_csn(definitions: &Definitions) -> Node {
+    let mut nodes = vec![];
+    for definition in definitions.definitions() {
+        match definition {
+            Definition::Service(service) => {
+                let mut properties = vec![kind_property("service")];
+                properties.extend(properties_of(&service.annotations));
+                nodes.push((service.name.clone(), Node::Object(properties)));
+                for action in &service.actions {
+                    nodes.push((action.name.clone(), action_to_csn(action)));
+                }
+                for event in &service.events {
+                    nodes.push((event.name.clone(), event_to_csn(event)));
+                }
+            }
+            Definition::Entity(entity) => {
+                let mut properties = vec![kind_property("entity")];
+                properties.extend(properties_of(&entity.annotations));
+                if let Some(query) = &entity.query {
+                    properties.push(("query".to_string(), value_of(query)));
+                }
+                properties.push(("elements".to_string(), elements_to_csn(&entity.elements)));
+                if !entity.actions.is_empty() {
+                    let actions = entity
+                        .actions
+                        .iter()
+                        .map(|action| (action.name.clone(), action_to_csn(action)));
+                    properties.push(("actions".to_string(), Node::Object(actions.collect())));
+                }
+                nodes.push((entity.name.clone(), Node::Object(properties)));
+            }
+            Definition::Type(type_definition) => {
+                let mut properties = vec![kind_property("type")];
+                properties.extend(kind_to_csn(
+                    &type_definition.kind,
+                    &type_definition.type_name,
+                ));
+                properties.extend(properties_of(&type_definition.annotations));
+                nodes.push((type_definition.name.clone(), Node::Object(properties)));
+            }
+            Definition::Action(action) => nodes.push((action.name.clone(), action_to_csn(action))),
+            Definition::Event(event) => nodes.push((event.name.clone(), event_to_csn(event))),
+        }
+    }
+    Node::Object(vec![("definitions".to_string(), Node::Object(nodes))])
+}
+
+pub(crate) fn write(definitions: &Definitions) -> String {
+    serde_json::to_string_pretty(definitions).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::CQN;
+    use std::str::FromStr;
+
+    #[test]
+    fn borrow_strings() {
+        let input_str = r#"{"definitions": {
+            "my.Books": {
+              "kind": "entity",
+              "elements": { "title": { "type": "cds.String" }, "na\"me": { "type": "cds.String" } }
+            }
+          }}"#;
+        let csn: RawCsn = serde_json::from_str(input_str).unwrap();
+        let (name, books) = &csn.definitions.as_ref().unwrap().0[0];
+        assert!(matches!(name, Cow::Borrowed("my.Books")));
+        let elements = &books.elements.as_ref().unwrap().0;
+        assert!(matches!(elements[0].0, Cow::Borrowed("title")));
+        assert!(matches!(elements[1].0, Cow::Owned(_)));
+    }
+
+    #[test]
+    fn select_from_csn_query() {
+        let query: RawQuery = serde_json::from_str(
+            r#"{"SELECT": {
+                "from": { "ref": ["my.Books"] },
+                "columns": ["*", { "ref": ["author", "name"], "as": "authorName" }],
+                "where": [{ "ref": ["stock"] }, ">", { "val": 10 }, "and",
+                  { "xpr": [{ "ref": ["title"] }, "=", { "val": "it's" }, "or",
+                    { "func": "lower", "args": [{ "ref": ["genre"] }] }, "=", { "val": "fantasy" }] }]
+            }}"#,
+        )
+        .unwrap();
+        let select = select_of(&query.select.unwrap()).unwrap();
+        assert_eq!(select.from, "my.Books");
+        assert_eq!(
+            select.to_sql().unwrap(),
+            "SELECT *,author.name as authorName FROM my.Books\n  WHERE stock > 10 AND (title = 'it''s' OR lower(genre) = 'fantasy')"
+        );
+
+        let query: RawQuery = serde_json::from_str(
+            r#"{"SELECT": {
+                "from": { "ref": ["my.Books"] },
+                "where": [{ "ref": ["published"] }, ">", { "val": "2020-01-01", "literal": "date" },
+                  "and", { "ref": ["ID"] }, "<", { "val": 123456789012345678901234567890 }]
+            }}"#,
+        )
+        .unwrap();
+        let select = select_of(&query.select.unwrap()).unwrap();
+        assert_eq!(
+            select.filter,
+            Some(
+                Expr::col("published")
+                    .gt(Expr::Val(Literal::Date("2020-01-01".parse().unwrap())))
+                    .and(Expr::col("ID").lt(Expr::Val(Literal::Decimal(
+                        "123456789012345678901234567890".parse().unwrap()
+                    ))))
+            )
+        );
+    }
+
+    #[test]
+    fn select_from_csn_projection() {
+        let projection: RawSelect =
+            serde_json::from_str(r#"{ "from": { "ref": ["my.Books"] } }"#).unwrap();
+        let select = select_of(&projection).unwrap();
+        assert_eq!(select.to_sql().unwrap(), "SELECT * FROM my.Books");
+
+        let join: RawSelect =
+            serde_json::from_str(r#"{ "from": { "join": "inner", "args": [] } }"#).unwrap();
+        assert!(select_of(&join).is_err());
+    }
+
+    #[test]
+    fn decimal_default_keeps_precision() {
+        let input_str =
+            r#"{"type": "cds.Decimal", "default": { "val": 12345678901234567890.123456789 }}"#;
+        let node: RawNode = serde_json::from_str(input_str).unwrap();
+        match element_kind_of(&node).unwrap() {
+            ElementKind::Decimal(a) => match a.default {
+                Some(Default::Val(default)) => {
+                    assert_eq!(default.as_str(), "12345678901234567890.123456789")
+                }
+                _ => panic!("Could not deserialize default"),
+            },
+            _ => panic!("Could not deserialize"),
+        }
+    }
+
+    #[test]
+    fn write_csn() {
+        let input_str = r#"{"definitions": {
+            "CatalogService": { "kind": "service", "@path": "/browse" },
+            "CatalogService.submitOrder": {
+              "kind": "action",
+              "params": { "book": { "type": "cds.Integer" }, "quantity": { "type": "cds.Integer" } },
+              "returns": { "items": { "type": "my.Books" } }
+            },
+            "my.Price": { "kind": "type", "type": "cds.Decimal", "precision": 9, "scale": 2 },
+            "my.Books": {
+              "kind": "entity",
+              "@title": "Books",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "title": { "type": "cds.String", "length": 111, "default": { "val": "new" } },
+                "price": { "type": "my.Price" },
+                "author": { "type": "cds.Association", "target": "my.Authors", "keys": [{ "ref": ["ID"] }] }
+              },
+              "actions": { "review": { "kind": "function", "returns": { "type": "cds.Boolean" } } }
+            },
+            "my.Authors": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "address": { "elements": { "city": { "type": "cds.String" } } },
+                "tags": { "items": { "type": "cds.String", "length": 10 } },
+                "books": {
+                  "type": "cds.Association", "target": "my.Books", "cardinality": { "max": "*" },
+                  "on": [{ "ref": ["books", "author"] }, "=", { "ref": ["$self"] }]
+                }
+              }
+            },
+            "CatalogService.Books": {
+              "kind": "entity",
+              "projection": { "from": { "ref": ["my.Books"] } }
+            }
+          }}"#;
+        let definitions = Definitions::from_str(input_str).unwrap();
+        let csn = definitions.to_csn();
+        let value: Value = serde_json::from_str(&csn).unwrap();
+        assert_eq!(
+            value["definitions"]["my.Books"]["elements"]["title"],
+            json!({ "type": "cds.String", "default": { "val": "new" }, "length": 111 })
+        );
+        assert_eq!(
+            value["definitions"]["my.Books"]["elements"]["price"],
+            json!({ "type": "my.Price", "precision": 9, "scale": 2 })
+        );
+        assert_eq!(
+            value["definitions"]["CatalogService.submitOrder"]["returns"],
+            json!({ "items": { "type": "my.Books" } })
+        );
+        assert_eq!(
+            value["definitions"]["CatalogService.Books"]["query"],
+            json!({ "SELECT": { "from": { "ref": ["my.Books"] } } })
+        );
+        assert_eq!(
+            value["definitions"]["my.Authors"]["elements"]["tags"],
+            json!({ "items": { "type": "cds.String", "length": 10 } })
+        );
+        assert!(csn.find("\"ID\"").unwrap() < csn.find("\"title\"").unwrap());
+
+        let reread = Definitions::from_str(&csn).unwrap();
+        assert_eq!(reread.to_csn(), csn);
+        assert_eq!(
+            reread
+                .entity("CatalogService.Books")
+                .unwrap()
+                .elements
+                .len(),
+            4
+        );
+    }
+}
@@ -0,0 +1,251 @@
+// Initial data of entities from CSV files like CAP's `db/data/*.csv`. The
+// first line names the columns, values are separated by `;` or `,`.
+use crate::ddl::Column;
+use crate::entities::*;
+use crate::expr::Literal;
+use crate::query::{Query, INSERT, UPSERT};
+use crate::resolve::{InvalidQuery, QueryErrorKind};
+use crate::validate::did_you_mean;
+use crate::values::Decimal;
+
+// The records of a CSV text. Quoted fields may contain separators, line
+// breaks and quotes written as `""`.
+fn records(csv: &str) -> Vec<(usize, Vec<String>)> {
+    let header = csv.lines().next().unwrap_or_default();
+    let separator = match header.contains(';') {
+        true => ';',
+        false => ',',
+    };
+    let mut records = vec![];
+    let (mut record, mut field, mut quoted) = (vec![], String::new(), false);
+    let (mut line, mut start) = (1, 1);
+    let mut chars = csv.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|field| !field.is_empty()) {
+                    records.push((start, std::mem::take(&mut record)));
+                }
+                record.clear();
+                line += 1;
+                start = line;
+            }
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    record.push(field);
+    if record.iter().any(|field| !field.is_empty()) {
+        records.push((start, record));
+    }
+    records
+}
+
+fn invalid(column: &Column, element: Option<&Element>, value: &str, line: usize) -> InvalidQuery {
+    let type_name = match element {
+        Some(element) => element.kind.type_name().to_string(),
+        None => column.column_type.clone(),
+    };
+    InvalidQuery {
+        kind: QueryErrorKind::InvalidValue,
+        path: column.name.clone(),
+        description: format!("Invalid {} value {} in line {}", type_name, value, line),
+    }
+}
+
+// Empty fields are NULL, other values are converted by the type of the
+// column, which foreign keys take from the keys of their target.
+fn literal(
+    column: &Column,
+    element: Option<&Element>,
+    value: &str,
+    line: usize,
+) -> Result<Literal, InvalidQuery> {
+    if value.is_empty() {
+        return Ok(Literal::Null);
+    }
+    let literal = match column.column_type.split('(').next().unwrap_or_default() {
+        "BOOLEAN" => match value.to_ascii_lowercase().as_str() {
+            "true" | "1" => Some(Literal::Bool(true)),
+            "false" | "0" => Some(Literal::Bool(false)),
+            _ => None,
+        },
+        "INTEGER" | "SMALLINT" | "BIGINT" | "TINYINT" => value.parse().ok().map(Literal::Integer),
+        "DOUBLE" => value
+            .parse()
+            .ok()
+            .filter(|double: &f64| double.is_finite())
+            .map(Literal::Double),
+        "DECIMAL" => value.parse::<Decimal>().ok().map(Literal::Decimal),
+        _ => Some(Literal::String(value.to_string())),
+    };
+    literal.ok_or_else(|| invalid(column, element, value, line))
+}
+
+impl Entity {
+    // Upserts the rows of a CSV text, so that loading it again keeps the
+    // data unchanged. Entities without keys cannot be upserted, their rows
+    // are inserted.
+    pub fn data_from_csv(
+        &self,
+        definitions: &Definitions,
+        csv: &str,
+    ) -> Result<Query, InvalidQuery> {
+        let keys = self.key_columns(definitions);
+        let mut records = records(csv).into_iter();
+        let header: Vec<String> = match records.next() {
+            Some((_, header)) => header.iter().map(|name| name.trim().to_string()).collect(),
+            None => return Ok(Query::Insert(INSERT::into(&self.name))),
+        };
+        let columns = self.columns(definitions);
+        let mut header_columns = vec![];
+        for name in &header {
+            match columns.iter().find(|column| &column.name == name) {
+                Some(column) => header_columns.push((column, self.element(name))),
+                None => {
+                    let names = columns.iter().map(|column| column.name.as_str());
+                    return Err(InvalidQuery::new(
+                        QueryErrorKind::UnknownElement,
+                        format!(
+                            "Cannot find column {} of {}{}",
+                            name,
+                            self.name,
+                            did_you_mean(name, names)
+                        ),
+                    ));
+                }
+            }
+        }
+        let mut rows = vec![];
+        for (line, record) in records {
+            if record.len() != header.len() {
+                return Err(InvalidQuery::new(
+                    QueryErrorKind::InvalidValue,
+                    format!(
+                        "Expected {} values in line {}, found {}",
+                        header.len(),
+                        line,
+                        record.len()
+                    ),
+                ));
+            }
+            let row = header_columns
+                .iter()
+                .zip(record.iter().map(|field| field.trim()))
+                .map(|((column, element), value)| literal(column, *element, value, line))
+                .collect::<Result<Vec<Literal>, InvalidQuery>>()?;
+            rows.push(row);
+        }
+        let insert = INSERT::into(&self.name)
+            .columns(header.iter().map(String::as_str).collect())
+            .rows(rows);
+        Ok(match keys.is_empty() {
+            true => Query::Insert(insert),
+            false => Query::Upsert(UPSERT { insert, keys }),
+        })
+    }
+}
+
+impl Definitions {
+    // The entity of a data file, named like `my.bookshop-Books.csv` or
+    // `my.bookshop.Books.csv`.
+    pub fn data_entity(&self, file_name: &str) -> Option<&Entity> {
+        let name = file_name.strip_suffix(".csv")?.replace('-', ".");
+        self.entity(&name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::CQN;
+    use std::str::FromStr;
+
+    fn get_test_csn() -> Definitions {
+        let input_str = r#"{"definitions": {
+            "my.Books": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "title": { "type": "cds.String" },
+                "available": { "type": "cds.Boolean" },
+                "rating": { "type": "cds.Double" },
+                "author": { "type": "cds.Association", "target": "my.Authors" }
+              }
+            },
+            "my.Authors": {
+              "kind": "entity",
+              "elements": { "ID": { "key": true, "type": "cds.Integer" } }
+            },
+            "my.Logs": {
+              "kind": "entity",
+              "elements": { "message": { "type": "cds.String" } }
+            }
+          }}"#;
+        Definitions::from_str(input_str).unwrap()
+    }
+
+    #[test]
+    fn data_from_csv() {
+        let definitions = get_test_csn();
+        let books = definitions.data_entity("my-Books.csv").unwrap();
+        let csv = "ID; title ;available;author_ID\n1;\"Jane \"\"Eyre\"\"; 1847\";true;2\n\n2;;0;\n";
+        assert_eq!(
+            books.data_from_csv(&definitions, csv).unwrap().to_sql().unwrap(),
+            "INSERT INTO my.Books (ID, title, available, author_ID) VALUES (1, 'Jane \"Eyre\"; 1847', TRUE, 2), (2, NULL, FALSE, NULL) ON CONFLICT (ID) DO UPDATE SET title = excluded.title, available = excluded.available, author_ID = excluded.author_ID"
+        );
+
+        let logs = definitions.data_entity("my.Logs.csv").unwrap();
+        assert_eq!(
+            logs.data_from_csv(&definitions, "message\nstarted\n")
+                .unwrap()
+                .to_sql()
+                .unwrap(),
+            "INSERT INTO my.Logs (message) VALUES ('started')"
+        );
+
+        let err = books
+            .data_from_csv(&definitions, "ID,titel\n1,Emma")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot find column titel of my.Books, did you mean title?"
+        );
+        let err = books.data_from_csv(&definitions, "ID\n1\nx").unwrap_err();
+        assert_eq!(err.to_string(), "ID: Invalid cds.Integer value x in line 3");
+        let err = books
+            .data_from_csv(&definitions, "ID,author_ID\n1,x")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "author_ID: Invalid INTEGER value x in line 2"
+        );
+        let err = books
+            .data_from_csv(&definitions, "ID,title\n1,Emma\n2\n")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Expected 2 values in line 3, found 1");
+        let err = books
+            .data_from_csv(&definitions, "ID,title\n1,Emma,1815\n")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Expected 2 values in line 2, found 3");
+        let err = books
+            .data_from_csv(&definitions, "ID,rating\n1,NaN\n")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "rating: Invalid cds.Double value NaN in line 2"
+        );
+    }
+}
@@ -0,0 +1,533 @@
+use crate::dialect::{Dialect, Generic};
+use crate::entities::*;
+use crate::query::CQN;
+use crate::resolve::{InvalidQuery, QueryErrorKind};
+
+// Table and view names follow CAP's convention of replacing dots, e.g.
+// `my.Books` becomes `my_Books`.
+pub fn table_name(entity_name: &str) -> String {
+    entity_name.replace('.', "_")
+}
+
+fn quote_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+fn default_sql<T, F: Fn(&T) -> String>(default: &Option<Default<T>>, f: F) -> Option<String> {
+    default.as_ref().map(|Default::Val(val)| f(val))
+}
+
+impl ElementKind {
+    // The column type as named by HANA, see `Dialect::column_type` for other
+    // databases. `None` for associations, compositions and structured
+    // elements. Arrays are stored as JSON text.
+    pub fn column_type(&self) -> Option<String> {
+        let column_type = match self {
+            ElementKind::UUID(_) => "NVARCHAR(36)".to_string(),
+            ElementKind::Boolean(_) => "BOOLEAN".to_string(),
+            ElementKind::Integer(_) | ElementKind::Int32(_) => "INTEGER".to_string(),
+            ElementKind::String(a) => format!("NVARCHAR({})", a.length.unwrap_or(5000)),
+            ElementKind::LargeString(_) => "NCLOB".to_string(),
+            ElementKind::Decimal(a) => match (a.precision, a.scale) {
+                (Some(precision), Some(scale)) => format!("DECIMAL({}, {})", precision, scale),
+                (Some(precision), None) => format!("DECIMAL({})", precision),
+                _ => "DECIMAL".to_string(),
+            },
+            ElementKind::Double(_) => "DOUBLE".to_string(),
+            ElementKind::Int16(_) => "SMALLINT".to_string(),
+            ElementKind::Int64(_) => "BIGINT".to_string(),
+            ElementKind::UInt8(_) => "TINYINT".to_string(),
+            ElementKind::Date(_) => "DATE".to_string(),
+            ElementKind::Time(_) => "TIME".to_string(),
+            ElementKind::DateTime(_) | ElementKind::Timestamp(_) => "TIMESTAMP".to_string(),
+            ElementKind::Binary(a) => format!("VARBINARY({})", a.length.unwrap_or(5000)),
+            ElementKind::LargeBinary(_) => "BLOB".to_string(),
+            ElementKind::Array(_) => "NCLOB".to_string(),
+            ElementKind::Association(_)
+            | ElementKind::Composition(_)
+            | ElementKind::Structured(_) => return None,
+        };
+        Some(column_type)
+    }
+
+    // The literal of the `default` value.
+    pub fn default_sql(&self) -> Option<String> {
+        match self {
+            ElementKind::UUID(a) => default_sql(&a.default, |v| quote_string(v)),
+            ElementKind::Boolean(a) => default_sql(&a.default, |v| match v {
+                true => "TRUE".to_string(),
+                false => "FALSE".to_string(),
+            }),
+            ElementKind::Integer(a) | ElementKind::Int64(a) => {
+                default_sql(&a.default, i64::to_string)
+            }
+            ElementKind::String(a) => default_sql(&a.default, |v| quote_string(v)),
+            ElementKind::LargeString(a) => default_sql(&a.default, |v| quote_string(v)),
+            ElementKind::Decimal(a) => default_sql(&a.default, |v| v.to_string()),
+            ElementKind::Double(a) => default_sql(&a.default, f64::to_string),
+            ElementKind::Int16(a) => default_sql(&a.default, i16::to_string),
+            ElementKind::Int32(a) => default_sql(&a.default, i32::to_string),
+            ElementKind::UInt8(a) => default_sql(&a.default, u8::to_string),
+            ElementKind::Date(a) => default_sql(&a.default, |v| quote_string(&v.to_string())),
+            ElementKind::Time(a) => default_sql(&a.default, |v| quote_string(&v.to_string())),
+            ElementKind::DateTime(a) => default_sql(&a.default, |v| quote_string(&v.to_string())),
+            ElementKind::Timestamp(a) => default_sql(&a.default, |v| quote_string(&v.to_string())),
+            ElementKind::Binary(a) => default_sql(&a.default, |v| quote_string(v)),
+            ElementKind::LargeBinary(a) => default_sql(&a.default, |v| quote_string(v)),
+            ElementKind::Association(_)
+            | ElementKind::Composition(_)
+            | ElementKind::Structured(_)
+            | ElementKind::Array(_) => None,
+        }
+    }
+}
+
+// A column of a table, either an element or the foreign key of a managed
+// association.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub column_type: String,
+    pub key: bool,
+    pub default: Option<String>,
+}
+
+impl Column {
+    pub fn to_sql(&self) -> String {
+        self.to_sql_for(&Generic)
+    }
+
+    // The column definition with its name quoted and its type named for
+    // `dialect`.
+    pub fn to_sql_for<D: Dialect + ?Sized>(&self, dialect: &D) -> String {
+        let mut sql = format!(
+            "{} {}",
+            dialect.quote(&self.name),
+            dialect.column_type(&self.column_type)
+        );
+        if self.key {
+            sql.push_str(" NOT NULL");
+        }
+        if let Some(default) = &self.default {
+            sql = format!("{} DEFAULT {}", sql, default);
+        }
+        sql
+    }
+}
+
+impl Element {
+    // The columns of the element. Managed to-one associations are stored in
+    // one column per foreign key named like `author_ID`, other associations
+    // have no columns. Structured elements are flattened like `address_city`.
+    pub fn columns(&self, definitions: &Definitions) -> Vec<Column> {
+        let association = match &self.kind {
+            ElementKind::Association(a) | ElementKind::Composition(a) => a,
+            ElementKind::Structured(structured) => {
+                let columns = structured
+                    .elements
+                    .iter()
+                    .flat_map(|element| element.columns(definitions));
+                return columns
+                    .map(|column| Column {
+                        name: format!("{}_{}", self.name, column.name),
+                        key: self.key || column.key,
+                        ..column
+                    })
+                    .collect();
+            }
+            kind => {
+                return vec![Column {
+                    name: self.name.clone(),
+                    column_type: kind.column_type().unwrap_or_default(),
+                    key: self.key,
+                    default: kind.default_sql(),
+                }]
+            }
+        };
+        if !association.is_managed() || association.is_to_many() {
+            return vec![];
+        }
+        let target = match definitions.entity(&association.target) {
+            Some(target) => target,
+            None => return vec![],
+        };
+        let foreign_keys: Vec<Vec<String>> = match &association.keys {
+            Some(keys) => keys.iter().map(|key| key.reference.clone()).collect(),
+            None => target.keys().map(|key| vec![key.name.clone()]).collect(),
+        };
+        let mut columns = vec![];
+        for reference in foreign_keys {
+            let element = match target.element(&reference.join(".")) {
+                Some(element) => element,
+                None => continue,
+            };
+            // Foreign keys may themselves be managed associations.
+            for column in element.columns(definitions) {
+                columns.push(Column {
+                    name: format!("{}_{}", self.name, column.name),
+                    column_type: column.column_type,
+                    key: self.key,
+                    default: None,
+                });
+            }
+        }
+        columns
+    }
+}
+
+impl Entity {
+    pub fn table_name(&self) -> String {
+        table_name(&self.name)
+    }
+
+    pub fn columns(&self, definitions: &Definitions) -> Vec<Column> {
+        self.elements
+            .iter()
+            .flat_map(|element| element.columns(definitions))
+            .collect()
+    }
+
+    pub fn key_columns(&self, definitions: &Definitions) -> Vec<String> {
+        self.columns(definitions)
+            .into_iter()
+            .filter(|column| column.key)
+            .map(|column| column.name)
+            .collect()
+    }
+
+    pub fn create_table(&self, definitions: &Definitions) -> String {
+        self.create_table_for(definitions, &Generic)
+    }
+
+    // The CREATE TABLE statement with identifiers quoted for `dialect`.
+    pub fn create_table_for(&self, definitions: &Definitions, dialect: &dyn Dialect) -> String {
+        self.create_table_named(definitions, &self.table_name(), dialect)
+    }
+
+    pub(crate) fn create_table_named(
+        &self,
+        definitions: &Definitions,
+        table: &str,
+        dialect: &dyn Dialect,
+    ) -> String {
+        let columns = self.columns(definitions);
+        let mut lines: Vec<String> = columns
+            .iter()
+            .map(|column| column.to_sql_for(dialect))
+            .collect();
+        let keys: Vec<String> = columns
+            .iter()
+            .filter(|column| column.key)
+            .map(|column| dialect.quote(&column.name))
+            .collect();
+        if !keys.is_empty() {
+            lines.push(format!("PRIMARY KEY({})", keys.join(", ")));
+        }
+        format!(
+            "CREATE TABLE {} (\n  {}\n)",
+            dialect.quote(table),
+            lines.join(",\n  ")
+        )
+    }
+}
+
+impl Entity {
+    // Entities with `@cds.persistence.skip` are not stored, those with
+    // `@cds.persistence.exists` are created outside of the model.
+    pub fn is_deployed(&self) -> bool {
+        !self.annotations.is_true("cds.persistence.skip")
+            && !self.annotations.is_true("cds.persistence.exists")
+    }
+
+    pub fn create_view(&self, definitions: &Definitions) -> Result<String, InvalidQuery> {
+        self.create_view_for(definitions, &Generic)
+    }
+
+    // The CREATE VIEW statement with identifiers quoted for `dialect`.
+    pub fn create_view_for(
+        &self,
+        definitions: &Definitions,
+        dialect: &'static dyn Dialect,
+    ) -> Result<String, InvalidQuery> {
+        let query = self.query.as_ref().ok_or_else(|| {
+            InvalidQuery::new(
+                QueryErrorKind::UnsupportedQuery,
+                format!("{} is not a view", self.name),
+            )
+        })?;
+        let select = query.resolve(definitions)?;
+        Ok(format!(
+            "CREATE VIEW {} AS {}",
+            dialect.quote(&self.table_name()),
+            select.to_sql_for(dialect)?
+        ))
+    }
+
+    // The entities a view reads from, including the targets of paths.
+    fn dependencies<'a>(
+        &self,
+        definitions: &'a Definitions,
+    ) -> Result<Vec<&'a Entity>, InvalidQuery> {
+        let select = match &self.query {
+            Some(query) => query.resolve(definitions)?,
+            None => return Ok(vec![]),
+        };
+        let mut tables = vec![select.from];
+        tables.extend(select.joins.into_iter().map(|join| join.table));
+        Ok(tables
+            .iter()
+            .filter_map(|table| definitions.table_entity(table))
+            .collect())
+    }
+}
+
+impl Definitions {
+    pub(crate) fn entities(&self) -> impl Iterator<Item = &Entity> {
+        self.definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Entity(entity) => Some(entity),
+                _ => None,
+            })
+    }
+
+    // The entity stored in a table or view.
+    pub fn table_entity(&self, table: &str) -> Option<&Entity> {
+        self.entities().find(|entity| entity.table_name() == table)
+    }
+
+    // CREATE TABLE statements for all deployed entities which are not views.
+    pub fn create_tables(&self) -> Vec<String> {
+        self.entities()
+            .filter(|entity| entity.query.is_none() && entity.is_deployed())
+            .map(|entity| entity.create_table(self))
+            .collect()
+    }
+
+    // The entities to deploy, tables first and views after the entities they
+    // read from.
+    pub fn deploy_order(&self) -> Result<Vec<&Entity>, InvalidQuery> {
+        let mut ordered: Vec<&Entity> = self
+            .entities()
+            .filter(|entity| entity.query.is_none() && entity.is_deployed())
+            .collect();
+        let mut views = vec![];
+        for entity in self.entities() {
+            if entity.query.is_some() && entity.is_deployed() {
+                views.push((entity, entity.dependencies(self)?));
+            }
+        }
+        while !views.is_empty() {
+            let (ready, pending): (Vec<_>, Vec<_>) =
+                views.into_iter().partition(|(_, dependencies)| {
+                    dependencies.iter().all(|dependency| {
+                        dependency.query.is_none()
+                            || !dependency.is_deployed()
+                            || ordered.iter().any(|entity| entity.name == dependency.name)
+                    })
+                });
+            if ready.is_empty() {
+                let names: Vec<&str> = pending.iter().map(|(view, _)| view.name.as_str()).collect();
+                return Err(InvalidQuery::new(
+                    QueryErrorKind::CyclicReference,
+                    format!("Views {} depend on each other", names.join(", ")),
+                ));
+            }
+            ordered.extend(ready.into_iter().map(|(view, _)| view));
+            views = pending;
+        }
+        Ok(ordered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn get_test_csn() -> Definitions {
+        let input_str = r#"{"definitions": {
+            "my.Authors": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "name": { "type": "cds.String", "length": 111 },
+                "address": {
+                  "elements": { "street": { "type": "cds.String", "length": 60 }, "city": { "type": "cds.String", "length": 40 } }
+                },
+                "tags": { "items": { "type": "cds.String" } },
+                "books": {
+                  "type": "cds.Association",
+                  "cardinality": { "max": "*" },
+                  "target": "my.Books",
+                  "on": [{ "ref": ["books", "author"] }, "=", { "ref": ["$self"] }]
+                }
+              }
+            },
+            "my.Books": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.UUID" },
+                "title": { "type": "cds.String", "default": { "val": "it's new" } },
+                "price": { "type": "cds.Decimal", "precision": 9, "scale": 2 },
+                "available": { "type": "cds.Boolean", "default": { "val": true } },
+                "published": { "type": "cds.Date", "default": { "val": "2020-01-01" } },
+                "author": { "type": "cds.Association", "target": "my.Authors" }
+              }
+            },
+            "my.BookInfos": {
+              "kind": "entity",
+              "projection": { "from": { "ref": ["my.Books"] }, "excluding": ["price", "author"] },
+              "elements": {
+                "ID": { "key": true, "type": "cds.UUID" },
+                "title": { "type": "cds.String" },
+                "available": { "type": "cds.Boolean" },
+                "published": { "type": "cds.Date" }
+              }
+            },
+            "my.BookTitles": {
+              "kind": "entity",
+              "projection": { "from": { "ref": ["my.CheapBooks"] }, "columns": [{ "ref": ["title"] }] },
+              "elements": { "title": { "type": "cds.String" } }
+            },
+            "my.CheapBooks": {
+              "kind": "entity",
+              "projection": { "from": { "ref": ["my.Books"] }, "where": [{ "ref": ["price"] }, "<", { "val": 10 }] },
+              "elements": {
+                "ID": { "key": true, "type": "cds.UUID" },
+                "title": { "type": "cds.String" }
+              }
+            },
+            "my.Drafts": {
+              "kind": "entity",
+              "@cds.persistence.skip": true,
+              "elements": { "ID": { "key": true, "type": "cds.UUID" } }
+            }
+          }}"#;
+        Definitions::from_str(input_str).unwrap()
+    }
+
+    #[test]
+    fn create_table() {
+        let definitions = get_test_csn();
+        let books = definitions.entity("my.Books").unwrap();
+        assert_eq!(
+            books.create_table(&definitions),
+            "CREATE TABLE my_Books (
+  ID NVARCHAR(36) NOT NULL,
+  title NVARCHAR(5000) DEFAULT 'it''s new',
+  price DECIMAL(9, 2),
+  available BOOLEAN DEFAULT TRUE,
+  published DATE DEFAULT '2020-01-01',
+  author_ID INTEGER,
+  PRIMARY KEY(ID)
+)"
+        );
+    }
+
+    #[test]
+    fn create_table_types() {
+        let definitions = Definitions::from_str(
+            r#"{"definitions": {
+            "Files": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.UUID" },
+                "name": { "type": "cds.String", "length": 100 },
+                "content": { "type": "cds.LargeBinary" },
+                "hash": { "type": "cds.Binary", "length": 32 },
+                "notes": { "type": "cds.LargeString" },
+                "ratio": { "type": "cds.Double" },
+                "level": { "type": "cds.UInt8" }
+              }
+            }
+          }}"#,
+        )
+        .unwrap();
+        let files = definitions.entity("Files").unwrap();
+        #[cfg(feature = "dialect-postgres")]
+        assert_eq!(
+            files.create_table_for(&definitions, &crate::dialect::Postgres),
+            r#"CREATE TABLE "Files" (
+  "ID" VARCHAR(36) NOT NULL,
+  "name" VARCHAR(100),
+  "content" BYTEA,
+  "hash" BYTEA,
+  "notes" TEXT,
+  "ratio" DOUBLE PRECISION,
+  "level" SMALLINT,
+  PRIMARY KEY("ID")
+)"#
+        );
+        #[cfg(feature = "dialect-mysql")]
+        assert_eq!(
+            files.create_table_for(&definitions, &crate::dialect::MySql),
+            "CREATE TABLE `Files` (
+  `ID` NVARCHAR(36) NOT NULL,
+  `name` NVARCHAR(100),
+  `content` LONGBLOB,
+  `hash` VARBINARY(32),
+  `notes` LONGTEXT,
+  `ratio` DOUBLE,
+  `level` TINYINT UNSIGNED,
+  PRIMARY KEY(`ID`)
+)"
+        );
+        assert!(files
+            .create_table(&definitions)
+            .contains("\n  content BLOB,\n"));
+    }
+
+    #[test]
+    fn create_tables() {
+        let tables = get_test_csn().create_tables();
+        assert_eq!(tables.len(), 2);
+        assert_eq!(
+            tables[0],
+            "CREATE TABLE my_Authors (
+  ID INTEGER NOT NULL,
+  name NVARCHAR(111),
+  address_street NVARCHAR(60),
+  address_city NVARCHAR(40),
+  tags NCLOB,
+  PRIMARY KEY(ID)
+)"
+        );
+    }
+
+    #[test]
+    fn deploy_order() {
+        let definitions = get_test_csn();
+        let names: Vec<&str> = definitions
+            .deploy_order()
+            .unwrap()
+            .iter()
+            .map(|entity| entity.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "my.Authors",
+                "my.Books",
+                "my.BookInfos",
+                "my.CheapBooks",
+                "my.BookTitles"
+            ]
+        );
+        let view = definitions.entity("my.BookTitles").unwrap();
+        assert_eq!(
+            view.create_view(&definitions).unwrap(),
+            "CREATE VIEW my_BookTitles AS SELECT CheapBooks.title FROM my_CheapBooks as CheapBooks"
+        );
+    }
+
+    #[test]
+    fn create_view_excluding() {
+        let definitions = get_test_csn();
+        let view = definitions.entity("my.BookInfos").unwrap();
+        assert_eq!(
+            view.create_view(&definitions).unwrap(),
+            "CREATE VIEW my_BookInfos AS SELECT Books.ID,Books.title,Books.available,Books.published FROM my_Books as Books"
+        );
+    }
+}
@@ -0,0 +1,579 @@
+// The differences between databases when rendering SQL. Each database's
+// dialect is behind a cargo feature like `dialect-sqlite`.
+use crate::migration::Change;
+use crate::params::PlaceholderStyle;
+use std::fmt::Debug;
+
+pub trait Dialect: Debug + Sync {
+    fn quote(&self, identifier: &str) -> String {
+        format!("\"{}\"", identifier.replace('"', "\"\""))
+    }
+
+    fn boolean(&self, value: bool) -> &'static str {
+        match value {
+            true => "TRUE",
+            false => "FALSE",
+        }
+    }
+
+    // Without `rows` all rows after the offset.
+    fn limit(&self, rows: Option<u64>, offset: u64) -> String {
+        match (rows, offset) {
+            (Some(rows), 0) => format!("LIMIT {}", rows),
+            (Some(rows), offset) => format!("LIMIT {} OFFSET {}", rows, offset),
+            (None, offset) => format!("OFFSET {}", offset),
+        }
+    }
+
+    // An expression generating a new UUID.
+    fn uuid(&self) -> &'static str;
+
+    // The current timestamp, for `$now`.
+    fn now(&self) -> &'static str {
+        "CURRENT_TIMESTAMP"
+    }
+
+    fn concat(&self, operands: &[String]) -> String {
+        operands.join(" || ")
+    }
+
+    fn placeholder_style(&self) -> PlaceholderStyle {
+        PlaceholderStyle::Question
+    }
+
+    // Whether `ORDER BY` supports NULLS FIRST/LAST.
+    fn nulls_order(&self) -> bool {
+        true
+    }
+
+    // The database's name of a column type like `NVARCHAR(100)`, which are
+    // given as in HANA.
+    fn column_type(&self, column_type: &str) -> String {
+        column_type.to_string()
+    }
+
+    // Inserts the `rows` into `table` or updates the other `columns` of the
+    // rows with the same `keys`.
+    fn upsert(
+        &self,
+        table: &str,
+        columns: &[String],
+        rows: &[Vec<String>],
+        keys: &[String],
+    ) -> String {
+        let updates: Vec<String> = columns
+            .iter()
+            .filter(|column| !keys.contains(column))
+            .map(|column| format!("{} = excluded.{}", column, column))
+            .collect();
+        let action = match updates.is_empty() {
+            true => "DO NOTHING".to_string(),
+            false => format!("DO UPDATE SET {}", updates.join(", ")),
+        };
+        format!(
+            "INSERT INTO {} ON CONFLICT ({}) {}",
+            values(table, columns, rows),
+            keys.join(", "),
+            action
+        )
+    }
+
+    // The statements applying a change of the columns or keys of a table,
+    // `None` if the table has to be rebuilt.
+    fn alter_table(&self, table: &str, change: &Change) -> Option<Vec<String>> {
+        Some(alter_table(self, table, change))
+    }
+}
+
+// Standard SQL for `Dialect::alter_table`, with identifiers quoted by
+// `dialect`.
+fn alter_table<D: Dialect + ?Sized>(dialect: &D, table: &str, change: &Change) -> Vec<String> {
+    let alter = |action: String| format!("ALTER TABLE {} {}", dialect.quote(table), action);
+    let quote = |name: &str| dialect.quote(name);
+    match change {
+        Change::AddEntity { .. } | Change::RemoveEntity { .. } => vec![],
+        Change::AddColumn { column, .. } => {
+            vec![alter(format!("ADD COLUMN {}", column.to_sql_for(dialect)))]
+        }
+        Change::RemoveColumn { column, .. } => {
+            vec![alter(format!("DROP COLUMN {}", quote(&column.name)))]
+        }
+        Change::ChangeType { to, .. } | Change::ChangeLength { to, .. } => vec![alter(format!(
+            "ALTER COLUMN {} SET DATA TYPE {}",
+            quote(&to.name),
+            dialect.column_type(&to.column_type)
+        ))],
+        Change::ChangeDefault { to, .. } => vec![alter(match &to.default {
+            Some(default) => format!("ALTER COLUMN {} SET DEFAULT {}", quote(&to.name), default),
+            None => format!("ALTER COLUMN {} DROP DEFAULT", quote(&to.name)),
+        })],
+        Change::ChangeKeys { from, to, .. } => {
+            let mut statements = vec![];
+            if !from.is_empty() {
+                statements.push(alter("DROP PRIMARY KEY".to_string()));
+            }
+            if !to.is_empty() {
+                statements.push(alter(format!(
+                    "ADD PRIMARY KEY({})",
+                    quote_all(dialect, to)
+                )));
+            }
+            statements
+        }
+    }
+}
+
+// The values of an INSERT, like `Books (ID, title) VALUES (1, 'Emma')`.
+pub(crate) fn values(table: &str, columns: &[String], rows: &[Vec<String>]) -> String {
+    let rows: Vec<String> = rows
+        .iter()
+        .map(|row| format!("({})", row.join(", ")))
+        .collect();
+    format!(
+        "{} ({}) VALUES {}",
+        table,
+        columns.join(", "),
+        rows.join(", ")
+    )
+}
+
+fn quote_all<D: Dialect + ?Sized>(dialect: &D, names: &[String]) -> String {
+    let quoted: Vec<String> = names.iter().map(|name| dialect.quote(name)).collect();
+    quoted.join(", ")
+}
+
+// Plain SQL as rendered by `to_sql`, with identifiers as they are unless
+// they are not plain names like `my.Books` or `author_ID`.
+#[derive(Debug, Clone, Copy)]
+pub struct Generic;
+
+impl Dialect for Generic {
+    fn quote(&self, identifier: &str) -> String {
+        let mut chars = identifier.chars();
+        let plain = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$')
+            && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '$' | '.'));
+        match plain {
+            true => identifier.to_string(),
+            false => format!("\"{}\"", identifier.replace('"', "\"\"")),
+        }
+    }
+
+    fn uuid(&self) -> &'static str {
+        "uuid()"
+    }
+}
+
+#[cfg(feature = "dialect-sqlite")]
+#[derive(Debug, Clone, Copy)]
+pub struct Sqlite;
+
+// Booleans are stored as integers, UUIDs are formatted from random bytes.
+#[cfg(feature = "dialect-sqlite")]
+impl Dialect for Sqlite {
+    fn boolean(&self, value: bool) -> &'static str {
+        match value {
+            true => "1",
+            false => "0",
+        }
+    }
+
+    fn uuid(&self) -> &'static str {
+        "lower(hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || substr(hex(randomblob(2)), 2) || '-' || substr('89ab', 1 + (abs(random()) % 4), 1) || substr(hex(randomblob(2)), 2) || '-' || hex(randomblob(6)))"
+    }
+
+    fn now(&self) -> &'static str {
+        "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')"
+    }
+
+    // An offset needs a limit, which is negative for all rows.
+    fn limit(&self, rows: Option<u64>, offset: u64) -> String {
+        match rows {
+            Some(_) => Generic.limit(rows, offset),
+            None => format!("LIMIT -1 OFFSET {}", offset),
+        }
+    }
+
+    // Only columns which are not keys can be added and dropped.
+    fn alter_table(&self, table: &str, change: &Change) -> Option<Vec<String>> {
+        match change {
+            Change::AddColumn { column, .. } | Change::RemoveColumn { column, .. }
+                if !column.key =>
+            {
+                Some(alter_table(self, table, change))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "dialect-postgres")]
+#[derive(Debug, Clone, Copy)]
+pub struct Postgres;
+
+#[cfg(feature = "dialect-postgres")]
+impl Dialect for Postgres {
+    fn uuid(&self) -> &'static str {
+        "gen_random_uuid()"
+    }
+
+    fn placeholder_style(&self) -> PlaceholderStyle {
+        PlaceholderStyle::Numbered
+    }
+
+    // Binary columns are `BYTEA` of any length.
+    fn column_type(&self, column_type: &str) -> String {
+        let (base, args) = crate::migration::type_parts(column_type);
+        match base {
+            "NVARCHAR" => format!("VARCHAR({})", args.first().copied().unwrap_or(5000)),
+            "NCLOB" => "TEXT".to_string(),
+            "DOUBLE" => "DOUBLE PRECISION".to_string(),
+            "TINYINT" => "SMALLINT".to_string(),
+            "VARBINARY" | "BLOB" => "BYTEA".to_string(),
+            _ => column_type.to_string(),
+        }
+    }
+
+    // Primary keys are constraints named like `table_pkey`.
+    fn alter_table(&self, table: &str, change: &Change) -> Option<Vec<String>> {
+        let alter = |action: String| format!("ALTER TABLE {} {}", self.quote(table), action);
+        match change {
+            Change::ChangeLength { to, .. } => Some(vec![alter(format!(
+                "ALTER COLUMN {} TYPE {}",
+                self.quote(&to.name),
+                self.column_type(&to.column_type)
+            ))]),
+            // Only widening types are converted implicitly.
+            Change::ChangeType { to, .. } => {
+                let column = self.quote(&to.name);
+                let column_type = self.column_type(&to.column_type);
+                let mut action = format!("ALTER COLUMN {} TYPE {}", column, column_type);
+                if change.is_lossy() {
+                    action.push_str(&format!(" USING {}::{}", column, column_type));
+                }
+                Some(vec![alter(action)])
+            }
+            Change::ChangeKeys { from, to, .. } => {
+                let mut statements = vec![];
+                if !from.is_empty() {
+                    let constraint = self.quote(&format!("{}_pkey", table));
+                    statements.push(alter(format!("DROP CONSTRAINT {}", constraint)));
+                }
+                if !to.is_empty() {
+                    statements.push(alter(format!("ADD PRIMARY KEY({})", quote_all(self, to))));
+                }
+                Some(statements)
+            }
+            _ => Some(alter_table(self, table, change)),
+        }
+    }
+}
+
+#[cfg(feature = "dialect-hana")]
+#[derive(Debug, Clone, Copy)]
+pub struct Hana;
+
+#[cfg(feature = "dialect-hana")]
+impl Dialect for Hana {
+    // `SYSUUID` is binary, so it is formatted like the lowercase UUID strings
+    // stored in `NVARCHAR(36)` columns.
+    fn uuid(&self) -> &'static str {
+        r"LOWER(REPLACE_REGEXPR('(.{8})(.{4})(.{4})(.{4})(.{12})' IN BINTOHEX(SYSUUID) WITH '\1-\2-\3-\4-\5'))"
+    }
+
+    fn now(&self) -> &'static str {
+        "CURRENT_UTCTIMESTAMP"
+    }
+
+    // An offset needs a limit, the largest one for all rows.
+    fn limit(&self, rows: Option<u64>, offset: u64) -> String {
+        Generic.limit(rows.or(Some(i32::MAX as u64)), offset)
+    }
+
+    // `UPSERT .. VALUES` takes a single row, more rows are selected from
+    // `DUMMY`. Both match the rows by their primary key.
+    fn upsert(
+        &self,
+        table: &str,
+        columns: &[String],
+        rows: &[Vec<String>],
+        _: &[String],
+    ) -> String {
+        if rows.len() <= 1 {
+            return format!("UPSERT {} WITH PRIMARY KEY", values(table, columns, rows));
+        }
+        let selects: Vec<String> = rows
+            .iter()
+            .map(|row| format!("SELECT {} FROM DUMMY", row.join(", ")))
+            .collect();
+        format!(
+            "UPSERT {} ({}) {}",
+            table,
+            columns.join(", "),
+            selects.join(" UNION ALL ")
+        )
+    }
+
+    // Columns are altered with their whole definition.
+    fn alter_table(&self, table: &str, change: &Change) -> Option<Vec<String>> {
+        let alter = |action: String| format!("ALTER TABLE {} {}", self.quote(table), action);
+        match change {
+            Change::AddColumn { column, .. } => {
+                Some(vec![alter(format!("ADD ({})", column.to_sql_for(self)))])
+            }
+            Change::RemoveColumn { column, .. } => {
+                Some(vec![alter(format!("DROP ({})", self.quote(&column.name)))])
+            }
+            Change::ChangeType { to, .. }
+            | Change::ChangeLength { to, .. }
+            | Change::ChangeDefault { to, .. } => {
+                Some(vec![alter(format!("ALTER ({})", to.to_sql_for(self)))])
+            }
+            _ => Some(alter_table(self, table, change)),
+        }
+    }
+}
+
+#[cfg(feature = "dialect-mysql")]
+#[derive(Debug, Clone, Copy)]
+pub struct MySql;
+
+// `||` is a logical or in MySQL.
+#[cfg(feature = "dialect-mysql")]
+impl Dialect for MySql {
+    fn quote(&self, identifier: &str) -> String {
+        format!("`{}`", identifier.replace('`', "``"))
+    }
+
+    fn uuid(&self) -> &'static str {
+        "UUID()"
+    }
+
+    fn now(&self) -> &'static str {
+        "UTC_TIMESTAMP()"
+    }
+
+    fn concat(&self, operands: &[String]) -> String {
+        format!("CONCAT({})", operands.join(", "))
+    }
+
+    fn nulls_order(&self) -> bool {
+        false
+    }
+
+    // `TINYINT` is signed unless declared otherwise.
+    fn column_type(&self, column_type: &str) -> String {
+        match column_type {
+            "NCLOB" => "LONGTEXT".to_string(),
+            "BLOB" => "LONGBLOB".to_string(),
+            "TINYINT" => "TINYINT UNSIGNED".to_string(),
+            _ => column_type.to_string(),
+        }
+    }
+
+    // An offset needs a limit, the largest one for all rows.
+    fn limit(&self, rows: Option<u64>, offset: u64) -> String {
+        Generic.limit(rows.or(Some(u64::MAX)), offset)
+    }
+
+    // Rows which only have keys are kept as they are.
+    fn upsert(
+        &self,
+        table: &str,
+        columns: &[String],
+        rows: &[Vec<String>],
+        keys: &[String],
+    ) -> String {
+        let mut updates: Vec<String> = columns
+            .iter()
+            .filter(|column| !keys.contains(column))
+            .map(|column| format!("{} = VALUES({})", column, column))
+            .collect();
+        if updates.is_empty() {
+            updates = columns
+                .iter()
+                .take(1)
+                .map(|column| format!("{} = {}", column, column))
+                .collect();
+        }
+        format!(
+            "INSERT INTO {} ON DUPLICATE KEY UPDATE {}",
+            values(table, columns, rows),
+            updates.join(", ")
+        )
+    }
+
+    fn alter_table(&self, table: &str, change: &Change) -> Option<Vec<String>> {
+        match change {
+            Change::ChangeType { to, .. } | Change::ChangeLength { to, .. } => Some(vec![format!(
+                "ALTER TABLE {} MODIFY COLUMN {}",
+                self.quote(table),
+                to.to_sql_for(self)
+            )]),
+            _ => Some(alter_table(self, table, change)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BinaryOp, Expr};
+    use crate::query::{OrderBy, CQN, SELECT, UPSERT};
+
+    fn get_test_select() -> SELECT {
+        SELECT::from("Books")
+            .column_as(
+                Expr::col("title").binary(BinaryOp::Concat, Expr::val(" (new)")),
+                "label",
+            )
+            .column_as(Expr::func("uuid", vec![]), "token")
+            .filter(
+                Expr::col("available")
+                    .eq(Expr::val(true))
+                    .and(Expr::col("modifiedAt").lt(Expr::col("$now"))),
+            )
+            .order_by(OrderBy::asc(Expr::col("title")))
+            .limit(10, 20)
+    }
+
+    #[test]
+    fn generic_to_sql() {
+        assert_eq!(
+            get_test_select().to_sql_for(&Generic).unwrap(),
+            get_test_select().to_sql().unwrap()
+        );
+    }
+
+    #[cfg(feature = "dialect-sqlite")]
+    #[test]
+    fn sqlite_to_statement() {
+        let statement = get_test_select().to_statement_for(&Sqlite).unwrap();
+        assert!(statement
+            .sql
+            .starts_with("SELECT \"title\" || ? as \"label\",lower(hex(randomblob(4)) || '-'"));
+        assert!(statement.sql.ends_with(
+            "FROM \"Books\"\n  WHERE \"available\" = 1 AND \"modifiedAt\" < strftime('%Y-%m-%dT%H:%M:%fZ', 'now')\n  ORDER BY \"title\" ASC\n  LIMIT 10 OFFSET 20"
+        ));
+        assert_eq!(statement.params.len(), 1);
+    }
+
+    #[cfg(feature = "dialect-postgres")]
+    #[test]
+    fn postgres_to_statement() {
+        let statement = get_test_select().to_statement_for(&Postgres).unwrap();
+        assert_eq!(
+            statement.sql,
+            "SELECT \"title\" || $1 as \"label\",gen_random_uuid() as \"token\" FROM \"Books\"\n  WHERE \"available\" = TRUE AND \"modifiedAt\" < CURRENT_TIMESTAMP\n  ORDER BY \"title\" ASC\n  LIMIT 10 OFFSET 20"
+        );
+    }
+
+    #[cfg(feature = "dialect-hana")]
+    #[test]
+    fn hana_to_sql() {
+        assert_eq!(
+            get_test_select().to_sql_for(&Hana).unwrap(),
+            r#"SELECT "title" || ' (new)' as "label",LOWER(REPLACE_REGEXPR('(.{8})(.{4})(.{4})(.{4})(.{12})' IN BINTOHEX(SYSUUID) WITH '\1-\2-\3-\4-\5')) as "token" FROM "Books"
+  WHERE "available" = TRUE AND "modifiedAt" < CURRENT_UTCTIMESTAMP
+  ORDER BY "title" ASC
+  LIMIT 10 OFFSET 20"#
+        );
+    }
+
+    #[cfg(feature = "dialect-mysql")]
+    #[test]
+    fn mysql_to_sql() {
+        assert_eq!(
+            get_test_select().to_sql_for(&MySql).unwrap(),
+            "SELECT CONCAT(`title`, ' (new)') as `label`,UUID() as `token` FROM `Books`\n  WHERE `available` = TRUE AND `modifiedAt` < UTC_TIMESTAMP()\n  ORDER BY `title` ASC\n  LIMIT 10 OFFSET 20"
+        );
+    }
+
+    #[test]
+    fn offset_to_sql() {
+        let select = SELECT::from("Books").offset(20);
+        assert!(select.to_sql().unwrap().ends_with("\n  OFFSET 20"));
+        #[cfg(feature = "dialect-sqlite")]
+        assert!(select
+            .to_sql_for(&Sqlite)
+            .unwrap()
+            .ends_with("\n  LIMIT -1 OFFSET 20"));
+        #[cfg(feature = "dialect-postgres")]
+        assert!(select
+            .to_sql_for(&Postgres)
+            .unwrap()
+            .ends_with("\n  OFFSET 20"));
+        #[cfg(feature = "dialect-hana")]
+        assert!(select
+            .to_sql_for(&Hana)
+            .unwrap()
+            .ends_with("\n  LIMIT 2147483647 OFFSET 20"));
+        #[cfg(feature = "dialect-mysql")]
+        assert!(select
+            .to_sql_for(&MySql)
+            .unwrap()
+            .ends_with("\n  LIMIT 18446744073709551615 OFFSET 20"));
+        assert_eq!(
+            SELECT::from("Books").limit(10, 0).offset(20).limit,
+            SELECT::from("Books").limit(10, 20).limit
+        );
+    }
+
+    #[test]
+    fn nulls_to_sql() {
+        let select = SELECT::from("Books").order_by(OrderBy::desc(Expr::col("price")).nulls_last());
+        assert!(select
+            .to_sql()
+            .unwrap()
+            .ends_with("ORDER BY price DESC NULLS LAST"));
+        #[cfg(feature = "dialect-mysql")]
+        assert!(select
+            .to_sql_for(&MySql)
+            .unwrap()
+            .ends_with("ORDER BY `price` IS NULL, `price` DESC"));
+    }
+
+    #[test]
+    fn upsert_to_sql() {
+        let upsert = UPSERT::into("Books")
+            .columns(vec!["ID", "stock"])
+            .values(vec![1.into(), 5.into()])
+            .keys(vec!["ID"]);
+        assert!(upsert
+            .to_sql()
+            .unwrap()
+            .ends_with("ON CONFLICT (ID) DO UPDATE SET stock = excluded.stock"));
+        #[cfg(feature = "dialect-postgres")]
+        assert_eq!(
+            upsert.to_sql_for(&Postgres).unwrap(),
+            "INSERT INTO \"Books\" (\"ID\", \"stock\") VALUES (1, 5) ON CONFLICT (\"ID\") DO UPDATE SET \"stock\" = excluded.\"stock\""
+        );
+        #[cfg(feature = "dialect-hana")]
+        assert_eq!(
+            upsert.to_sql_for(&Hana).unwrap(),
+            "UPSERT \"Books\" (\"ID\", \"stock\") VALUES (1, 5) WITH PRIMARY KEY"
+        );
+        #[cfg(feature = "dialect-hana")]
+        assert_eq!(
+            upsert
+                .clone()
+                .values(vec![2.into(), 7.into()])
+                .to_statement_for(&Hana)
+                .unwrap()
+                .sql,
+            "UPSERT \"Books\" (\"ID\", \"stock\") SELECT 1, 5 FROM DUMMY UNION ALL SELECT 2, 7 FROM DUMMY"
+        );
+        #[cfg(feature = "dialect-mysql")]
+        assert_eq!(
+            upsert.to_sql_for(&MySql).unwrap(),
+            "INSERT INTO `Books` (`ID`, `stock`) VALUES (1, 5) ON DUPLICATE KEY UPDATE `stock` = VALUES(`stock`)"
+        );
+        let keys_only = UPSERT::into("Books")
+            .columns(vec!["ID"])
+            .values(vec![1.into()])
+            .keys(vec!["ID"]);
+        assert_eq!(
+            keys_only.to_sql().unwrap(),
+            "INSERT INTO Books (ID) VALUES (1) ON CONFLICT (ID) DO NOTHING"
+        );
+    }
+}
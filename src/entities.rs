@@ -1,117 +1,560 @@
-use serde::{Deserialize, Serialize};
+use crate::annotations::Annotations;
+use crate::csn::{self, RawCsn, RawNode};
+use crate::query::SELECT;
+use crate::resolve::QueryErrorKind;
+use crate::values::{Date, DateTime, Decimal, Time, Timestamp};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Deserialize, Serialize, Debug)]
+// `Definitions` are (de)serialized as a CSN document, see `csn.rs`.
+#[derive(Debug, Clone)]
 pub struct Definitions {
-    definitions: Vec<Definition>,
+    pub definitions: Vec<Definition>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Service {
     pub name: String,
+    #[serde(default)]
+    pub annotations: Annotations,
+    // Unbound actions and functions.
+    #[serde(default)]
+    pub actions: Vec<Action>,
+    #[serde(default)]
+    pub events: Vec<Event>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Entity {
     pub name: String,
     pub elements: Vec<Element>,
+    #[serde(default)]
+    pub annotations: Annotations,
+    // Set for views and projections.
+    #[serde(skip)]
+    pub query: Option<SELECT>,
+    // Bound actions and functions.
+    #[serde(default)]
+    pub actions: Vec<Action>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Element {
     pub name: String,
     pub key: bool,
     pub kind: ElementKind,
+    // The declared type if it is not a built-in one, e.g. `my.Currency`.
+    #[serde(default)]
+    pub type_name: Option<String>,
+    #[serde(default)]
+    pub annotations: Annotations,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(tag = "type")]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum ActionKind {
+    #[serde(rename = "action")]
+    Action,
+    #[serde(rename = "function")]
+    Function,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Action {
+    pub name: String,
+    pub kind: ActionKind,
+    pub params: Vec<Parameter>,
+    pub returns: Option<Returns>,
+    #[serde(default)]
+    pub annotations: Annotations,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub many: bool,
+    pub kind: ParameterKind,
+    #[serde(default)]
+    pub type_name: Option<String>,
+    #[serde(default)]
+    pub annotations: Annotations,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Returns {
+    pub many: bool,
+    pub kind: ParameterKind,
+    #[serde(default)]
+    pub type_name: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub enum ParameterKind {
+    Scalar(ElementKind),
+    Entity(String),
+    Structured(Vec<Element>),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Event {
+    pub name: String,
+    pub elements: Vec<Element>,
+    #[serde(default)]
+    pub annotations: Annotations,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TypeDefinition {
+    pub name: String,
+    pub kind: ElementKind,
+    #[serde(default)]
+    pub type_name: Option<String>,
+    #[serde(default)]
+    pub annotations: Annotations,
+}
+
+// Element kinds are (de)serialized like CSN elements, e.g.
+// `{"type": "cds.String", "length": 100}`.
+#[derive(Debug, Clone)]
 pub enum ElementKind {
-    #[serde(rename = "cds.UUID")]
     UUID(PrimitiveKind<String>),
-    #[serde(rename = "cds.Boolean")]
     Boolean(PrimitiveKind<bool>),
-    #[serde(rename = "cds.Integer")]
     Integer(PrimitiveKind<i64>),
-    #[serde(rename = "cds.String")]
     String(PrimitiveKindString),
+    LargeString(PrimitiveKind<String>),
+    Decimal(PrimitiveKindDecimal),
+    Double(PrimitiveKind<f64>),
+    Int16(PrimitiveKind<i16>),
+    Int32(PrimitiveKind<i32>),
+    Int64(PrimitiveKind<i64>),
+    UInt8(PrimitiveKind<u8>),
+    Date(PrimitiveKind<Date>),
+    Time(PrimitiveKind<Time>),
+    DateTime(PrimitiveKind<DateTime>),
+    Timestamp(PrimitiveKind<Timestamp>),
+    Binary(PrimitiveKindBinary),
+    LargeBinary(PrimitiveKind<String>),
+    Association(AssociationKind),
+    Composition(AssociationKind),
+    Structured(StructuredKind),
+    Array(ArrayKind),
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+impl ElementKind {
+    // The name of the built-in type, e.g. `cds.String`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ElementKind::UUID(_) => "cds.UUID",
+            ElementKind::Boolean(_) => "cds.Boolean",
+            ElementKind::Integer(_) => "cds.Integer",
+            ElementKind::String(_) => "cds.String",
+            ElementKind::LargeString(_) => "cds.LargeString",
+            ElementKind::Decimal(_) => "cds.Decimal",
+            ElementKind::Double(_) => "cds.Double",
+            ElementKind::Int16(_) => "cds.Int16",
+            ElementKind::Int32(_) => "cds.Int32",
+            ElementKind::Int64(_) => "cds.Int64",
+            ElementKind::UInt8(_) => "cds.UInt8",
+            ElementKind::Date(_) => "cds.Date",
+            ElementKind::Time(_) => "cds.Time",
+            ElementKind::DateTime(_) => "cds.DateTime",
+            ElementKind::Timestamp(_) => "cds.Timestamp",
+            ElementKind::Binary(_) => "cds.Binary",
+            ElementKind::LargeBinary(_) => "cds.LargeBinary",
+            ElementKind::Association(_) => "cds.Association",
+            ElementKind::Composition(_) => "cds.Composition",
+            ElementKind::Structured(_) => "cds.Structured",
+            ElementKind::Array(_) => "cds.Array",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct PrimitiveKind<T> {
     pub default: Option<Default<T>>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct PrimitiveKindString {
     pub default: Option<Default<String>>,
     pub length: Option<u64>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PrimitiveKindDecimal {
+    pub default: Option<Default<Decimal>>,
+    pub precision: Option<u32>,
+    pub scale: Option<u32>,
+}
+
+// Binary defaults are kept in their CSN representation (base64).
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PrimitiveKindBinary {
+    pub default: Option<Default<String>>,
+    pub length: Option<u64>,
+}
+
+// Structured types and elements, e.g. `address : { street : String }`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct StructuredKind {
+    pub elements: Vec<Element>,
+}
+
+// Arrayed types and elements, e.g. `tags : many String`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ArrayKind {
+    pub items: Box<ElementKind>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AssociationKind {
+    pub target: String,
+    pub cardinality: Option<Cardinality>,
+    pub keys: Option<Vec<ForeignKey>>,
+    pub on: Option<Vec<OnToken>>,
+}
+
+impl AssociationKind {
+    pub fn is_managed(&self) -> bool {
+        self.on.is_none()
+    }
+
+    pub fn is_to_many(&self) -> bool {
+        match &self.cardinality {
+            Some(cardinality) => cardinality.max == Some(CardinalityBound::Many),
+            None => false,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Cardinality {
+    pub src: Option<CardinalityBound>,
+    pub min: Option<u64>,
+    pub max: Option<CardinalityBound>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CardinalityBound {
+    Finite(u64),
+    Many,
+}
+
+impl Serialize for CardinalityBound {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            CardinalityBound::Finite(n) => serializer.serialize_u64(*n),
+            CardinalityBound::Many => serializer.serialize_str("*"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CardinalityBound {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BoundVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for BoundVisitor {
+            type Value = CardinalityBound;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a non-negative number or \"*\"")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<CardinalityBound, E> {
+                Ok(CardinalityBound::Finite(v))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<CardinalityBound, E> {
+                match v {
+                    "*" => Ok(CardinalityBound::Many),
+                    _ => Err(E::invalid_value(serde::de::Unexpected::Str(v), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(BoundVisitor)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ForeignKey {
+    #[serde(rename = "ref")]
+    pub reference: Vec<String>,
+    #[serde(rename = "as")]
+    pub alias: Option<String>,
+}
+
+impl ForeignKey {
+    pub fn name(&self) -> String {
+        match &self.alias {
+            Some(alias) => alias.clone(),
+            None => self.reference.join("_"),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum OnToken {
+    Ref {
+        #[serde(rename = "ref")]
+        reference: Vec<String>,
+    },
+    Val {
+        val: serde_json::Value,
+    },
+    Xpr {
+        xpr: Vec<OnToken>,
+    },
+    Operator(String),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub enum Default<T> {
     // TODO: other possibilities
     #[serde(rename = "val")]
     Val(T),
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(tag = "kind")]
 pub enum Definition {
     Service(Service),
     Entity(Entity),
+    Type(TypeDefinition),
+    // Actions and events which do not belong to a service.
+    Action(Action),
+    Event(Event),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    InvalidJson,
+    InvalidValue,
+    MissingDefinitions,
+    MissingElements,
+    UnknownType,
+    InvalidType,
+    CyclicReference,
+    // A problem of the query of a view.
+    InvalidQuery(QueryErrorKind),
+}
+
+// A problem found while loading a CSN document. `path` points to the
+// offending property, like `definitions."my.Books".elements.price.type`.
+// Loading continues after most problems, these are collected in `additional`.
+#[derive(Debug, Clone, PartialEq)]
 pub struct DeserializationError {
-    description: String,
+    pub kind: ErrorKind,
+    pub path: String,
+    pub description: String,
+    pub additional: Vec<DeserializationError>,
 }
 
 impl DeserializationError {
-    pub fn new(description: &str) -> DeserializationError {
+    pub fn new(kind: ErrorKind, description: &str) -> DeserializationError {
         DeserializationError {
+            kind,
+            path: String::new(),
             description: description.to_string(),
+            additional: vec![],
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    // All problems, starting with this one.
+    pub fn problems(&self) -> impl Iterator<Item = &DeserializationError> {
+        std::iter::once(self).chain(self.additional.iter())
+    }
+
+    // Prefixes the path with the path of the enclosing property. Paths which
+    // already start at the document root are kept.
+    pub(crate) fn at(mut self, prefix: &str) -> DeserializationError {
+        let rooted = self.path == "definitions"
+            || self.path.starts_with("definitions.")
+            || self.path.starts_with("definitions[");
+        if !rooted {
+            self.path = match self.path.chars().next() {
+                None => prefix.to_string(),
+                Some('[') => format!("{}{}", prefix, self.path),
+                Some(_) => format!("{}.{}", prefix, self.path),
+            };
+        }
+        self.additional = self
+            .additional
+            .into_iter()
+            .map(|err| err.at(prefix))
+            .collect();
+        self
+    }
+
+    pub(crate) fn merge(errors: Vec<DeserializationError>) -> Option<DeserializationError> {
+        let mut problems = errors.into_iter().flat_map(|mut err| {
+            let additional = std::mem::take(&mut err.additional);
+            std::iter::once(err).chain(additional)
+        });
+        let mut first = problems.next()?;
+        first.additional = problems.collect();
+        Some(first)
+    }
+}
+
+impl fmt::Display for DeserializationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.description)?;
+        } else {
+            write!(f, "{}: {}", self.path, self.description)?;
+        }
+        match self.additional.len() {
+            0 => Ok(()),
+            1 => write!(f, " (and 1 more problem)"),
+            n => write!(f, " (and {} more problems)", n),
         }
     }
 }
 
+impl std::error::Error for DeserializationError {}
+
 impl From<serde_json::error::Error> for DeserializationError {
     fn from(err: serde_json::error::Error) -> DeserializationError {
-        DeserializationError::new(&err.to_string())
+        let kind = match err.classify() {
+            serde_json::error::Category::Data => ErrorKind::InvalidValue,
+            _ => ErrorKind::InvalidJson,
+        };
+        DeserializationError::new(kind, &err.to_string())
     }
 }
 
+// Formats property names as a JSON path, quoting names which are not plain
+// identifiers.
+pub(crate) fn json_path(names: &[&str]) -> String {
+    let plain = |name: &str| {
+        name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+    };
+    let segments: Vec<String> = names
+        .iter()
+        .map(|name| match plain(name) {
+            true => name.to_string(),
+            false => serde_json::to_string(name).unwrap(),
+        })
+        .collect();
+    segments.join(".")
+}
+
 impl Definitions {
-    pub fn from_str(csn: &str) -> Result<Definitions, DeserializationError> {
-        let mut definitions = vec![];
-        let csn_json: serde_json::value::Value = serde_json::from_str(csn)?;
-        let map = csn_json["definitions"]
-            .as_object()
-            .ok_or(DeserializationError {
-                description: "Cannot find definitions".to_string(),
-            })?;
-        for (key, val) in map {
-            if val["kind"] == "service" {
-                definitions.push(Definition::Service(Service { name: key.clone() }));
-            } else if val["kind"] == "entity" {
-                let mut elements: Vec<Element> = vec![];
-                for (el_key, el_val) in val["elements"].as_object().ok_or(DeserializationError {
-                    description: "Cannot find elements".to_string(),
-                })? {
-                    let el_val_str = &el_val.to_string();
-                    let element_kind: ElementKind = serde_json::from_str(el_val_str)?;
-                    let element = Element {
-                        name: el_key.to_string(),
-                        key: el_val["key"] == true,
-                        kind: element_kind,
-                    };
-                    elements.push(element);
+    pub fn definitions(&self) -> &[Definition] {
+        &self.definitions
+    }
+
+    // The definitions as a CSN document, which `from_str` reads back.
+    pub fn to_csn(&self) -> String {
+        csn::write(self)
+    }
+
+    pub fn service(&self, name: &str) -> Option<&Service> {
+        self.definitions
+            .iter()
+            .find_map(|definition| match definition {
+                Definition::Service(service) if service.name == name => Some(service),
+                _ => None,
+            })
+    }
+
+    pub fn entity(&self, name: &str) -> Option<&Entity> {
+        self.definitions
+            .iter()
+            .find_map(|definition| match definition {
+                Definition::Entity(entity) if entity.name == name => Some(entity),
+                _ => None,
+            })
+    }
+
+    pub fn type_definition(&self, name: &str) -> Option<&TypeDefinition> {
+        self.definitions
+            .iter()
+            .find_map(|definition| match definition {
+                Definition::Type(type_definition) if type_definition.name == name => {
+                    Some(type_definition)
                 }
-                definitions.push(Definition::Entity(Entity {
-                    name: key.clone(),
-                    elements: elements,
-                }))
-            }
-        }
-        Ok(Definitions { definitions })
+                _ => None,
+            })
+    }
+
+    pub fn event(&self, name: &str) -> Option<&Event> {
+        self.definitions
+            .iter()
+            .find_map(|definition| match definition {
+                Definition::Service(service) => service.events.iter().find(|e| e.name == name),
+                Definition::Event(event) if event.name == name => Some(event),
+                _ => None,
+            })
+    }
+
+    pub fn action(&self, name: &str) -> Option<&Action> {
+        self.definitions
+            .iter()
+            .find_map(|definition| match definition {
+                Definition::Service(service) => service.actions.iter().find(|a| a.name == name),
+                Definition::Action(action) if action.name == name => Some(action),
+                _ => None,
+            })
+    }
+}
+
+impl Entity {
+    pub fn element(&self, name: &str) -> Option<&Element> {
+        self.elements.iter().find(|element| element.name == name)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Element> {
+        self.elements.iter().filter(|element| element.key)
+    }
+
+    pub fn action(&self, name: &str) -> Option<&Action> {
+        self.actions.iter().find(|action| action.name == name)
+    }
+}
+
+impl FromStr for Definitions {
+    type Err = DeserializationError;
+
+    fn from_str(csn: &str) -> Result<Definitions, DeserializationError> {
+        let raw: RawCsn = serde_json::from_str(csn).map_err(|err| csn::json_error(csn, err))?;
+        csn::load(&raw)
+    }
+}
+
+impl Serialize for Definitions {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        csn::definitions_to_csn(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Definitions {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Definitions, D::Error> {
+        let csn = RawCsn::deserialize(deserializer)?;
+        csn::load(&csn).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for ElementKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        csn::element_kind_to_csn(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ElementKind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ElementKind, D::Error> {
+        let node = RawNode::deserialize(deserializer)?;
+        csn::element_kind_of(&node).map_err(D::Error::custom)
     }
 }
 
@@ -119,6 +562,7 @@ impl Definitions {
 mod tests {
 
     use super::*;
+    use crate::expr::Expr;
 
     fn get_test_csn() -> &'static str {
         r#"{"definitions": {
@@ -127,6 +571,8 @@ mod tests {
               "kind": "service"
             },
             "TestService.TestEntity": {
+              "@readonly": true,
+              "@UI.LineItem#short": [{ "Value": { "=": "name" } }],
               "kind": "entity",
               "elements": {
                 "ID": {
@@ -134,6 +580,8 @@ mod tests {
                   "type": "cds.UUID"
                 },
                 "name": {
+                  "@title": "Name",
+                  "@assert.format": "[a-z]+",
                   "type": "cds.String",
                   "default": {
                     "val": "myDefaultName"
@@ -141,6 +589,17 @@ mod tests {
                 },
                 "age": {
                   "type": "cds.Integer"
+                },
+                "parent": {
+                  "type": "cds.Association",
+                  "target": "TestService.TestEntity",
+                  "keys": [{ "ref": ["ID"] }]
+                },
+                "children": {
+                  "type": "cds.Composition",
+                  "cardinality": { "max": "*" },
+                  "target": "TestService.TestEntity",
+                  "on": [{ "ref": ["children", "parent"] }, "=", { "ref": ["$self"] }]
                 }
               }
             }
@@ -187,7 +646,7 @@ mod tests {
         let deserialized: ElementKind = serde_json::from_str(input_str).unwrap();
 
         match deserialized {
-            ElementKind::UUID(a) => assert_eq!(a.default.is_none(), true),
+            ElementKind::UUID(a) => assert!(a.default.is_none()),
             _ => panic!("Could not deserialize"),
         }
     }
@@ -220,10 +679,691 @@ mod tests {
         }
     }
 
+    #[test]
+    fn deserialize_decimal_with_facets() {
+        let input_str =
+            r#"{"type": "cds.Decimal", "precision": 9, "scale": 2, "default": { "val": 9.99 }}"#;
+        let deserialized: ElementKind = serde_json::from_str(input_str).unwrap();
+
+        match deserialized {
+            ElementKind::Decimal(a) => {
+                assert_eq!(a.precision, Some(9));
+                assert_eq!(a.scale, Some(2));
+                match a.default {
+                    Some(Default::Val(default)) => assert_eq!(default.as_str(), "9.99"),
+                    _ => panic!("Could not deserialize default"),
+                }
+            }
+            _ => panic!("Could not deserialize"),
+        }
+    }
+
+    #[test]
+    fn deserialize_date_default() {
+        let input_str = r#"{"type": "cds.Date", "default": { "val": "2020-02-29" }}"#;
+        let deserialized: ElementKind = serde_json::from_str(input_str).unwrap();
+
+        match deserialized {
+            ElementKind::Date(a) => match a.default {
+                Some(Default::Val(default)) => assert_eq!(default, Date::new(2020, 2, 29).unwrap()),
+                _ => panic!("Could not deserialize default"),
+            },
+            _ => panic!("Could not deserialize"),
+        }
+
+        let invalid = r#"{"type": "cds.Date", "default": { "val": "2020-02-30" }}"#;
+        assert!(serde_json::from_str::<ElementKind>(invalid).is_err());
+    }
+
+    #[test]
+    fn deserialize_scalar_types() {
+        let types = [
+            "cds.LargeString",
+            "cds.Double",
+            "cds.Int16",
+            "cds.Int32",
+            "cds.Int64",
+            "cds.UInt8",
+            "cds.Time",
+            "cds.DateTime",
+            "cds.Timestamp",
+            "cds.Binary",
+            "cds.LargeBinary",
+        ];
+        for ty in types.iter() {
+            let input_str = format!(r#"{{"type": "{}"}}"#, ty);
+            assert!(
+                serde_json::from_str::<ElementKind>(&input_str).is_ok(),
+                "{}",
+                ty
+            );
+        }
+
+        let out_of_range = r#"{"type": "cds.UInt8", "default": { "val": 256 }}"#;
+        assert!(serde_json::from_str::<ElementKind>(out_of_range).is_err());
+    }
+
+    #[test]
+    fn deserialize_managed_association() {
+        let input_str = r#"{
+            "type": "cds.Association",
+            "target": "TestService.Authors",
+            "keys": [{ "ref": ["ID"] }]
+        }"#;
+        let deserialized: ElementKind = serde_json::from_str(input_str).unwrap();
+
+        match deserialized {
+            ElementKind::Association(a) => {
+                assert_eq!(a.target, "TestService.Authors");
+                assert!(a.is_managed());
+                assert!(!a.is_to_many());
+                let keys = a.keys.unwrap();
+                assert_eq!(keys.len(), 1);
+                assert_eq!(keys[0].name(), "ID");
+            }
+            _ => panic!("Could not deserialize"),
+        }
+    }
+
+    #[test]
+    fn deserialize_unmanaged_composition() {
+        let input_str = r#"{
+            "type": "cds.Composition",
+            "cardinality": { "max": "*" },
+            "target": "TestService.Books",
+            "on": [{ "ref": ["books", "author"] }, "=", { "ref": ["$self"] }]
+        }"#;
+        let deserialized: ElementKind = serde_json::from_str(input_str).unwrap();
+
+        match deserialized {
+            ElementKind::Composition(a) => {
+                assert_eq!(a.target, "TestService.Books");
+                assert!(!a.is_managed());
+                assert!(a.is_to_many());
+                match a.on.unwrap().as_slice() {
+                    [OnToken::Ref { reference: lhs }, OnToken::Operator(op), OnToken::Ref { reference: rhs }] =>
+                    {
+                        assert_eq!(lhs, &vec!["books", "author"]);
+                        assert_eq!(op, "=");
+                        assert_eq!(rhs, &vec!["$self"]);
+                    }
+                    _ => panic!("Could not deserialize on condition"),
+                }
+            }
+            _ => panic!("Could not deserialize"),
+        }
+    }
+
+    #[test]
+    fn deserialize_cardinality() {
+        let input_str = r#"{ "src": 1, "min": 0, "max": 1 }"#;
+        let deserialized: Cardinality = serde_json::from_str(input_str).unwrap();
+        assert_eq!(deserialized.src, Some(CardinalityBound::Finite(1)));
+        assert_eq!(deserialized.min, Some(0));
+        assert_eq!(deserialized.max, Some(CardinalityBound::Finite(1)));
+        assert!(serde_json::from_str::<Cardinality>(r#"{ "max": "n" }"#).is_err());
+    }
+
     #[test]
     fn test_get_csn() {
         let csn = get_test_csn();
-        assert_eq!(Definitions::from_str(csn).is_ok(), true);
+        assert!(Definitions::from_str(csn).is_ok());
+    }
+
+    #[test]
+    fn test_deserialize_csn() {
+        let csn = get_test_csn();
+        let definitions: Definitions = serde_json::from_str(csn).unwrap();
+        assert_eq!(definitions.definitions.len(), 2);
+        let res: Result<Definitions, _> = serde_json::from_str(get_test_csn_no_elements());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "definitions.\"TestService.TestEntity\": Cannot find elements"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_csn_owned() {
+        // A `Value` does not keep the order of properties.
+        for csn in [get_test_csn(), get_test_csn_views()] {
+            let expected = serde_json::to_value(Definitions::from_str(csn).unwrap()).unwrap();
+            let from_reader: Definitions = serde_json::from_reader(csn.as_bytes()).unwrap();
+            assert_eq!(serde_json::to_value(from_reader).unwrap(), expected);
+            let value: serde_json::Value = serde_json::from_str(csn).unwrap();
+            let from_value: Definitions = serde_json::from_value(value).unwrap();
+            assert_eq!(serde_json::to_value(from_value).unwrap(), expected);
+        }
+        let kind = serde_json::json!({ "type": "cds.String", "default": { "val": "x" } });
+        let kind: ElementKind = serde_json::from_value(kind).unwrap();
+        assert!(matches!(kind, ElementKind::String(s) if s.default.is_some()));
+    }
+
+    #[test]
+    fn test_serialize_csn() {
+        let definitions = Definitions::from_str(get_test_csn()).unwrap();
+        let csn = serde_json::to_string(&definitions).unwrap();
+        let parsed = Definitions::from_str(&csn).unwrap();
+        assert_eq!(parsed.definitions.len(), definitions.definitions.len());
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), csn);
+
+        let kind = ElementKind::String(PrimitiveKindString {
+            default: None,
+            length: Some(255),
+        });
+        assert_eq!(
+            serde_json::to_string(&kind).unwrap(),
+            r#"{"type":"cds.String","length":255}"#
+        );
+        let parsed: ElementKind = serde_json::to_string(&kind)
+            .and_then(|json| serde_json::from_str(&json))
+            .unwrap();
+        assert!(matches!(parsed, ElementKind::String(s) if s.length == Some(255)));
+    }
+
+    #[test]
+    fn test_get_csn_annotations() {
+        let csn = get_test_csn();
+        let definitions = Definitions::from_str(csn).unwrap();
+        let service = definitions.service("TestService").unwrap();
+        assert_eq!(
+            service.annotations.get("@source").and_then(|v| v.as_str()),
+            Some("srv/service.cds")
+        );
+        let entity = definitions.entity("TestService.TestEntity").unwrap();
+        assert!(entity.annotations.is_true("@readonly"));
+        assert!(entity
+            .annotations
+            .get_qualified("@UI.LineItem", Some("short"))
+            .is_some());
+        let name = entity.element("name").unwrap();
+        assert_eq!(name.annotations.len(), 2);
+        assert_eq!(
+            name.annotations.get("@title").and_then(|v| v.as_str()),
+            Some("Name")
+        );
+    }
+
+    fn get_test_csn_types() -> &'static str {
+        r#"{"definitions": {
+            "my.Name": {
+              "@title": "Name",
+              "kind": "type",
+              "type": "cds.String",
+              "length": 100
+            },
+            "my.ShortName": {
+              "kind": "type",
+              "type": "my.Name",
+              "length": 10
+            },
+            "my.Address": {
+              "kind": "type",
+              "elements": { "street": { "type": "cds.String" } }
+            },
+            "my.Books": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "title": { "type": "my.ShortName", "@title": "Title" },
+                "price": { "type": "cds.Decimal", "precision": 9, "scale": 2 }
+              }
+            },
+            "my.Orders": {
+              "kind": "entity",
+              "elements": {
+                "book_ID": { "type": "my.Books:ID" },
+                "amount": { "type": "my.Books:price" }
+              }
+            }
+          }}"#
+    }
+
+    #[test]
+    fn test_get_csn_types() {
+        let csn = get_test_csn_types();
+        let definitions = Definitions::from_str(csn).unwrap();
+
+        let short_name = definitions.type_definition("my.ShortName").unwrap();
+        assert_eq!(short_name.type_name.as_deref(), Some("my.Name"));
+        assert_eq!(
+            short_name
+                .annotations
+                .get("@title")
+                .and_then(|v| v.as_str()),
+            Some("Name")
+        );
+        match &short_name.kind {
+            ElementKind::String(a) => assert_eq!(a.length, Some(10)),
+            _ => panic!("Could not resolve type"),
+        }
+        assert!(matches!(
+            definitions.type_definition("my.Address").unwrap().kind,
+            ElementKind::Structured(_)
+        ));
+
+        let title = definitions
+            .entity("my.Books")
+            .unwrap()
+            .element("title")
+            .unwrap();
+        assert_eq!(title.type_name.as_deref(), Some("my.ShortName"));
+        assert_eq!(
+            title.annotations.get("@title").and_then(|v| v.as_str()),
+            Some("Title")
+        );
+        match &title.kind {
+            ElementKind::String(a) => assert_eq!(a.length, Some(10)),
+            _ => panic!("Could not resolve type"),
+        }
+
+        let orders = definitions.entity("my.Orders").unwrap();
+        let book_id = orders.element("book_ID").unwrap();
+        assert!(!book_id.key);
+        assert!(matches!(book_id.kind, ElementKind::Integer(_)));
+        match &orders.element("amount").unwrap().kind {
+            ElementKind::Decimal(a) => assert_eq!((a.precision, a.scale), (Some(9), Some(2))),
+            _ => panic!("Could not resolve type"),
+        }
+    }
+
+    #[test]
+    fn test_get_csn_unknown_type() {
+        let csn = r#"{"definitions": {
+            "my.Books": {
+              "kind": "entity",
+              "elements": { "price": { "type": "my.Currency" } }
+            }
+          }}"#;
+        let res = Definitions::from_str(csn);
+        match res {
+            Ok(_) => assert_eq!(1, 0),
+            Err(e) => assert_eq!(e.description, "Unknown type my.Currency"),
+        }
+
+        let csn = r#"{"definitions": {
+            "my.Books": {
+              "kind": "entity",
+              "elements": { "address": { "type": "my.Address" } }
+            },
+            "my.Address": { "kind": "type" }
+          }}"#;
+        let e = Definitions::from_str(csn).unwrap_err();
+        let problems: Vec<&str> = e.problems().map(|p| p.description()).collect();
+        assert_eq!(
+            problems,
+            vec!["Cannot find type of my.Address", "Cannot find type"]
+        );
+    }
+
+    #[test]
+    fn test_get_csn_diagnostics() {
+        let csn = r#"{"definitions": {
+            "my.Books": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "type": "cds.Integer", "default": { "val": "one" } },
+                "price": { "type": "my.Currency" }
+              }
+            },
+            "my.Authors": { "kind": "entity" },
+            "my.View": {
+              "kind": "entity",
+              "projection": { "from": { "ref": ["my.Bookz"] } }
+            }
+          }}"#;
+        let e = Definitions::from_str(csn).unwrap_err();
+        let problems: Vec<(ErrorKind, &str)> =
+            e.problems().map(|p| (p.kind, p.path.as_str())).collect();
+        assert_eq!(
+            problems,
+            vec![
+                (
+                    ErrorKind::InvalidValue,
+                    "definitions.\"my.Books\".elements.ID.default"
+                ),
+                (
+                    ErrorKind::UnknownType,
+                    "definitions.\"my.Books\".elements.price.type"
+                ),
+                (ErrorKind::MissingElements, "definitions.\"my.Authors\""),
+                (
+                    ErrorKind::InvalidQuery(QueryErrorKind::UnknownEntity),
+                    "definitions.\"my.View\".projection.from"
+                ),
+            ]
+        );
+        assert_eq!(
+            e.additional[0].to_string(),
+            "definitions.\"my.Books\".elements.price.type: Unknown type my.Currency"
+        );
+        assert!(e.to_string().ends_with("(and 3 more problems)"));
+        let source: &dyn std::error::Error = &e;
+        assert!(source.source().is_none());
+    }
+
+    #[test]
+    fn test_get_csn_cyclic_types() {
+        let csn = r#"{"definitions": {
+            "my.A": { "kind": "type", "type": "my.B" },
+            "my.B": { "kind": "type", "type": "my.A" }
+          }}"#;
+        let res = Definitions::from_str(csn);
+        match res {
+            Ok(_) => assert_eq!(1, 0),
+            Err(e) => assert_eq!(e.description, "Cyclic type reference my.A -> my.B -> my.A"),
+        }
+    }
+
+    #[test]
+    fn test_get_csn_structured_types() {
+        let csn = r#"{"definitions": {
+            "my.Address": {
+              "kind": "type",
+              "elements": { "street": { "type": "cds.String", "length": 60 }, "city": { "type": "cds.String" } }
+            },
+            "my.Tags": { "kind": "type", "items": { "type": "cds.String", "length": 10 } },
+            "my.Authors": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "address": { "type": "my.Address" },
+                "geo": { "elements": { "lat": { "type": "cds.Double" }, "lon": { "type": "cds.Double" } } },
+                "tags": { "type": "my.Tags" },
+                "emails": { "items": { "type": "cds.String" } }
+              }
+            }
+          }}"#;
+        let definitions = Definitions::from_str(csn).unwrap();
+        match &definitions.type_definition("my.Address").unwrap().kind {
+            ElementKind::Structured(structured) => assert_eq!(structured.elements.len(), 2),
+            _ => panic!("Expected a structured type"),
+        }
+        let authors = definitions.entity("my.Authors").unwrap();
+        let address = authors.element("address").unwrap();
+        assert_eq!(address.type_name.as_deref(), Some("my.Address"));
+        match &address.kind {
+            ElementKind::Structured(structured) => {
+                assert_eq!(structured.elements[0].name, "street");
+                assert!(matches!(
+                    structured.elements[0].kind,
+                    ElementKind::String(PrimitiveKindString {
+                        length: Some(60),
+                        ..
+                    })
+                ));
+            }
+            _ => panic!("Expected a structured element"),
+        }
+        assert!(matches!(
+            authors.element("geo").unwrap().kind,
+            ElementKind::Structured(_)
+        ));
+        for name in ["tags", "emails"] {
+            match &authors.element(name).unwrap().kind {
+                ElementKind::Array(array) => {
+                    assert!(matches!(*array.items, ElementKind::String(_)))
+                }
+                _ => panic!("Expected an arrayed element"),
+            }
+        }
+
+        let csn = r#"{"definitions": {
+            "my.Node": {
+              "kind": "type",
+              "elements": { "children": { "items": { "type": "my.Node" } } }
+            }
+          }}"#;
+        let e = Definitions::from_str(csn).unwrap_err();
+        assert_eq!(e.description, "Cyclic structured type my.Node -> my.Node");
+        assert_eq!(e.path, r#"definitions."my.Node".elements.children.items"#);
+    }
+
+    fn get_test_csn_views() -> &'static str {
+        r#"{"definitions": {
+            "my.Books": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "title": { "type": "cds.String", "length": 111 },
+                "stock": { "type": "cds.Integer" },
+                "author": {
+                  "type": "cds.Association",
+                  "target": "my.Authors",
+                  "keys": [{ "ref": ["ID"] }]
+                }
+              }
+            },
+            "my.Authors": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "name": { "type": "cds.String" }
+              }
+            },
+            "CatalogService.Books": {
+              "kind": "entity",
+              "projection": { "from": { "ref": ["my.Books"] }, "excluding": ["stock"] }
+            },
+            "CatalogService.CheapBooks": {
+              "@readonly": true,
+              "kind": "entity",
+              "projection": {
+                "from": { "ref": ["CatalogService.Books"] },
+                "where": [{ "ref": ["ID"] }, "<", { "val": 10 }]
+              }
+            },
+            "CatalogService.ListOfBooks": {
+              "kind": "entity",
+              "query": {
+                "SELECT": {
+                  "from": { "ref": ["my.Books"] },
+                  "columns": [
+                    { "ref": ["ID"] },
+                    { "ref": ["title"], "as": "name" },
+                    { "ref": ["author", "name"], "as": "author" },
+                    { "val": 1, "as": "one", "cast": { "type": "cds.Integer" } }
+                  ]
+                }
+              }
+            },
+            "CatalogService.Authors": {
+              "kind": "entity",
+              "projection": { "from": { "ref": ["my.Authors"] } },
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" }
+              }
+            }
+          }}"#
+    }
+
+    #[test]
+    fn test_get_csn_views() {
+        let csn = get_test_csn_views();
+        let definitions = Definitions::from_str(csn).unwrap();
+        let names = |entity: &Entity| -> Vec<String> {
+            entity.elements.iter().map(|e| e.name.clone()).collect()
+        };
+
+        let books = definitions.entity("CatalogService.Books").unwrap();
+        assert_eq!(names(books), vec!["ID", "title", "author"]);
+        assert_eq!(books.query.as_ref().unwrap().from, "my.Books");
+        assert_eq!(books.keys().count(), 1);
+
+        let cheap_books = definitions.entity("CatalogService.CheapBooks").unwrap();
+        assert_eq!(names(cheap_books), vec!["ID", "title", "author"]);
+        assert!(cheap_books.annotations.is_true("@readonly"));
+        assert_eq!(
+            cheap_books.query.as_ref().unwrap().filter,
+            Some(Expr::col("ID").lt(Expr::val(10)))
+        );
+
+        let list = definitions.entity("CatalogService.ListOfBooks").unwrap();
+        assert_eq!(names(list), vec!["ID", "name", "author", "one"]);
+        assert!(list.element("ID").unwrap().key);
+        match &list.element("name").unwrap().kind {
+            ElementKind::String(a) => assert_eq!(a.length, Some(111)),
+            _ => panic!("Could not infer element"),
+        }
+        assert!(matches!(
+            list.element("author").unwrap().kind,
+            ElementKind::String(_)
+        ));
+        assert!(matches!(
+            list.element("one").unwrap().kind,
+            ElementKind::Integer(_)
+        ));
+
+        let authors = definitions.entity("CatalogService.Authors").unwrap();
+        assert_eq!(names(authors), vec!["ID"]);
+        assert_eq!(authors.query.as_ref().unwrap().from, "my.Authors");
+    }
+
+    #[test]
+    fn test_get_csn_invalid_views() {
+        let csn = r#"{"definitions": {
+            "my.A": { "kind": "entity", "projection": { "from": { "ref": ["my.B"] } } },
+            "my.B": { "kind": "entity", "projection": { "from": { "ref": ["my.A"] } } }
+          }}"#;
+        match Definitions::from_str(csn) {
+            Ok(_) => assert_eq!(1, 0),
+            Err(e) => assert_eq!(e.description, "Cyclic view definition my.A -> my.B -> my.A"),
+        }
+
+        let csn = r#"{"definitions": {
+            "my.Books": {
+              "kind": "entity",
+              "elements": { "ID": { "key": true, "type": "cds.Integer" } }
+            },
+            "my.View": {
+              "kind": "entity",
+              "query": { "SELECT": { "from": { "ref": ["my.Books"] }, "columns": [{ "ref": ["titel"] }] } }
+            }
+          }}"#;
+        match Definitions::from_str(csn) {
+            Ok(_) => assert_eq!(1, 0),
+            Err(e) => assert_eq!(e.description, "Cannot infer element titel of my.View"),
+        }
+
+        let csn = r#"{"definitions": {
+            "my.View": { "kind": "entity", "projection": { "from": { "ref": ["my.Books"] } } }
+          }}"#;
+        match Definitions::from_str(csn) {
+            Ok(_) => assert_eq!(1, 0),
+            Err(e) => assert_eq!(e.description, "Unknown entity my.Books"),
+        }
+    }
+
+    fn get_test_csn_actions() -> &'static str {
+        r#"{"definitions": {
+            "CatalogService": { "kind": "service" },
+            "CatalogService.Books": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "stock": { "type": "cds.Integer" }
+              },
+              "actions": {
+                "addStock": {
+                  "kind": "action",
+                  "params": {
+                    "in": { "type": "$self" },
+                    "amount": { "type": "cds.Integer", "@assert.range": [1, 100] }
+                  },
+                  "returns": { "type": "CatalogService.Books" }
+                },
+                "discount": {
+                  "kind": "function",
+                  "returns": { "type": "cds.Decimal", "precision": 5, "scale": 2 }
+                }
+              }
+            },
+            "CatalogService.submitOrder": {
+              "@requires": "authenticated-user",
+              "kind": "action",
+              "params": {
+                "book": { "type": "CatalogService.Books:ID" },
+                "quantity": { "type": "cds.Integer" }
+              },
+              "returns": { "elements": { "stock": { "type": "cds.Integer" } } }
+            },
+            "CatalogService.listTitles": {
+              "kind": "function",
+              "returns": { "items": { "type": "cds.String" } }
+            },
+            "CatalogService.OrderedBook": {
+              "kind": "event",
+              "elements": {
+                "book": { "type": "cds.Integer" },
+                "quantity": { "type": "cds.Integer" }
+              }
+            },
+            "my.ping": { "kind": "function", "returns": { "type": "cds.Boolean" } }
+          }}"#
+    }
+
+    #[test]
+    fn test_get_csn_actions() {
+        let csn = get_test_csn_actions();
+        let definitions = Definitions::from_str(csn).unwrap();
+
+        let books = definitions.entity("CatalogService.Books").unwrap();
+        let add_stock = books.action("addStock").unwrap();
+        assert_eq!(add_stock.kind, ActionKind::Action);
+        assert_eq!(add_stock.params.len(), 2);
+        let amount = add_stock
+            .params
+            .iter()
+            .find(|p| p.name == "amount")
+            .unwrap();
+        assert!(matches!(
+            amount.kind,
+            ParameterKind::Scalar(ElementKind::Integer(_))
+        ));
+        assert!(amount.annotations.contains("@assert.range"));
+        let binding = add_stock.params.iter().find(|p| p.name == "in").unwrap();
+        assert!(matches!(&binding.kind, ParameterKind::Entity(e) if e == "CatalogService.Books"));
+        match &add_stock.returns.as_ref().unwrap().kind {
+            ParameterKind::Entity(entity) => assert_eq!(entity, "CatalogService.Books"),
+            _ => panic!("Could not deserialize returns"),
+        }
+        let discount = books.action("discount").unwrap();
+        assert_eq!(discount.kind, ActionKind::Function);
+        assert!(discount.params.is_empty());
+
+        let service = definitions.service("CatalogService").unwrap();
+        assert_eq!(service.actions.len(), 2);
+        assert_eq!(service.events.len(), 1);
+
+        let submit_order = definitions.action("CatalogService.submitOrder").unwrap();
+        assert!(submit_order.annotations.contains("@requires"));
+        let book = submit_order
+            .params
+            .iter()
+            .find(|p| p.name == "book")
+            .unwrap();
+        assert_eq!(book.type_name.as_deref(), Some("CatalogService.Books:ID"));
+        match &submit_order.returns.as_ref().unwrap().kind {
+            ParameterKind::Structured(elements) => assert_eq!(elements[0].name, "stock"),
+            _ => panic!("Could not deserialize returns"),
+        }
+
+        let list_titles = definitions.action("CatalogService.listTitles").unwrap();
+        let returns = list_titles.returns.as_ref().unwrap();
+        assert!(returns.many);
+        assert!(matches!(
+            returns.kind,
+            ParameterKind::Scalar(ElementKind::String(_))
+        ));
+
+        let event = definitions.event("CatalogService.OrderedBook").unwrap();
+        assert_eq!(event.elements.len(), 2);
+
+        let ping = definitions.action("my.ping").unwrap();
+        assert_eq!(ping.kind, ActionKind::Function);
+        assert!(definitions
+            .definitions()
+            .iter()
+            .any(|d| matches!(d, Definition::Action(a) if a.name == "my.ping")));
     }
 
     #[test]
@@ -255,4 +1395,21 @@ mod tests {
             Err(e) => assert_eq!(e.description, "invalid number at line 1 column 11"),
         }
     }
+
+    #[test]
+    fn test_get_csn_invalid_json_path() {
+        let csn = r#"{"definitions": {
+            "my.Books": {
+              "kind": "entity",
+              "elements": { "title": { "type": "cds.String", "length": "long" } }
+            }
+          }}"#;
+        let e = Definitions::from_str(csn).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::InvalidValue);
+        assert_eq!(e.path, "definitions.\"my.Books\".elements.title.length");
+
+        let e = Definitions::from_str(get_test_csn_invalid_json()).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::InvalidJson);
+        assert_eq!(e.path, "meta");
+    }
 }
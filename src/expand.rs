@@ -0,0 +1,356 @@
+// Expanded associations are read with one query per association, filtered
+// by the link columns of the parent rows, and assembled into nested objects
+// and arrays.
+use crate::entities::{Definitions, ElementKind};
+use crate::expr::{Expr, Literal};
+use crate::query::{Column, SELECT};
+use crate::resolve::{find_element, find_entity, link_columns, InvalidQuery, QueryErrorKind};
+use serde_json::{Map, Value};
+
+pub type Row = Map<String, Value>;
+
+// A link between the rows of a parent and an expanded association, by the
+// names of the columns in both results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub parent: String,
+    pub child: String,
+    // The column of the child query to filter by.
+    pub column: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpandedAssociation {
+    pub name: String,
+    pub to_many: bool,
+    pub links: Vec<Link>,
+    pub expansion: Expansion,
+}
+
+// A resolved query with the queries of its expanded associations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expansion {
+    pub query: SELECT,
+    pub associations: Vec<ExpandedAssociation>,
+    // Columns which are only selected to link rows and removed from the
+    // result.
+    pub hidden: Vec<String>,
+}
+
+// The name of `expr` in the result, adding it as hidden column if it is not
+// selected yet.
+fn link_column(query: &mut SELECT, hidden: &mut Vec<String>, expr: Expr) -> String {
+    let name = |column: &Column| match (&column.alias, &column.expr) {
+        (Some(alias), _) => Some(alias.clone()),
+        (None, Expr::Ref(path)) => path.last().cloned(),
+        _ => None,
+    };
+    if let Some(column) = query.columns.iter().find(|column| column.expr == expr) {
+        if let Some(name) = name(column) {
+            return name;
+        }
+    }
+    let column = match &expr {
+        Expr::Ref(path) => format!("_{}", path.join("_")),
+        _ => format!("_{}", query.columns.len()),
+    };
+    query.columns.push(Column::new(expr).alias(&column));
+    hidden.push(column.clone());
+    column
+}
+
+impl SELECT {
+    // The queries to read the rows of the query together with its expanded
+    // associations, at any depth.
+    pub fn expansion(&self, definitions: &Definitions) -> Result<Expansion, InvalidQuery> {
+        let entity = find_entity(definitions, &self.from)?;
+        let mut query = self.resolve(definitions)?;
+        let alias = query.alias.clone().unwrap_or_default();
+        let mut hidden = vec![];
+        let mut associations = vec![];
+        for column in &self.columns {
+            let expand = match &column.expand {
+                Some(expand) => expand,
+                None => continue,
+            };
+            let name = match &column.expr {
+                Expr::Ref(path) if path.len() == 1 => &path[0],
+                expr => {
+                    return Err(InvalidQuery::new(
+                        QueryErrorKind::UnsupportedQuery,
+                        format!("Cannot expand {}", expr.to_sql()),
+                    ))
+                }
+            };
+            let element = find_element(entity, name)?;
+            let association = match &element.kind {
+                ElementKind::Association(a) | ElementKind::Composition(a) => a,
+                _ => {
+                    return Err(InvalidQuery::new(
+                        QueryErrorKind::InvalidType,
+                        format!(
+                            "Cannot expand {}.{}, which is not an association",
+                            entity.name, name
+                        ),
+                    ))
+                }
+            };
+            let mut child = SELECT::from(&association.target);
+            child.columns = match expand.is_empty() {
+                true => vec![Column::new(Expr::Star)],
+                false => expand.clone(),
+            };
+            child.filter = column.filter.clone();
+            let mut expansion = child.expansion(definitions)?;
+            let child_alias = expansion.query.alias.clone().unwrap_or_default();
+            let mut links = vec![];
+            for (source, target) in link_columns(definitions, entity, element)? {
+                let parent = Expr::Ref(vec![alias.clone(), source]);
+                let column = Expr::Ref(vec![child_alias.clone(), target]);
+                links.push(Link {
+                    parent: link_column(&mut query, &mut hidden, parent),
+                    child: link_column(&mut expansion.query, &mut expansion.hidden, column.clone()),
+                    column,
+                });
+            }
+            associations.push(ExpandedAssociation {
+                name: column.alias.clone().unwrap_or_else(|| name.clone()),
+                to_many: association.is_to_many(),
+                links,
+                expansion,
+            });
+        }
+        Ok(Expansion {
+            query,
+            associations,
+            hidden,
+        })
+    }
+}
+
+fn link_values(row: &Row, columns: &[&String]) -> Option<Vec<Value>> {
+    let values: Vec<Value> = columns
+        .iter()
+        .map(|column| row.get(*column).cloned().unwrap_or(Value::Null))
+        .collect();
+    match values.iter().any(Value::is_null) {
+        true => None,
+        false => Some(values),
+    }
+}
+
+// `link IN (...)` for single links, otherwise a disjunction per parent.
+fn link_filter(links: &[Link], parents: &[Vec<Value>]) -> Expr {
+    if let [link] = links {
+        let values = parents
+            .iter()
+            .map(|values| Expr::Val(Literal::from_json(&values[0])))
+            .collect();
+        return link.column.clone().in_list(Expr::List(values));
+    }
+    parents
+        .iter()
+        .map(|values| {
+            links
+                .iter()
+                .zip(values)
+                .map(|(link, value)| link.column.clone().eq(Expr::Val(Literal::from_json(value))))
+                .reduce(Expr::and)
+                .unwrap()
+        })
+        .reduce(Expr::or)
+        .unwrap()
+}
+
+impl Expansion {
+    // Reads the nested rows, running the queries with `fetch`. The query of
+    // an association is only run if parent rows link to it.
+    pub fn execute<E, F>(&self, fetch: &mut F) -> Result<Vec<Value>, E>
+    where
+        F: FnMut(&SELECT) -> Result<Vec<Row>, E>,
+    {
+        let rows = self.fetch(&self.query, &[], fetch)?;
+        Ok(rows.into_iter().map(Value::Object).collect())
+    }
+
+    fn fetch<E, F>(&self, query: &SELECT, keep: &[String], fetch: &mut F) -> Result<Vec<Row>, E>
+    where
+        F: FnMut(&SELECT) -> Result<Vec<Row>, E>,
+    {
+        let mut rows = fetch(query)?;
+        for association in &self.associations {
+            let parents: Vec<&String> = association.links.iter().map(|link| &link.parent).collect();
+            let children: Vec<&String> = association.links.iter().map(|link| &link.child).collect();
+            let mut values: Vec<Vec<Value>> = vec![];
+            for row in &rows {
+                match link_values(row, &parents) {
+                    Some(row_values) if !values.contains(&row_values) => values.push(row_values),
+                    _ => {}
+                }
+            }
+            let child_rows = match values.is_empty() {
+                true => vec![],
+                false => {
+                    let expansion = &association.expansion;
+                    let query = expansion
+                        .query
+                        .clone()
+                        .filter(link_filter(&association.links, &values));
+                    let keep: Vec<String> =
+                        children.iter().map(|child| child.to_string()).collect();
+                    expansion.fetch(&query, &keep, fetch)?
+                }
+            };
+            for row in rows.iter_mut() {
+                let row_values = link_values(row, &parents);
+                let mut nested = child_rows
+                    .iter()
+                    .filter(|child| {
+                        row_values.is_some() && link_values(child, &children) == row_values
+                    })
+                    .map(|child| {
+                        let mut child = child.clone();
+                        for column in &children {
+                            if association.expansion.hidden.contains(column) {
+                                child.remove(*column);
+                            }
+                        }
+                        Value::Object(child)
+                    });
+                let value = match association.to_many {
+                    true => Value::Array(nested.collect()),
+                    false => nested.next().unwrap_or(Value::Null),
+                };
+                row.insert(association.name.clone(), value);
+            }
+        }
+        for row in rows.iter_mut() {
+            for column in &self.hidden {
+                if !keep.contains(column) {
+                    row.remove(column);
+                }
+            }
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::CQN;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    fn get_test_csn() -> Definitions {
+        let input_str = r#"{"definitions": {
+            "my.Authors": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "name": { "type": "cds.String" },
+                "books": {
+                  "type": "cds.Association",
+                  "cardinality": { "max": "*" },
+                  "target": "my.Books",
+                  "on": [{ "ref": ["books", "author"] }, "=", { "ref": ["$self"] }]
+                }
+              }
+            },
+            "my.Books": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "title": { "type": "cds.String" },
+                "author": { "type": "cds.Association", "target": "my.Authors" },
+                "genre": { "type": "cds.Association", "target": "my.Genres" }
+              }
+            },
+            "my.Genres": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "name": { "type": "cds.String" }
+              }
+            }
+          }}"#;
+        Definitions::from_str(input_str).unwrap()
+    }
+
+    fn rows(value: Value) -> Vec<Row> {
+        match value {
+            Value::Array(rows) => rows
+                .into_iter()
+                .map(|row| match row {
+                    Value::Object(row) => row,
+                    _ => unreachable!(),
+                })
+                .collect(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn expand_to_many_and_to_one() {
+        let definitions = get_test_csn();
+        let select = SELECT::from("my.Authors")
+            .columns(vec!["name"])
+            .expand(
+                "books",
+                vec![
+                    Column::new(Expr::col("title")),
+                    Column::expand("genre", vec![]),
+                ],
+            )
+            .filter(Expr::col("ID").lt(Expr::val(3)));
+        let expansion = select.expansion(&definitions).unwrap();
+
+        let mut queries = vec![];
+        let result = expansion
+            .execute(&mut |query: &SELECT| {
+                let sql = query.to_sql().unwrap();
+                queries.push(sql.clone());
+                let result = match queries.len() {
+                    1 => json!([{ "name": "Emily", "_Authors_ID": 1 }, { "name": "Jane", "_Authors_ID": 2 }]),
+                    2 => json!([
+                        { "title": "Wuthering Heights", "_Books_genre_ID": 10, "_Books_author_ID": 1 },
+                        { "title": "Jane Eyre", "_Books_genre_ID": null, "_Books_author_ID": 1 }
+                    ]),
+                    _ => json!([{ "ID": 10, "name": "Drama" }]),
+                };
+                Ok::<_, ()>(rows(result))
+            })
+            .unwrap();
+        assert_eq!(
+            queries,
+            vec![
+                "SELECT Authors.name,Authors.ID as _Authors_ID FROM my_Authors as Authors\n  WHERE Authors.ID < 3",
+                "SELECT Books.title,Books.genre_ID as _Books_genre_ID,Books.author_ID as _Books_author_ID FROM my_Books as Books\n  WHERE Books.author_ID IN (1, 2)",
+                "SELECT Genres.ID,Genres.name FROM my_Genres as Genres\n  WHERE Genres.ID IN (10)",
+            ]
+        );
+        assert_eq!(
+            Value::Array(result),
+            json!([
+                {
+                    "name": "Emily",
+                    "books": [
+                        { "title": "Wuthering Heights", "genre": { "ID": 10, "name": "Drama" } },
+                        { "title": "Jane Eyre", "genre": null }
+                    ]
+                },
+                { "name": "Jane", "books": [] }
+            ])
+        );
+    }
+
+    #[test]
+    fn expand_non_association() {
+        let definitions = get_test_csn();
+        let err = SELECT::from("my.Books")
+            .expand("title", vec![])
+            .expansion(&definitions)
+            .unwrap_err();
+        assert_eq!(err.kind, QueryErrorKind::InvalidType);
+    }
+}
@@ -0,0 +1,816 @@
+use crate::params::Bindings;
+use crate::resolve::{InvalidQuery, QueryErrorKind};
+use crate::values::{Date, DateTime, Decimal, InvalidLiteral, Time, Timestamp};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Double(f64),
+    Decimal(Decimal),
+    String(String),
+    Date(Date),
+    Time(Time),
+    DateTime(DateTime),
+    Timestamp(Timestamp),
+}
+
+impl Literal {
+    // Objects and arrays are kept as their JSON text.
+    pub fn from_json(value: &serde_json::Value) -> Literal {
+        match value {
+            serde_json::Value::Null => Literal::Null,
+            serde_json::Value::Bool(b) => Literal::Bool(*b),
+            serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => Literal::Integer(i),
+                (None, Some(u)) => Literal::Decimal(u.into()),
+                (None, None) => Literal::Double(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            serde_json::Value::String(s) => Literal::String(s.clone()),
+            other => Literal::String(other.to_string()),
+        }
+    }
+
+    // A value with the kind of a CQN `literal` property, e.g.
+    // `{"val": "2020-01-01", "literal": "date"}`. Decimals are given as
+    // strings to keep all their digits. Other kinds are read like `from_json`.
+    pub fn from_typed_json(
+        value: &serde_json::Value,
+        kind: &str,
+    ) -> Result<Literal, InvalidLiteral> {
+        let text = match value {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        match kind {
+            "number" => text.parse().map(Literal::Decimal),
+            "date" => text.parse().map(Literal::Date),
+            "time" => text.parse().map(Literal::Time),
+            "timestamp" if text.contains('.') => text.parse().map(Literal::Timestamp),
+            "timestamp" => text.parse().map(Literal::DateTime),
+            _ => Ok(Literal::from_json(value)),
+        }
+    }
+
+    // The kind of the `literal` property for values which are strings in
+    // JSON but not text.
+    pub fn json_kind(&self) -> Option<&'static str> {
+        match self {
+            Literal::Decimal(_) => Some("number"),
+            Literal::Date(_) => Some("date"),
+            Literal::Time(_) => Some("time"),
+            Literal::DateTime(_) | Literal::Timestamp(_) => Some("timestamp"),
+            _ => None,
+        }
+    }
+
+    // Literals which cannot carry SQL and need no quoting. `NaN` and
+    // infinite doubles have no SQL literal.
+    pub fn is_inline_safe(&self) -> bool {
+        match self {
+            Literal::Double(d) => d.is_finite(),
+            literal => matches!(
+                literal,
+                Literal::Null | Literal::Bool(_) | Literal::Integer(_) | Literal::Decimal(_)
+            ),
+        }
+    }
+
+    pub fn to_sql(&self) -> String {
+        let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+        match self {
+            Literal::Null => "NULL".to_string(),
+            Literal::Bool(true) => "TRUE".to_string(),
+            Literal::Bool(false) => "FALSE".to_string(),
+            Literal::Integer(i) => i.to_string(),
+            Literal::Double(d) => d.to_string(),
+            Literal::Decimal(d) => d.to_string(),
+            Literal::String(s) => quote(s),
+            Literal::Date(d) => quote(&d.to_string()),
+            Literal::Time(t) => quote(&t.to_string()),
+            Literal::DateTime(dt) => quote(&dt.to_string()),
+            Literal::Timestamp(ts) => quote(&ts.to_string()),
+        }
+    }
+}
+
+macro_rules! literal_from {
+    ($($ty:ty => $variant:ident),*) => {
+        $(
+            impl From<$ty> for Literal {
+                fn from(v: $ty) -> Literal {
+                    Literal::$variant(v.into())
+                }
+            }
+        )*
+    };
+}
+
+literal_from!(
+    bool => Bool,
+    i32 => Integer,
+    i64 => Integer,
+    f64 => Double,
+    Decimal => Decimal,
+    String => String,
+    &str => String,
+    Date => Date,
+    Time => Time,
+    DateTime => DateTime,
+    Timestamp => Timestamp
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Not,
+    Neg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Like,
+    In,
+    Is,
+    IsNot,
+    Concat,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl BinaryOp {
+    pub fn to_sql(self) -> &'static str {
+        match self {
+            BinaryOp::Or => "OR",
+            BinaryOp::And => "AND",
+            BinaryOp::Eq => "=",
+            BinaryOp::Ne => "<>",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Like => "LIKE",
+            BinaryOp::In => "IN",
+            BinaryOp::Is => "IS",
+            BinaryOp::IsNot => "IS NOT",
+            BinaryOp::Concat => "||",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+        }
+    }
+
+    pub(crate) fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge
+            | BinaryOp::Like
+            | BinaryOp::In
+            | BinaryOp::Is
+            | BinaryOp::IsNot => COMPARISON,
+            BinaryOp::Concat => 5,
+            BinaryOp::Add | BinaryOp::Sub => 6,
+            BinaryOp::Mul | BinaryOp::Div => 7,
+        }
+    }
+
+    // Operators which the parser accepts in CQN token lists.
+    pub(crate) fn parse(token: &str) -> Option<BinaryOp> {
+        let op = match token.to_ascii_lowercase().as_str() {
+            "or" => BinaryOp::Or,
+            "and" => BinaryOp::And,
+            "=" | "==" => BinaryOp::Eq,
+            "!=" | "<>" => BinaryOp::Ne,
+            "<" => BinaryOp::Lt,
+            "<=" => BinaryOp::Le,
+            ">" => BinaryOp::Gt,
+            ">=" => BinaryOp::Ge,
+            "like" => BinaryOp::Like,
+            "in" => BinaryOp::In,
+            "is" => BinaryOp::Is,
+            "||" => BinaryOp::Concat,
+            "+" => BinaryOp::Add,
+            "-" => BinaryOp::Sub,
+            "*" => BinaryOp::Mul,
+            "/" => BinaryOp::Div,
+            _ => return None,
+        };
+        Some(op)
+    }
+}
+
+pub(crate) const NOT: u8 = 3;
+pub(crate) const COMPARISON: u8 = 4;
+pub(crate) const NEG: u8 = 8;
+const ATOM: u8 = 9;
+
+// A CQN expression. Nested `xpr` token lists are represented by the shape of
+// the tree, parentheses are added when rendering where precedence needs them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    // `*`, in columns and as argument of `count`.
+    Star,
+    Ref(Vec<String>),
+    Val(Literal),
+    Func(String, Vec<Expr>),
+    List(Vec<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(Box<Expr>, BinaryOp, Box<Expr>),
+    Between(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+// Builders
+impl Expr {
+    // A reference to an element, paths are separated by dots.
+    pub fn col(path: &str) -> Expr {
+        Expr::Ref(path.split('.').map(str::to_string).collect())
+    }
+
+    pub fn val<T: Into<Literal>>(val: T) -> Expr {
+        Expr::Val(val.into())
+    }
+
+    pub fn null() -> Expr {
+        Expr::Val(Literal::Null)
+    }
+
+    pub fn func(name: &str, args: Vec<Expr>) -> Expr {
+        Expr::Func(name.to_string(), args)
+    }
+
+    pub fn count(expr: Expr) -> Expr {
+        Expr::func("count", vec![expr])
+    }
+
+    pub fn count_distinct(expr: Expr) -> Expr {
+        Expr::func("countdistinct", vec![expr])
+    }
+
+    pub fn sum(expr: Expr) -> Expr {
+        Expr::func("sum", vec![expr])
+    }
+
+    pub fn avg(expr: Expr) -> Expr {
+        Expr::func("avg", vec![expr])
+    }
+
+    pub fn min(expr: Expr) -> Expr {
+        Expr::func("min", vec![expr])
+    }
+
+    pub fn max(expr: Expr) -> Expr {
+        Expr::func("max", vec![expr])
+    }
+
+    pub fn list<T: Into<Literal>>(values: Vec<T>) -> Expr {
+        Expr::List(values.into_iter().map(Expr::val).collect())
+    }
+
+    pub fn binary(self, op: BinaryOp, other: Expr) -> Expr {
+        Expr::Binary(Box::new(self), op, Box::new(other))
+    }
+
+    pub fn and(self, other: Expr) -> Expr {
+        self.binary(BinaryOp::And, other)
+    }
+
+    pub fn or(self, other: Expr) -> Expr {
+        self.binary(BinaryOp::Or, other)
+    }
+
+    pub fn eq(self, other: Expr) -> Expr {
+        self.binary(BinaryOp::Eq, other)
+    }
+
+    pub fn ne(self, other: Expr) -> Expr {
+        self.binary(BinaryOp::Ne, other)
+    }
+
+    pub fn lt(self, other: Expr) -> Expr {
+        self.binary(BinaryOp::Lt, other)
+    }
+
+    pub fn le(self, other: Expr) -> Expr {
+        self.binary(BinaryOp::Le, other)
+    }
+
+    pub fn gt(self, other: Expr) -> Expr {
+        self.binary(BinaryOp::Gt, other)
+    }
+
+    pub fn ge(self, other: Expr) -> Expr {
+        self.binary(BinaryOp::Ge, other)
+    }
+
+    pub fn like(self, other: Expr) -> Expr {
+        self.binary(BinaryOp::Like, other)
+    }
+
+    pub fn in_list(self, list: Expr) -> Expr {
+        self.binary(BinaryOp::In, list)
+    }
+
+    pub fn is_null(self) -> Expr {
+        self.binary(BinaryOp::Is, Expr::null())
+    }
+
+    pub fn is_not_null(self) -> Expr {
+        self.binary(BinaryOp::IsNot, Expr::null())
+    }
+
+    pub fn between(self, low: Expr, high: Expr) -> Expr {
+        Expr::Between(Box::new(self), Box::new(low), Box::new(high))
+    }
+
+    pub fn negate(self) -> Expr {
+        Expr::Unary(UnaryOp::Neg, Box::new(self))
+    }
+}
+
+impl std::ops::Not for Expr {
+    type Output = Expr;
+
+    fn not(self) -> Expr {
+        Expr::Unary(UnaryOp::Not, Box::new(self))
+    }
+}
+
+// Traversal
+impl Expr {
+    // Replaces all references of the expression, e.g. to qualify them.
+    pub fn try_map_refs<E, F>(&self, f: &mut F) -> Result<Expr, E>
+    where
+        F: FnMut(&[String]) -> Result<Expr, E>,
+    {
+        let map_all = |exprs: &[Expr], f: &mut F| -> Result<Vec<Expr>, E> {
+            exprs.iter().map(|expr| expr.try_map_refs(f)).collect()
+        };
+        let expr = match self {
+            Expr::Ref(path) => f(path)?,
+            Expr::Func(name, args) => Expr::Func(name.clone(), map_all(args, f)?),
+            Expr::List(items) => Expr::List(map_all(items, f)?),
+            Expr::Unary(op, operand) => Expr::Unary(*op, Box::new(operand.try_map_refs(f)?)),
+            Expr::Binary(left, op, right) => Expr::Binary(
+                Box::new(left.try_map_refs(f)?),
+                *op,
+                Box::new(right.try_map_refs(f)?),
+            ),
+            Expr::Between(expr, low, high) => Expr::Between(
+                Box::new(expr.try_map_refs(f)?),
+                Box::new(low.try_map_refs(f)?),
+                Box::new(high.try_map_refs(f)?),
+            ),
+            Expr::Star | Expr::Val(_) => self.clone(),
+        };
+        Ok(expr)
+    }
+}
+
+const AGGREGATES: [&str; 6] = ["count", "countdistinct", "sum", "avg", "min", "max"];
+
+// Rendering
+impl Expr {
+    // Whether the expression is or contains a call of an aggregate function.
+    pub fn is_aggregate(&self) -> bool {
+        match self {
+            Expr::Func(name, args) => {
+                AGGREGATES.contains(&name.to_ascii_lowercase().as_str())
+                    || args.iter().any(Expr::is_aggregate)
+            }
+            Expr::List(items) => items.iter().any(Expr::is_aggregate),
+            Expr::Unary(_, operand) => operand.is_aggregate(),
+            Expr::Binary(left, _, right) => left.is_aggregate() || right.is_aggregate(),
+            Expr::Between(expr, low, high) => {
+                expr.is_aggregate() || low.is_aggregate() || high.is_aggregate()
+            }
+            _ => false,
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Unary(UnaryOp::Not, _) => NOT,
+            Expr::Unary(UnaryOp::Neg, _) => NEG,
+            Expr::Binary(_, op, _) => op.precedence(),
+            Expr::Between(..) => COMPARISON,
+            _ => ATOM,
+        }
+    }
+
+    // Renders `self` as operand of an operator with precedence `parent`.
+    // Operands on the right and operands of comparisons, which do not
+    // associate, also need parentheses on equal precedence.
+    fn needs_parens(&self, parent: u8, right: bool) -> bool {
+        let precedence = self.precedence();
+        precedence < parent || (precedence == parent && (right || parent == COMPARISON))
+    }
+
+    fn operand_sql(&self, bindings: &mut Bindings, parent: u8, right: bool) -> String {
+        let sql = self.render(bindings);
+        match self.needs_parens(parent, right) {
+            true => format!("({})", sql),
+            false => sql,
+        }
+    }
+
+    pub fn render(&self, bindings: &mut Bindings) -> String {
+        match self {
+            Expr::Star => "*".to_string(),
+            Expr::Ref(path) if path.len() == 1 && path[0] == "$now" => {
+                bindings.dialect().now().to_string()
+            }
+            Expr::Ref(path) => {
+                let path: Vec<String> = path.iter().map(|name| bindings.identifier(name)).collect();
+                path.join(".")
+            }
+            Expr::Val(literal) => bindings.literal(literal),
+            Expr::Func(name, args) => {
+                let args: Vec<String> = args.iter().map(|arg| arg.render(bindings)).collect();
+                // Aggregates are rendered in standard SQL, which all databases
+                // understand, `uuid` and `concat` by the dialect, other
+                // functions are passed through.
+                match name.to_ascii_lowercase().as_str() {
+                    "countdistinct" => format!("COUNT(DISTINCT {})", args.join(", ")),
+                    aggregate if AGGREGATES.contains(&aggregate) => {
+                        format!("{}({})", aggregate.to_ascii_uppercase(), args.join(", "))
+                    }
+                    "uuid" if args.is_empty() => bindings.dialect().uuid().to_string(),
+                    "concat" => bindings.dialect().concat(&args),
+                    _ if is_function_name(name) => format!("{}({})", name, args.join(", ")),
+                    _ => {
+                        bindings.reject(invalid_function(name));
+                        format!("\"{}\"({})", name.replace('"', "\"\""), args.join(", "))
+                    }
+                }
+            }
+            Expr::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.render(bindings)).collect();
+                format!("({})", items.join(", "))
+            }
+            Expr::Unary(UnaryOp::Not, operand) => {
+                format!("NOT {}", operand.operand_sql(bindings, NOT, true))
+            }
+            // `--` would start a comment.
+            Expr::Unary(UnaryOp::Neg, operand) => {
+                let operand = operand.operand_sql(bindings, NEG, true);
+                match operand.starts_with('-') {
+                    true => format!("-({})", operand),
+                    false => format!("-{}", operand),
+                }
+            }
+            Expr::Binary(left, op, right) => {
+                let precedence = op.precedence();
+                let left = left.operand_sql(bindings, precedence, false);
+                let right = right.operand_sql(bindings, precedence, true);
+                match op {
+                    BinaryOp::Concat => bindings.dialect().concat(&[left, right]),
+                    op => format!("{} {} {}", left, op.to_sql(), right),
+                }
+            }
+            Expr::Between(expr, low, high) => {
+                let expr = expr.operand_sql(bindings, COMPARISON, false);
+                let low = low.operand_sql(bindings, COMPARISON + 1, false);
+                let high = high.operand_sql(bindings, COMPARISON + 1, false);
+                format!("{} BETWEEN {} AND {}", expr, low, high)
+            }
+        }
+    }
+
+    // Renders the expression with all values inlined.
+    pub fn to_sql(&self) -> String {
+        self.render(&mut Bindings::inline())
+    }
+}
+
+// Function names are pasted into the SQL, so they must be plain
+// identifiers like `[A-Za-z_][A-Za-z0-9_]*`.
+pub(crate) fn is_function_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub(crate) fn invalid_function(name: &str) -> InvalidQuery {
+    InvalidQuery::new(
+        QueryErrorKind::InvalidValue,
+        format!("Invalid function name {:?}", name),
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidExpression {
+    pub description: String,
+}
+
+impl fmt::Display for InvalidExpression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.description)
+    }
+}
+
+impl std::error::Error for InvalidExpression {}
+
+fn invalid(description: String) -> InvalidExpression {
+    InvalidExpression { description }
+}
+
+// A token of a flat CQN expression like the `where` of a query, either an
+// operator or keyword, or an operand.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Keyword(String),
+    Expr(Expr),
+}
+
+struct TokenParser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl TokenParser {
+    fn peek_keyword(&mut self) -> Option<String> {
+        match self.tokens.peek() {
+            Some(Token::Keyword(keyword)) => Some(keyword.to_ascii_lowercase()),
+            _ => None,
+        }
+    }
+
+    fn expect_keyword(&mut self, expected: &str) -> Result<(), InvalidExpression> {
+        match self.peek_keyword() {
+            Some(keyword) if keyword == expected => {
+                self.tokens.next();
+                Ok(())
+            }
+            _ => Err(invalid(format!("Expected {}", expected))),
+        }
+    }
+
+    fn prefix(&mut self) -> Result<Expr, InvalidExpression> {
+        let keyword = match self.tokens.next() {
+            Some(Token::Expr(expr)) => return Ok(expr),
+            Some(Token::Keyword(keyword)) => keyword,
+            None => return Err(invalid("Unexpected end of expression".to_string())),
+        };
+        match keyword.to_ascii_lowercase().as_str() {
+            "not" => Ok(!self.expr(NOT)?),
+            "-" => Ok(self.expr(NEG)?.negate()),
+            "null" => Ok(Expr::null()),
+            "true" => Ok(Expr::val(true)),
+            "false" => Ok(Expr::val(false)),
+            "(" => {
+                let expr = self.expr(0)?;
+                self.expect_keyword(")")?;
+                Ok(expr)
+            }
+            _ => Err(invalid(format!("Unexpected {}", keyword))),
+        }
+    }
+
+    fn expr(&mut self, min_precedence: u8) -> Result<Expr, InvalidExpression> {
+        let mut left = self.prefix()?;
+        while let Some(keyword) = self.peek_keyword() {
+            // `not` in infix position negates `in`, `like` and `between`.
+            let negated = keyword == "not";
+            let (keyword, precedence) = match (negated, BinaryOp::parse(&keyword)) {
+                (true, _) => (self.negated_keyword()?, COMPARISON),
+                (false, Some(op)) => (keyword, op.precedence()),
+                (false, None) if keyword == "between" => (keyword, COMPARISON),
+                (false, None) => break,
+            };
+            if precedence < min_precedence {
+                break;
+            }
+            self.tokens.next();
+            if negated {
+                self.tokens.next();
+            }
+            let expr = match keyword.as_str() {
+                "between" => {
+                    let low = self.expr(COMPARISON + 1)?;
+                    self.expect_keyword("and")?;
+                    let high = self.expr(COMPARISON + 1)?;
+                    left.between(low, high)
+                }
+                "is" if self.peek_keyword().as_deref() == Some("not") => {
+                    self.tokens.next();
+                    left.binary(BinaryOp::IsNot, self.expr(precedence + 1)?)
+                }
+                _ => {
+                    let op = BinaryOp::parse(&keyword).unwrap();
+                    left.binary(op, self.expr(precedence + 1)?)
+                }
+            };
+            left = match negated {
+                true => !expr,
+                false => expr,
+            };
+        }
+        Ok(left)
+    }
+
+    fn negated_keyword(&mut self) -> Result<String, InvalidExpression> {
+        let mut lookahead = self.tokens.clone();
+        lookahead.next();
+        match lookahead.next() {
+            Some(Token::Keyword(keyword))
+                if ["in", "like", "between"].contains(&keyword.to_ascii_lowercase().as_str()) =>
+            {
+                Ok(keyword.to_ascii_lowercase())
+            }
+            _ => Err(invalid("Unexpected not".to_string())),
+        }
+    }
+}
+
+impl Expr {
+    // The flat CQN token list of the expression, the inverse of `from_tokens`.
+    // Operands which need parentheses are kept as nested expressions.
+    pub fn to_tokens(&self) -> Vec<Token> {
+        let mut tokens = vec![];
+        self.push_tokens(&mut tokens);
+        tokens
+    }
+
+    fn push_tokens(&self, tokens: &mut Vec<Token>) {
+        let keyword = |keyword: &str| Token::Keyword(keyword.to_string());
+        match self {
+            Expr::Unary(op, operand) => {
+                let (word, precedence) = match op {
+                    UnaryOp::Not => ("not", NOT),
+                    UnaryOp::Neg => ("-", NEG),
+                };
+                tokens.push(keyword(word));
+                operand.push_operand(tokens, precedence, true);
+            }
+            Expr::Binary(left, op, right) => {
+                let precedence = op.precedence();
+                left.push_operand(tokens, precedence, false);
+                for word in op.to_sql().split(' ') {
+                    tokens.push(keyword(&word.to_ascii_lowercase()));
+                }
+                right.push_operand(tokens, precedence, true);
+            }
+            Expr::Between(expr, low, high) => {
+                expr.push_operand(tokens, COMPARISON, false);
+                tokens.push(keyword("between"));
+                low.push_operand(tokens, COMPARISON + 1, false);
+                tokens.push(keyword("and"));
+                high.push_operand(tokens, COMPARISON + 1, false);
+            }
+            atom => tokens.push(Token::Expr(atom.clone())),
+        }
+    }
+
+    fn push_operand(&self, tokens: &mut Vec<Token>, parent: u8, right: bool) {
+        match self.needs_parens(parent, right) {
+            true => tokens.push(Token::Expr(self.clone())),
+            false => self.push_tokens(tokens),
+        }
+    }
+
+    // Builds the expression tree of a flat CQN token list, e.g. the tokens
+    // `a`, `>`, `2`, `and`, `b`, `<`, `9`.
+    pub fn from_tokens(tokens: Vec<Token>) -> Result<Expr, InvalidExpression> {
+        let mut parser = TokenParser {
+            tokens: tokens.into_iter().peekable(),
+        };
+        let expr = parser.expr(0)?;
+        match parser.tokens.next() {
+            None => Ok(expr),
+            Some(Token::Keyword(keyword)) => Err(invalid(format!("Unexpected {}", keyword))),
+            Some(Token::Expr(expr)) => Err(invalid(format!("Unexpected {}", expr.to_sql()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyword(keyword: &str) -> Token {
+        Token::Keyword(keyword.to_string())
+    }
+
+    fn operand(expr: Expr) -> Token {
+        Token::Expr(expr)
+    }
+
+    #[test]
+    fn render_with_precedence() {
+        let expr = Expr::col("a")
+            .gt(Expr::val(2))
+            .and(Expr::col("b").lt(Expr::val(9)))
+            .or(Expr::col("c").lt(Expr::val(4)));
+        assert_eq!(expr.to_sql(), "a > 2 AND b < 9 OR c < 4");
+
+        let expr = Expr::col("a").gt(Expr::val(2)).and(
+            Expr::col("b")
+                .lt(Expr::val(9))
+                .or(Expr::col("c").lt(Expr::val(4))),
+        );
+        assert_eq!(expr.to_sql(), "a > 2 AND (b < 9 OR c < 4)");
+
+        let expr = !Expr::col("a")
+            .eq(Expr::val("it's"))
+            .or(Expr::col("b").is_null());
+        assert_eq!(expr.to_sql(), "NOT (a = 'it''s' OR b IS NULL)");
+
+        let price = Expr::col("price").binary(BinaryOp::Sub, Expr::val(1));
+        let expr = price
+            .binary(BinaryOp::Mul, Expr::val(2))
+            .ge(Expr::func("avg", vec![Expr::col("author.price")]));
+        assert_eq!(expr.to_sql(), "(price - 1) * 2 >= AVG(author.price)");
+
+        let expr = Expr::col("a").binary(
+            BinaryOp::Sub,
+            Expr::col("b").binary(BinaryOp::Sub, Expr::col("c")),
+        );
+        assert_eq!(expr.to_sql(), "a - (b - c)");
+
+        let expr = Expr::count(Expr::Star)
+            .gt(Expr::val(1))
+            .and(Expr::func("Max", vec![Expr::col("price")]).lt(Expr::val(9)));
+        assert!(expr.is_aggregate());
+        assert_eq!(expr.to_sql(), "COUNT(*) > 1 AND MAX(price) < 9");
+        assert_eq!(
+            Expr::count_distinct(Expr::col("author.ID")).to_sql(),
+            "COUNT(DISTINCT author.ID)"
+        );
+
+        let expr = Expr::col("ID")
+            .in_list(Expr::list(vec![1, 2]))
+            .and(Expr::col("stock").between(Expr::val(1), Expr::val(10)));
+        assert_eq!(expr.to_sql(), "ID IN (1, 2) AND stock BETWEEN 1 AND 10");
+
+        let expr = Expr::col("a").eq(Expr::val(-1).negate());
+        assert_eq!(expr.to_sql(), "a = -(-1)");
+        assert_eq!(Expr::col("a").negate().negate().to_sql(), "-(-a)");
+    }
+
+    #[test]
+    fn parse_tokens() {
+        // ( a > 2 and b < 9 ) or not c in (1, 2)
+        let tokens = vec![
+            keyword("("),
+            operand(Expr::col("a")),
+            keyword(">"),
+            operand(Expr::val(2)),
+            keyword("and"),
+            operand(Expr::col("b")),
+            keyword("<"),
+            operand(Expr::val(9)),
+            keyword(")"),
+            keyword("or"),
+            operand(Expr::col("c")),
+            keyword("not"),
+            keyword("in"),
+            operand(Expr::list(vec![1, 2])),
+        ];
+        let expr = Expr::from_tokens(tokens).unwrap();
+        assert_eq!(
+            expr,
+            Expr::col("a")
+                .gt(Expr::val(2))
+                .and(Expr::col("b").lt(Expr::val(9)))
+                .or(!Expr::col("c").in_list(Expr::list(vec![1, 2])))
+        );
+        assert_eq!(expr.to_sql(), "a > 2 AND b < 9 OR NOT c IN (1, 2)");
+
+        let tokens = vec![
+            operand(Expr::col("a")),
+            keyword("is"),
+            keyword("not"),
+            keyword("null"),
+            keyword("and"),
+            operand(Expr::col("b")),
+            keyword("between"),
+            operand(Expr::val(1)),
+            keyword("and"),
+            operand(Expr::val(5)),
+        ];
+        let expr = Expr::from_tokens(tokens).unwrap();
+        assert_eq!(expr.to_sql(), "a IS NOT NULL AND b BETWEEN 1 AND 5");
+
+        let res = Expr::from_tokens(vec![operand(Expr::col("a")), keyword(">")]);
+        assert_eq!(res.unwrap_err().description, "Unexpected end of expression");
+        let res = Expr::from_tokens(vec![operand(Expr::col("a")), operand(Expr::col("b"))]);
+        assert_eq!(res.unwrap_err().description, "Unexpected b");
+    }
+}
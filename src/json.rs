@@ -0,0 +1,840 @@
+// Serialization of expressions and queries as CQN JSON, e.g.
+// `{"SELECT":{"from":{"ref":["Books"]},"where":[{"ref":["ID"]},"=",{"val":1}]}}`.
+use crate::expr::{invalid_function, is_function_name, Expr, Literal, Token};
+use crate::query::{
+    all_columns, Column, Limit, Nulls, OrderBy, Query, SortOrder, DELETE, INSERT, SELECT, UPDATE,
+    UPSERT,
+};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Map, Value};
+
+type Result<T> = std::result::Result<T, String>;
+
+// Decimals and dates are written as strings, which keep their type in a
+// `literal` property.
+fn literal_to_json(literal: &Literal) -> Value {
+    match literal {
+        Literal::Null => Value::Null,
+        Literal::Bool(b) => Value::Bool(*b),
+        Literal::Integer(i) => json!(i),
+        Literal::Double(d) => json!(d),
+        Literal::Decimal(d) => json!(d.to_string()),
+        Literal::String(s) => json!(s),
+        Literal::Date(d) => json!(d.to_string()),
+        Literal::Time(t) => json!(t.to_string()),
+        Literal::DateTime(dt) => json!(dt.to_string()),
+        Literal::Timestamp(ts) => json!(ts.to_string()),
+    }
+}
+
+fn val_to_json(literal: &Literal) -> Value {
+    match literal.json_kind() {
+        Some(kind) => json!({ "val": literal_to_json(literal), "literal": kind }),
+        None => json!({ "val": literal_to_json(literal) }),
+    }
+}
+
+// Data and rows are plain JSON values unless their type would be lost,
+// e.g. `{"val": "9.99", "literal": "number"}` for a decimal.
+fn data_to_json(literal: &Literal) -> Value {
+    match literal.json_kind() {
+        Some(_) => val_to_json(literal),
+        None => literal_to_json(literal),
+    }
+}
+
+fn data_from_json(value: &Value) -> Result<Literal> {
+    match value {
+        Value::Object(object) if object.contains_key("val") => match expr_from_json(value)? {
+            Expr::Val(literal) => Ok(literal),
+            _ => Err(format!("Expected a value, found {}", value)),
+        },
+        other => Ok(Literal::from_json(other)),
+    }
+}
+
+fn tokens_to_json(tokens: Vec<Token>) -> Value {
+    let tokens = tokens
+        .iter()
+        .map(|token| match token {
+            Token::Keyword(keyword) => json!(keyword),
+            Token::Expr(expr) => expr_to_json(expr),
+        })
+        .collect();
+    Value::Array(tokens)
+}
+
+fn expr_to_json(expr: &Expr) -> Value {
+    let exprs = |exprs: &[Expr]| Value::Array(exprs.iter().map(expr_to_json).collect());
+    match expr {
+        Expr::Star => json!("*"),
+        Expr::Ref(path) => json!({ "ref": path }),
+        Expr::Val(literal) => val_to_json(literal),
+        Expr::Func(name, args) => json!({ "func": name, "args": exprs(args) }),
+        Expr::List(items) => json!({ "list": exprs(items) }),
+        compound => json!({ "xpr": tokens_to_json(compound.to_tokens()) }),
+    }
+}
+
+fn strings(value: &Value) -> Result<Vec<String>> {
+    let invalid = || format!("Expected strings, found {}", value);
+    let items = value.as_array().ok_or_else(invalid)?;
+    items
+        .iter()
+        .map(|item| item.as_str().map(str::to_string).ok_or_else(invalid))
+        .collect()
+}
+
+fn exprs_from_json(value: &Value) -> Result<Vec<Expr>> {
+    match value {
+        Value::Array(items) => items.iter().map(expr_from_json).collect(),
+        other => Err(format!("Expected an array, found {}", other)),
+    }
+}
+
+fn tokens_from_json(value: &Value) -> Result<Expr> {
+    let items = match value {
+        Value::Array(items) => items,
+        other => return Err(format!("Expected an array, found {}", other)),
+    };
+    let tokens = items
+        .iter()
+        .map(|item| match item {
+            // `*` in token lists is the multiplication.
+            Value::String(s) => Ok(Token::Keyword(s.clone())),
+            item => expr_from_json(item).map(Token::Expr),
+        })
+        .collect::<Result<Vec<Token>>>()?;
+    Expr::from_tokens(tokens).map_err(|err| err.description)
+}
+
+fn expr_from_json(value: &Value) -> Result<Expr> {
+    let object = match value {
+        Value::String(s) if s == "*" => return Ok(Expr::Star),
+        Value::Object(object) => object,
+        other => return Err(format!("Unsupported expression {}", other)),
+    };
+    if let Some(path) = object.get("ref") {
+        Ok(Expr::Ref(strings(path)?))
+    } else if let Some(val) = object.get("val") {
+        match object.get("literal").and_then(Value::as_str) {
+            Some(kind) => Literal::from_typed_json(val, kind)
+                .map(Expr::Val)
+                .map_err(|err| err.to_string()),
+            None => Ok(Expr::Val(Literal::from_json(val))),
+        }
+    } else if let Some(func) = object.get("func") {
+        let name = func.as_str().ok_or("Expected a function name")?;
+        if !is_function_name(name) {
+            return Err(invalid_function(name).description);
+        }
+        let args = match object.get("args") {
+            Some(args) => exprs_from_json(args)?,
+            None => vec![],
+        };
+        Ok(Expr::func(name, args))
+    } else if let Some(list) = object.get("list") {
+        Ok(Expr::List(exprs_from_json(list)?))
+    } else if let Some(xpr) = object.get("xpr") {
+        tokens_from_json(xpr)
+    } else {
+        Err(format!("Unsupported expression {}", value))
+    }
+}
+
+// An infix filter is written into the last segment of the reference,
+// `{"ref":[{"id":"books","where":[...]}]}`.
+fn column_to_json(column: &Column) -> Value {
+    let mut value = expr_to_json(&column.expr);
+    if let Value::Object(object) = &mut value {
+        if let (Some(filter), Some(Value::Array(path))) = (&column.filter, object.get_mut("ref")) {
+            if let Some(last) = path.pop() {
+                path.push(json!({ "id": last, "where": tokens_to_json(filter.to_tokens()) }));
+            }
+        }
+        if let Some(alias) = &column.alias {
+            object.insert("as".to_string(), json!(alias));
+        }
+        if let Some(expand) = &column.expand {
+            let expand = match expand.is_empty() {
+                true => vec![json!("*")],
+                false => expand.iter().map(column_to_json).collect(),
+            };
+            object.insert("expand".to_string(), Value::Array(expand));
+        }
+    }
+    value
+}
+
+fn column_from_json(value: &Value) -> Result<Column> {
+    let alias = match value.get("as") {
+        Some(alias) => Some(alias.as_str().ok_or("Expected an alias")?.to_string()),
+        None => None,
+    };
+    let expand = match value.get("expand") {
+        Some(Value::Array(columns)) => Some(all_columns(
+            columns
+                .iter()
+                .map(column_from_json)
+                .collect::<Result<Vec<Column>>>()?,
+        )),
+        Some(other) => return Err(format!("Expected expand, found {}", other)),
+        None => None,
+    };
+    let mut value = value.clone();
+    let mut filter = None;
+    if let Some(Value::Array(path)) = value.get_mut("ref") {
+        if let Some(Value::Object(last)) = path.last().cloned() {
+            let id = last.get("id").ok_or("Expected an id")?.clone();
+            filter = last.get("where").map(tokens_from_json).transpose()?;
+            *path.last_mut().unwrap() = id;
+        }
+    }
+    Ok(Column {
+        expr: expr_from_json(&value)?,
+        alias,
+        expand,
+        filter,
+    })
+}
+
+// Query sources are written as `{"ref":["Books"]}`, plain names are accepted
+// as well. Only SELECT sources may have an alias.
+fn entity_to_json(entity: &str) -> Value {
+    json!({ "ref": [entity] })
+}
+
+fn source_from_json(query: &Map<String, Value>, key: &str) -> Result<(String, Option<String>)> {
+    let source = match query.get(key) {
+        Some(Value::String(entity)) => return Ok((entity.clone(), None)),
+        Some(Value::Object(source)) => source,
+        Some(other) => return Err(format!("Unsupported query source {}", other)),
+        None => return Err(format!("Cannot find {}", key)),
+    };
+    let alias = match source.get("as") {
+        Some(Value::String(alias)) => Some(alias.clone()),
+        Some(other) => return Err(format!("Expected an alias, found {}", other)),
+        None => None,
+    };
+    let path = match source.get("ref") {
+        Some(path) if source.len() == 1 + alias.iter().count() => strings(path)?,
+        _ => {
+            return Err(format!(
+                "Unsupported query source {}",
+                Value::Object(source.clone())
+            ))
+        }
+    };
+    match path.as_slice() {
+        [entity] => Ok((entity.clone(), alias)),
+        _ => Err(format!("Unsupported query source {}", source["ref"])),
+    }
+}
+
+fn entity_from_json(query: &Map<String, Value>, key: &str) -> Result<String> {
+    match source_from_json(query, key)? {
+        (entity, None) => Ok(entity),
+        (_, Some(alias)) => Err(format!("Unsupported alias {}", alias)),
+    }
+}
+
+fn filter_to_json(query: &mut Map<String, Value>, filter: &Option<Expr>) {
+    if let Some(filter) = filter {
+        query.insert("where".to_string(), tokens_to_json(filter.to_tokens()));
+    }
+}
+
+fn filter_from_json(query: &Map<String, Value>) -> Result<Option<Expr>> {
+    query.get("where").map(tokens_from_json).transpose()
+}
+
+fn order_by_to_json(order_by: &OrderBy) -> Value {
+    let mut value = expr_to_json(&order_by.expr);
+    if let Value::Object(object) = &mut value {
+        match order_by.sort {
+            Some(SortOrder::Asc) => object.insert("sort".to_string(), json!("asc")),
+            Some(SortOrder::Desc) => object.insert("sort".to_string(), json!("desc")),
+            None => None,
+        };
+        match order_by.nulls {
+            Some(Nulls::First) => object.insert("nulls".to_string(), json!("first")),
+            Some(Nulls::Last) => object.insert("nulls".to_string(), json!("last")),
+            None => None,
+        };
+    }
+    value
+}
+
+fn order_by_from_json(value: &Value) -> Result<OrderBy> {
+    let sort = match value.get("sort").and_then(Value::as_str) {
+        Some("asc") => Some(SortOrder::Asc),
+        Some("desc") => Some(SortOrder::Desc),
+        Some(other) => return Err(format!("Unsupported sort order {}", other)),
+        None => None,
+    };
+    let nulls = match value.get("nulls").and_then(Value::as_str) {
+        Some("first") => Some(Nulls::First),
+        Some("last") => Some(Nulls::Last),
+        Some(other) => return Err(format!("Unsupported nulls order {}", other)),
+        None => None,
+    };
+    Ok(OrderBy {
+        expr: expr_from_json(value)?,
+        sort,
+        nulls,
+    })
+}
+
+// Limits are written as `{"rows":{"val":10},"offset":{"val":20}}`, both
+// parts are optional.
+fn limit_from_json(value: &Value) -> Result<Limit> {
+    let count = |key: &str| match value.get(key) {
+        Some(count) => {
+            let count = count.get("val").unwrap_or(count);
+            count
+                .as_u64()
+                .map(Some)
+                .ok_or_else(|| format!("Expected a number of rows, found {}", count))
+        }
+        None => Ok(None),
+    };
+    Ok(Limit {
+        rows: count("rows")?,
+        offset: count("offset")?.unwrap_or(0),
+    })
+}
+
+fn flag(query: &Map<String, Value>, key: &str) -> bool {
+    query.get(key).and_then(Value::as_bool).unwrap_or(false)
+}
+
+fn select_to_json(select: &SELECT) -> Value {
+    let mut query = Map::new();
+    if select.one {
+        query.insert("one".to_string(), json!(true));
+    }
+    if select.distinct {
+        query.insert("distinct".to_string(), json!(true));
+    }
+    let mut from = entity_to_json(&select.from);
+    if let Some(alias) = &select.alias {
+        from["as"] = json!(alias);
+    }
+    query.insert("from".to_string(), from);
+    if !select.columns.is_empty() {
+        let columns = select.columns.iter().map(column_to_json).collect();
+        query.insert("columns".to_string(), Value::Array(columns));
+    }
+    if !select.excluding.is_empty() {
+        query.insert("excluding".to_string(), json!(select.excluding));
+    }
+    filter_to_json(&mut query, &select.filter);
+    if !select.group_by.is_empty() {
+        let group_by = select.group_by.iter().map(expr_to_json).collect();
+        query.insert("groupBy".to_string(), Value::Array(group_by));
+    }
+    if let Some(having) = &select.having {
+        query.insert("having".to_string(), tokens_to_json(having.to_tokens()));
+    }
+    if !select.order_by.is_empty() {
+        let order_by = select.order_by.iter().map(order_by_to_json).collect();
+        query.insert("orderBy".to_string(), Value::Array(order_by));
+    }
+    if let Some(limit) = select.limit {
+        let mut value = json!({});
+        if let Some(rows) = limit.rows {
+            value["rows"] = json!({ "val": rows });
+        }
+        if limit.offset > 0 {
+            value["offset"] = json!({ "val": limit.offset });
+        }
+        query.insert("limit".to_string(), value);
+    }
+    json!({ "SELECT": query })
+}
+
+fn select_from_json(query: &Map<String, Value>) -> Result<SELECT> {
+    let columns = match query.get("columns") {
+        Some(Value::Array(columns)) => columns
+            .iter()
+            .map(column_from_json)
+            .collect::<Result<Vec<Column>>>()?,
+        Some(other) => return Err(format!("Expected columns, found {}", other)),
+        None => vec![],
+    };
+    let order_by = match query.get("orderBy") {
+        Some(Value::Array(order_by)) => order_by
+            .iter()
+            .map(order_by_from_json)
+            .collect::<Result<Vec<OrderBy>>>()?,
+        Some(other) => return Err(format!("Expected orderBy, found {}", other)),
+        None => vec![],
+    };
+    let (from, alias) = source_from_json(query, "from")?;
+    // Joins are only added by `resolve` and have no CQN representation.
+    Ok(SELECT {
+        from,
+        alias,
+        joins: vec![],
+        one: flag(query, "one"),
+        distinct: flag(query, "distinct"),
+        columns,
+        excluding: match query.get("excluding") {
+            Some(excluding) => strings(excluding)?,
+            None => vec![],
+        },
+        filter: filter_from_json(query)?,
+        group_by: match query.get("groupBy") {
+            Some(group_by) => exprs_from_json(group_by)?,
+            None => vec![],
+        },
+        having: query.get("having").map(tokens_from_json).transpose()?,
+        order_by,
+        limit: query.get("limit").map(limit_from_json).transpose()?,
+    })
+}
+
+fn insert_to_json(insert: &INSERT, kind: &str) -> Value {
+    let mut query = Map::new();
+    query.insert("into".to_string(), entity_to_json(&insert.into));
+    if !insert.entries.is_empty() {
+        let entries = insert.entries.iter().cloned().map(Value::Object).collect();
+        query.insert("entries".to_string(), Value::Array(entries));
+    }
+    if !insert.columns.is_empty() {
+        query.insert("columns".to_string(), json!(insert.columns));
+    }
+    if !insert.rows.is_empty() {
+        let rows = insert
+            .rows
+            .iter()
+            .map(|row| Value::Array(row.iter().map(data_to_json).collect()))
+            .collect();
+        query.insert("rows".to_string(), Value::Array(rows));
+    }
+    json!({ kind: query })
+}
+
+// The keys are not part of CQN, where they are taken from the entity.
+fn upsert_to_json(upsert: &UPSERT) -> Value {
+    let mut json = insert_to_json(&upsert.insert, "UPSERT");
+    if !upsert.keys.is_empty() {
+        json["UPSERT"]["keys"] = json!(upsert.keys);
+    }
+    json
+}
+
+fn upsert_from_json(query: &Map<String, Value>) -> Result<UPSERT> {
+    let keys = match query.get("keys") {
+        Some(keys) => strings(keys)?,
+        None => vec![],
+    };
+    Ok(UPSERT {
+        insert: insert_from_json(query)?,
+        keys,
+    })
+}
+
+fn row_from_json(value: &Value) -> Result<Vec<Literal>> {
+    match value {
+        Value::Array(values) => values.iter().map(data_from_json).collect(),
+        other => Err(format!("Expected values, found {}", other)),
+    }
+}
+
+// A single row may be given as `values`.
+fn insert_from_json(query: &Map<String, Value>) -> Result<INSERT> {
+    let mut insert = INSERT::into(&entity_from_json(query, "into")?);
+    if let Some(entries) = query.get("entries") {
+        match entries {
+            Value::Array(entries) if entries.iter().all(Value::is_object) => {
+                insert = insert.entries(entries.clone());
+            }
+            other => return Err(format!("Expected entries, found {}", other)),
+        }
+    }
+    if let Some(columns) = query.get("columns") {
+        insert.columns = strings(columns)?;
+    }
+    if let Some(values) = query.get("values") {
+        insert.rows.push(row_from_json(values)?);
+    }
+    match query.get("rows") {
+        Some(Value::Array(rows)) => {
+            for row in rows {
+                insert.rows.push(row_from_json(row)?);
+            }
+        }
+        Some(other) => return Err(format!("Expected rows, found {}", other)),
+        None => {}
+    }
+    Ok(insert)
+}
+
+fn update_to_json(update: &UPDATE) -> Value {
+    let mut query = Map::new();
+    query.insert("entity".to_string(), entity_to_json(&update.entity));
+    if !update.data.is_empty() {
+        let data = update
+            .data
+            .iter()
+            .map(|(column, value)| (column.clone(), data_to_json(value)))
+            .collect();
+        query.insert("data".to_string(), Value::Object(data));
+    }
+    if !update.with.is_empty() {
+        let with = update
+            .with
+            .iter()
+            .map(|(column, expr)| (column.clone(), expr_to_json(expr)))
+            .collect();
+        query.insert("with".to_string(), Value::Object(with));
+    }
+    filter_to_json(&mut query, &update.filter);
+    json!({ "UPDATE": query })
+}
+
+fn update_from_json(query: &Map<String, Value>) -> Result<UPDATE> {
+    let mut update = UPDATE::entity(&entity_from_json(query, "entity")?);
+    match query.get("data") {
+        Some(Value::Object(data)) => {
+            for (column, value) in data {
+                update.data.push((column.clone(), data_from_json(value)?));
+            }
+        }
+        Some(other) => return Err(format!("Expected data, found {}", other)),
+        None => {}
+    }
+    match query.get("with") {
+        Some(Value::Object(with)) => {
+            for (column, expr) in with {
+                update.with.push((column.clone(), expr_from_json(expr)?));
+            }
+        }
+        Some(other) => return Err(format!("Expected with, found {}", other)),
+        None => {}
+    }
+    update.filter = filter_from_json(query)?;
+    Ok(update)
+}
+
+fn delete_to_json(delete: &DELETE) -> Value {
+    let mut query = Map::new();
+    query.insert("from".to_string(), entity_to_json(&delete.from));
+    filter_to_json(&mut query, &delete.filter);
+    json!({ "DELETE": query })
+}
+
+fn delete_from_json(query: &Map<String, Value>) -> Result<DELETE> {
+    Ok(DELETE {
+        from: entity_from_json(query, "from")?,
+        filter: filter_from_json(query)?,
+    })
+}
+
+fn query_to_json(query: &Query) -> Value {
+    match query {
+        Query::Select(select) => select_to_json(select),
+        Query::Insert(insert) => insert_to_json(insert, "INSERT"),
+        Query::Upsert(upsert) => upsert_to_json(upsert),
+        Query::Update(update) => update_to_json(update),
+        Query::Delete(delete) => delete_to_json(delete),
+    }
+}
+
+fn query_from_json(value: &Value) -> Result<Query> {
+    let (kind, query) = match value {
+        Value::Object(object) if object.len() == 1 => object.iter().next().unwrap(),
+        other => return Err(format!("Expected a query, found {}", other)),
+    };
+    let query = match query {
+        Value::Object(query) => query,
+        other => return Err(format!("Expected a query, found {}", other)),
+    };
+    match kind.as_str() {
+        "SELECT" => select_from_json(query).map(Query::Select),
+        "INSERT" => insert_from_json(query).map(Query::Insert),
+        "UPSERT" => upsert_from_json(query).map(Query::Upsert),
+        "UPDATE" => update_from_json(query).map(Query::Update),
+        "DELETE" => delete_from_json(query).map(Query::Delete),
+        other => Err(format!("Unsupported query {}", other)),
+    }
+}
+
+// A JSON object which keeps the order of its properties, which a
+// `serde_json::Map` sorts by name.
+pub(crate) enum Node {
+    Value(Value),
+    Object(Vec<(String, Node)>),
+}
+
+impl Serialize for Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Node::Value(value) => value.serialize(serializer),
+            Node::Object(properties) => {
+                serializer.collect_map(properties.iter().map(|(name, node)| (name.as_str(), node)))
+            }
+        }
+    }
+}
+
+macro_rules! serde_via_json {
+    ($ty:ty, $to_json:expr, $from_json:expr) => {
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                $to_json(self).serialize(serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> std::result::Result<Self, D::Error> {
+                let value = Value::deserialize(deserializer)?;
+                $from_json(&value).map_err(D::Error::custom)
+            }
+        }
+    };
+}
+
+// Queries of the wrong kind are rejected, e.g. a DELETE read as SELECT.
+macro_rules! serde_query {
+    ($ty:ty, $variant:ident) => {
+        serde_via_json!(
+            $ty,
+            |query: &$ty| query_to_json(&Query::$variant(query.clone())),
+            |value: &Value| match query_from_json(value)? {
+                Query::$variant(query) => Ok(query),
+                _ => Err(format!("Expected {}", stringify!($ty))),
+            }
+        );
+    };
+}
+
+serde_via_json!(Expr, expr_to_json, expr_from_json);
+serde_via_json!(Column, column_to_json, column_from_json);
+serde_via_json!(Query, query_to_json, query_from_json);
+serde_query!(SELECT, Select);
+serde_query!(INSERT, Insert);
+serde_query!(UPSERT, Upsert);
+serde_query!(UPDATE, Update);
+serde_query!(DELETE, Delete);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::BinaryOp;
+    use crate::query::CQN;
+
+    fn roundtrip(query: Query) {
+        let json = serde_json::to_string(&query).unwrap();
+        let parsed: Query = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, query);
+    }
+
+    #[test]
+    fn select_from_json() {
+        let json = r#"{"SELECT":{
+            "from": {"ref": ["my.Books"]},
+            "columns": [
+                "*",
+                {"ref": ["author", "name"], "as": "authorName"},
+                {"func": "count", "args": ["*"], "as": "count"}
+            ],
+            "where": [
+                {"ref": ["stock"]}, ">", {"val": 10}, "and",
+                {"xpr": [{"ref": ["title"]}, "=", {"val": "it's"}, "or", {"ref": ["genre"]}, "is", "not", "null"]}
+            ]
+        }}"#;
+        let select: SELECT = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            select.to_sql().unwrap(),
+            "SELECT *,author.name as authorName,COUNT(*) as count FROM my.Books\n  WHERE stock > 10 AND (title = 'it''s' OR genre IS NOT NULL)"
+        );
+
+        let written = serde_json::to_value(&select).unwrap();
+        assert_eq!(
+            written["SELECT"]["where"],
+            json!([
+                {"ref": ["stock"]}, ">", {"val": 10}, "and",
+                {"xpr": [{"ref": ["title"]}, "=", {"val": "it's"}, "or", {"ref": ["genre"]}, "is", "not", {"val": null}]}
+            ])
+        );
+        assert_eq!(serde_json::from_value::<SELECT>(written).unwrap(), select);
+
+        let res = serde_json::from_str::<SELECT>(r#"{"DELETE":{"from":"Books"}}"#);
+        assert_eq!(res.unwrap_err().to_string(), "Expected SELECT");
+        let res = serde_json::from_str::<SELECT>(
+            r#"{"SELECT":{"from":{"ref":["Books"]},"where":["and"]}}"#,
+        );
+        assert_eq!(res.unwrap_err().to_string(), "Unexpected and");
+        let res = serde_json::from_str::<SELECT>(
+            r#"{"SELECT":{"from":{"ref":["Books"]},"columns":[{"func":"x() --"}]}}"#,
+        );
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "Invalid function name \"x() --\""
+        );
+    }
+
+    #[test]
+    fn limit_without_rows() {
+        let select: SELECT = serde_json::from_str(
+            r#"{"SELECT":{"from":{"ref":["Books"]},"limit":{"offset":{"val":20}}}}"#,
+        )
+        .unwrap();
+        assert_eq!(select.to_sql().unwrap(), "SELECT * FROM Books\n  OFFSET 20");
+        roundtrip(Query::Select(select));
+    }
+
+    #[test]
+    fn typed_literals() {
+        let expr = Expr::Val(Literal::Decimal("0.10".parse().unwrap()));
+        assert_eq!(
+            serde_json::to_value(&expr).unwrap(),
+            json!({ "val": "0.10", "literal": "number" })
+        );
+        let expr: Expr = serde_json::from_str(r#"{"val": 18446744073709551615}"#).unwrap();
+        assert_eq!(expr, Expr::Val(Literal::Decimal(u64::MAX.into())));
+        let res = serde_json::from_str::<Expr>(r#"{"val": "2020-13-01", "literal": "date"}"#);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "invalid date literal \"2020-13-01\""
+        );
+    }
+
+    #[test]
+    fn typed_data() {
+        // Data is read in the order of the column names.
+        let typed = vec![
+            Literal::Time("23:59:00".parse().unwrap()),
+            Literal::Timestamp("2020-02-29T23:59:00.120Z".parse().unwrap()),
+            Literal::DateTime("2020-02-29T23:59:00Z".parse().unwrap()),
+            Literal::Decimal("9.99".parse().unwrap()),
+            Literal::Date("2020-02-29".parse().unwrap()),
+        ];
+        let columns = vec!["at", "createdAt", "modifiedAt", "price", "released"];
+        let insert = INSERT::into("Books")
+            .columns(columns.clone())
+            .values(typed.clone());
+        roundtrip(Query::Insert(insert.clone()));
+        roundtrip(Query::Upsert(UPSERT {
+            insert,
+            keys: vec!["ID".to_string()],
+        }));
+        let mut update = UPDATE::entity("Books").filter(Expr::col("ID").eq(Expr::val(1)));
+        for (column, literal) in columns.iter().zip(typed) {
+            update = update.set(column, literal);
+        }
+        roundtrip(Query::Update(update.clone()));
+        assert_eq!(
+            serde_json::to_value(Query::Update(update)).unwrap()["UPDATE"]["data"]["price"],
+            json!({ "val": "9.99", "literal": "number" })
+        );
+    }
+
+    #[test]
+    fn queries_roundtrip() {
+        roundtrip(Query::Select(
+            SELECT::from("Books")
+                .columns(vec!["ID", "author.name"])
+                .column_as(
+                    Expr::col("price").binary(BinaryOp::Mul, Expr::val(2)),
+                    "double",
+                )
+                .filter(!Expr::col("ID").in_list(Expr::list(vec![1, 2])))
+                .filter(Expr::col("stock").between(Expr::val(1), Expr::val(10).negate())),
+        ));
+        roundtrip(Query::Select(
+            SELECT::one("Books")
+                .distinct()
+                .order_by(OrderBy::desc(Expr::col("stock")).nulls_first())
+                .order_by(OrderBy {
+                    expr: Expr::col("title"),
+                    sort: None,
+                    nulls: None,
+                })
+                .limit(10, 20),
+        ));
+        roundtrip(Query::Select(
+            SELECT::from("Books")
+                .column(Expr::col("author_ID"))
+                .column_as(Expr::max(Expr::col("price")), "price")
+                .group_by(Expr::col("author_ID"))
+                .having(Expr::count(Expr::Star).ge(Expr::val(2))),
+        ));
+        roundtrip(Query::Select(
+            SELECT::from("Books").excluding(vec!["price", "stock"]),
+        ));
+        roundtrip(Query::Select(SELECT::from("Authors").expand(
+            "books",
+            vec![
+                Column::new(Expr::col("title")).alias("name"),
+                Column::expand("genre", vec![]).filter(Expr::col("name").ne(Expr::null())),
+            ],
+        )));
+        let expand = Column::expand("books", vec![]);
+        assert_eq!(column_to_json(&expand)["expand"], json!(["*"]));
+        assert_eq!(column_from_json(&column_to_json(&expand)).unwrap(), expand);
+        assert_eq!(
+            Column::expand("books", vec![Column::new(Expr::Star)]),
+            expand
+        );
+        roundtrip(Query::Select(SELECT::from("Books").filter(Expr::List(
+            vec![
+                Expr::Val(Literal::Decimal("12.50".parse().unwrap())),
+                Expr::Val(Literal::Decimal(u64::MAX.into())),
+                Expr::Val(Literal::Date("2020-02-29".parse().unwrap())),
+                Expr::Val(Literal::Time("23:59:00".parse().unwrap())),
+                Expr::Val(Literal::DateTime("2020-02-29T23:59:00Z".parse().unwrap())),
+                Expr::Val(Literal::Timestamp(
+                    "2020-02-29T23:59:00.120Z".parse().unwrap(),
+                )),
+                Expr::val(1.5),
+            ],
+        ))));
+        roundtrip(Query::Insert(
+            INSERT::into("Books").entries(vec![json!({ "ID": 1, "title": "Emma" })]),
+        ));
+        roundtrip(Query::Upsert(
+            UPSERT::into("Books")
+                .columns(vec!["ID", "stock"])
+                .values(vec![Literal::from(1), Literal::Null])
+                .keys(vec!["ID"]),
+        ));
+        roundtrip(Query::Update(
+            UPDATE::entity("Books")
+                .set("title", "Persuasion")
+                .with(
+                    "stock",
+                    Expr::col("stock").binary(BinaryOp::Sub, Expr::val(1)),
+                )
+                .filter(Expr::col("ID").eq(Expr::val(1))),
+        ));
+        roundtrip(Query::Delete(
+            DELETE::from("Books").filter(Expr::col("stock").eq(Expr::val(0))),
+        ));
+
+        let select: SELECT = serde_json::from_str(
+            r#"{"SELECT":{"from":{"ref":["Books"]},"orderBy":[{"ref":["title"],"sort":"asc"}],"limit":{"rows":{"val":5}}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            select.to_sql().unwrap(),
+            "SELECT * FROM Books\n  ORDER BY title ASC\n  LIMIT 5"
+        );
+
+        let insert: INSERT =
+            serde_json::from_str(r#"{"INSERT":{"into":"Books","columns":["ID"],"values":[1]}}"#)
+                .unwrap();
+        assert_eq!(
+            insert.to_sql().unwrap(),
+            "INSERT INTO Books (ID) VALUES (1)"
+        );
+    }
+}
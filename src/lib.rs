@@ -0,0 +1,27 @@
+#![allow(clippy::upper_case_acronyms)]
+
+pub mod annotations;
+pub mod cql;
+mod csn;
+pub mod data;
+pub mod ddl;
+pub mod dialect;
+pub mod entities;
+pub mod expand;
+pub mod expr;
+mod json;
+pub mod migration;
+pub mod odata;
+pub mod params;
+pub mod query;
+pub mod resolve;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod validate;
+pub mod values;
+
+pub use cql::parse;
+pub use dialect::Dialect;
+pub use expr::Expr;
+pub use params::{PlaceholderStyle, Statement};
+pub use query::{OrderBy, Query, CQN, DELETE, INSERT, SELECT, UPDATE, UPSERT};
@@ -0,0 +1,613 @@
+// Differences between two versions of a model, and the statements migrating
+// a database from the old to the new version. Tables are compared by their
+// columns, so managed associations show up as their foreign keys.
+use crate::ddl::{table_name, Column};
+use crate::dialect::Dialect;
+use crate::entities::{Definitions, Entity};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    AddEntity {
+        entity: String,
+    },
+    RemoveEntity {
+        entity: String,
+    },
+    AddColumn {
+        entity: String,
+        column: Column,
+    },
+    RemoveColumn {
+        entity: String,
+        column: Column,
+    },
+    ChangeType {
+        entity: String,
+        from: Column,
+        to: Column,
+    },
+    // Of strings and binaries.
+    ChangeLength {
+        entity: String,
+        from: Column,
+        to: Column,
+    },
+    ChangeDefault {
+        entity: String,
+        from: Column,
+        to: Column,
+    },
+    ChangeKeys {
+        entity: String,
+        from: Vec<String>,
+        to: Vec<String>,
+    },
+}
+
+// `DECIMAL(9, 2)` is split into `DECIMAL` and `[9, 2]`.
+pub(crate) fn type_parts(column_type: &str) -> (&str, Vec<u64>) {
+    match column_type.split_once('(') {
+        Some((base, args)) => (
+            base,
+            args.trim_end_matches(')')
+                .split(',')
+                .filter_map(|arg| arg.trim().parse().ok())
+                .collect(),
+        ),
+        None => (column_type, vec![]),
+    }
+}
+
+const INTEGERS: [(&str, u64); 4] = [
+    ("TINYINT", 3),
+    ("SMALLINT", 5),
+    ("INTEGER", 10),
+    ("BIGINT", 19),
+];
+
+// Whether all values of the old type fit into the new type.
+fn widens(from: &str, to: &str) -> bool {
+    let digits = |base: &str| {
+        INTEGERS
+            .iter()
+            .find(|(name, _)| *name == base)
+            .map(|(_, digits)| *digits)
+    };
+    let (from, from_args) = type_parts(from);
+    let (to, to_args) = type_parts(to);
+    let integer_digits = |args: &[u64]| match args {
+        [precision] => Some(*precision),
+        [precision, scale] => Some(precision.saturating_sub(*scale)),
+        _ => None,
+    };
+    match (from, to) {
+        ("NVARCHAR", "NCLOB") | ("VARBINARY", "BLOB") => true,
+        (_, "DECIMAL") if to_args.is_empty() => digits(from).is_some() || from == "DECIMAL",
+        ("DECIMAL", "DECIMAL") => {
+            let scale = |args: &[u64]| args.get(1).copied().unwrap_or(0);
+            match (integer_digits(&from_args), integer_digits(&to_args)) {
+                (Some(from_digits), Some(to_digits)) => {
+                    to_digits >= from_digits && scale(&to_args) >= scale(&from_args)
+                }
+                _ => false,
+            }
+        }
+        (_, "DECIMAL") => match (digits(from), integer_digits(&to_args)) {
+            (Some(from_digits), Some(to_digits)) => to_digits >= from_digits,
+            _ => false,
+        },
+        _ => match (digits(from), digits(to)) {
+            (Some(from_digits), Some(to_digits)) => to_digits >= from_digits,
+            _ => false,
+        },
+    }
+}
+
+fn length(column: &Column) -> u64 {
+    type_parts(&column.column_type)
+        .1
+        .first()
+        .copied()
+        .unwrap_or(0)
+}
+
+impl Change {
+    pub fn entity(&self) -> &str {
+        match self {
+            Change::AddEntity { entity }
+            | Change::RemoveEntity { entity }
+            | Change::AddColumn { entity, .. }
+            | Change::RemoveColumn { entity, .. }
+            | Change::ChangeType { entity, .. }
+            | Change::ChangeLength { entity, .. }
+            | Change::ChangeDefault { entity, .. }
+            | Change::ChangeKeys { entity, .. } => entity,
+        }
+    }
+
+    // Whether existing data may be lost. Changing keys is lossy if rows
+    // may no longer be unique.
+    pub fn is_lossy(&self) -> bool {
+        match self {
+            Change::AddEntity { .. } | Change::AddColumn { .. } | Change::ChangeDefault { .. } => {
+                false
+            }
+            Change::RemoveEntity { .. } | Change::RemoveColumn { .. } => true,
+            Change::ChangeType { from, to, .. } => !widens(&from.column_type, &to.column_type),
+            Change::ChangeLength { from, to, .. } => length(to) < length(from),
+            Change::ChangeKeys { from, to, .. } => from.iter().any(|key| !to.contains(key)),
+        }
+    }
+}
+
+impl Change {
+    // Whether the change fails on tables with rows, like adding a NOT NULL
+    // column without a default.
+    pub fn fails_on_rows(&self) -> bool {
+        match self {
+            Change::AddColumn { column, .. } => column.key && column.default.is_none(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::AddEntity { entity } => write!(f, "Add entity {}", entity),
+            Change::RemoveEntity { entity } => write!(f, "Remove entity {}", entity),
+            Change::AddColumn { entity, column } => {
+                write!(f, "Add column {}.{}", entity, column.name)
+            }
+            Change::RemoveColumn { entity, column } => {
+                write!(f, "Remove column {}.{}", entity, column.name)
+            }
+            Change::ChangeType { entity, from, to } => write!(
+                f,
+                "Change type of {}.{} from {} to {}",
+                entity, to.name, from.column_type, to.column_type
+            ),
+            Change::ChangeLength { entity, from, to } => write!(
+                f,
+                "Change length of {}.{} from {} to {}",
+                entity,
+                to.name,
+                length(from),
+                length(to)
+            ),
+            Change::ChangeDefault { entity, from, to } => {
+                let default =
+                    |column: &Column| column.default.clone().unwrap_or_else(|| "none".to_string());
+                write!(
+                    f,
+                    "Change default of {}.{} from {} to {}",
+                    entity,
+                    to.name,
+                    default(from),
+                    default(to)
+                )
+            }
+            Change::ChangeKeys { entity, from, to } => write!(
+                f,
+                "Change keys of {} from ({}) to ({})",
+                entity,
+                from.join(", "),
+                to.join(", ")
+            ),
+        }
+    }
+}
+
+fn tables(definitions: &Definitions) -> impl Iterator<Item = &Entity> {
+    definitions
+        .entities()
+        .filter(|entity| entity.query.is_none() && entity.is_deployed())
+}
+
+fn keys(columns: &[Column]) -> Vec<String> {
+    columns
+        .iter()
+        .filter(|column| column.key)
+        .map(|column| column.name.clone())
+        .collect()
+}
+
+fn column_changes(entity: &str, from: &[Column], to: &[Column], changes: &mut Vec<Change>) {
+    let entity = || entity.to_string();
+    for column in from {
+        if !to.iter().any(|new| new.name == column.name) {
+            changes.push(Change::RemoveColumn {
+                entity: entity(),
+                column: column.clone(),
+            });
+        }
+    }
+    for column in to {
+        let old = match from.iter().find(|old| old.name == column.name) {
+            Some(old) => old,
+            None => {
+                changes.push(Change::AddColumn {
+                    entity: entity(),
+                    column: column.clone(),
+                });
+                continue;
+            }
+        };
+        let (from_base, from_args) = type_parts(&old.column_type);
+        let (to_base, to_args) = type_parts(&column.column_type);
+        if old.column_type != column.column_type {
+            let (from, to) = (old.clone(), column.clone());
+            changes.push(match ["NVARCHAR", "VARBINARY"].contains(&to_base) {
+                true if from_base == to_base && from_args.len() == 1 && to_args.len() == 1 => {
+                    Change::ChangeLength {
+                        entity: entity(),
+                        from,
+                        to,
+                    }
+                }
+                _ => Change::ChangeType {
+                    entity: entity(),
+                    from,
+                    to,
+                },
+            });
+        }
+        if old.default != column.default {
+            changes.push(Change::ChangeDefault {
+                entity: entity(),
+                from: old.clone(),
+                to: column.clone(),
+            });
+        }
+    }
+    let (from_keys, to_keys) = (keys(from), keys(to));
+    if from_keys != to_keys {
+        changes.push(Change::ChangeKeys {
+            entity: entity(),
+            from: from_keys,
+            to: to_keys,
+        });
+    }
+}
+
+// The statements migrating one table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration {
+    pub entity: String,
+    pub changes: Vec<Change>,
+    pub statements: Vec<String>,
+}
+
+impl Migration {
+    pub fn is_lossy(&self) -> bool {
+        self.changes.iter().any(Change::is_lossy)
+    }
+
+    pub fn fails_on_rows(&self) -> bool {
+        self.changes.iter().any(Change::fails_on_rows)
+    }
+}
+
+// For dialects which cannot alter a column the table is copied into a new
+// table with the new columns.
+fn rebuild(
+    old: &[Column],
+    entity: &Entity,
+    definitions: &Definitions,
+    dialect: &dyn Dialect,
+) -> Vec<String> {
+    let table = entity.table_name();
+    let copy = format!("{}__new", table);
+    let columns: Vec<String> = entity
+        .columns(definitions)
+        .into_iter()
+        .filter(|column| old.iter().any(|old| old.name == column.name))
+        .map(|column| dialect.quote(&column.name))
+        .collect();
+    let (table, quoted_copy) = (dialect.quote(&table), dialect.quote(&copy));
+    vec![
+        entity.create_table_named(definitions, &copy, dialect),
+        format!(
+            "INSERT INTO {} ({}) SELECT {} FROM {}",
+            quoted_copy,
+            columns.join(", "),
+            columns.join(", "),
+            table
+        ),
+        format!("DROP TABLE {}", table),
+        format!("ALTER TABLE {} RENAME TO {}", quoted_copy, table),
+    ]
+}
+
+impl Definitions {
+    // The changes of the tables from this version of the model to `new`.
+    pub fn diff(&self, new: &Definitions) -> Vec<Change> {
+        let mut changes = vec![];
+        for entity in tables(self) {
+            if !tables(new).any(|table| table.name == entity.name) {
+                changes.push(Change::RemoveEntity {
+                    entity: entity.name.clone(),
+                });
+            }
+        }
+        for entity in tables(new) {
+            match tables(self).find(|table| table.name == entity.name) {
+                Some(old) => column_changes(
+                    &entity.name,
+                    &old.columns(self),
+                    &entity.columns(new),
+                    &mut changes,
+                ),
+                None => changes.push(Change::AddEntity {
+                    entity: entity.name.clone(),
+                }),
+            }
+        }
+        changes
+    }
+
+    // The statements migrating the tables of this version of the model to
+    // `new`, one migration per table.
+    pub fn migrations(&self, new: &Definitions, dialect: &dyn Dialect) -> Vec<Migration> {
+        let mut migrations: Vec<Migration> = vec![];
+        for change in self.diff(new) {
+            match migrations.last_mut() {
+                Some(migration) if migration.entity == change.entity() => {
+                    migration.changes.push(change)
+                }
+                _ => migrations.push(Migration {
+                    entity: change.entity().to_string(),
+                    changes: vec![change],
+                    statements: vec![],
+                }),
+            }
+        }
+        for migration in migrations.iter_mut() {
+            let table = table_name(&migration.entity);
+            migration.statements = match (&migration.changes[0], new.entity(&migration.entity)) {
+                (Change::RemoveEntity { .. }, _) => {
+                    vec![format!("DROP TABLE {}", dialect.quote(&table))]
+                }
+                (Change::AddEntity { .. }, Some(entity)) => {
+                    vec![entity.create_table_for(new, dialect)]
+                }
+                (_, Some(entity)) => {
+                    let altered: Option<Vec<Vec<String>>> = migration
+                        .changes
+                        .iter()
+                        .map(|change| dialect.alter_table(&table, change))
+                        .collect();
+                    match altered {
+                        Some(statements) => statements.concat(),
+                        None => {
+                            let old = self.entity(&migration.entity).unwrap().columns(self);
+                            rebuild(&old, entity, new, dialect)
+                        }
+                    }
+                }
+                (_, None) => vec![],
+            };
+        }
+        migrations
+    }
+}
+
+// The statements of the migrations as one script, with the changes as
+// comments. Lossy changes are marked with `LOSSY`, changes which fail on
+// tables with rows with `FAILS ON ROWS`.
+pub fn migration_script(migrations: &[Migration]) -> String {
+    let mut script = String::new();
+    for migration in migrations {
+        for change in &migration.changes {
+            match (change.is_lossy(), change.fails_on_rows()) {
+                (true, _) => script.push_str(&format!("-- LOSSY: {}\n", change)),
+                (_, true) => script.push_str(&format!("-- FAILS ON ROWS: {}\n", change)),
+                _ => script.push_str(&format!("-- {}\n", change)),
+            }
+        }
+        for statement in &migration.statements {
+            script.push_str(&format!("{};\n", statement));
+        }
+    }
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Generic;
+    use std::str::FromStr;
+
+    fn get_test_csn(books: &str, others: &str) -> Definitions {
+        let input_str = format!(
+            r#"{{"definitions": {{
+            "my.Authors": {{
+              "kind": "entity",
+              "elements": {{
+                "ID": {{ "key": true, "type": "cds.Integer" }},
+                "name": {{ "type": "cds.String", "length": 100 }}
+              }}
+            }},
+            "my.Books": {{ "kind": "entity", "elements": {{ {} }} }},
+            {}
+          }}}}"#,
+            books, others
+        );
+        Definitions::from_str(&input_str).unwrap()
+    }
+
+    fn get_old_csn() -> Definitions {
+        get_test_csn(
+            r#""ID": { "key": true, "type": "cds.Integer" },
+               "title": { "type": "cds.String", "length": 100 },
+               "descr": { "type": "cds.LargeString" },
+               "price": { "type": "cds.Decimal", "precision": 9, "scale": 2 },
+               "stock": { "type": "cds.Integer" },
+               "author": { "type": "cds.Association", "target": "my.Authors" }"#,
+            r#""my.Orders": { "kind": "entity", "elements": { "ID": { "key": true, "type": "cds.UUID" } } }"#,
+        )
+    }
+
+    fn get_new_csn() -> Definitions {
+        get_test_csn(
+            r#""ID": { "key": true, "type": "cds.Integer" },
+               "title": { "type": "cds.String", "length": 50 },
+               "price": { "type": "cds.Decimal", "precision": 11, "scale": 2 },
+               "stock": { "type": "cds.Integer", "default": { "val": 0 } },
+               "author": { "type": "cds.Association", "target": "my.Authors" },
+               "genre": { "type": "cds.String", "length": 20 }"#,
+            r#""my.Genres": { "kind": "entity", "elements": { "name": { "key": true, "type": "cds.String" } } }"#,
+        )
+    }
+
+    #[test]
+    fn diff() {
+        let changes = get_old_csn().diff(&get_new_csn());
+        let changes: Vec<(String, bool)> = changes
+            .iter()
+            .map(|change| (change.to_string(), change.is_lossy()))
+            .collect();
+        let expected = vec![
+            ("Remove entity my.Orders", true),
+            ("Remove column my.Books.descr", true),
+            ("Change length of my.Books.title from 100 to 50", true),
+            (
+                "Change type of my.Books.price from DECIMAL(9, 2) to DECIMAL(11, 2)",
+                false,
+            ),
+            ("Change default of my.Books.stock from none to 0", false),
+            ("Add column my.Books.genre", false),
+            ("Add entity my.Genres", false),
+        ];
+        let expected: Vec<(String, bool)> = expected
+            .into_iter()
+            .map(|(change, lossy)| (change.to_string(), lossy))
+            .collect();
+        assert_eq!(changes, expected);
+        assert!(widens("SMALLINT", "DECIMAL(6, 1)"));
+        assert!(!widens("DECIMAL(9, 2)", "DECIMAL(9, 1)"));
+        assert!(!widens("BIGINT", "INTEGER"));
+    }
+
+    #[test]
+    fn migrations() {
+        let (old, new) = (get_old_csn(), get_new_csn());
+        let migrations = old.migrations(&new, &Generic);
+        assert!(migrations[1].is_lossy() && !migrations[2].is_lossy());
+        assert_eq!(
+            migration_script(&migrations),
+            "-- LOSSY: Remove entity my.Orders
+DROP TABLE my_Orders;
+-- LOSSY: Remove column my.Books.descr
+-- LOSSY: Change length of my.Books.title from 100 to 50
+-- Change type of my.Books.price from DECIMAL(9, 2) to DECIMAL(11, 2)
+-- Change default of my.Books.stock from none to 0
+-- Add column my.Books.genre
+ALTER TABLE my_Books DROP COLUMN descr;
+ALTER TABLE my_Books ALTER COLUMN title SET DATA TYPE NVARCHAR(50);
+ALTER TABLE my_Books ALTER COLUMN price SET DATA TYPE DECIMAL(11, 2);
+ALTER TABLE my_Books ALTER COLUMN stock SET DEFAULT 0;
+ALTER TABLE my_Books ADD COLUMN genre NVARCHAR(20);
+-- Add entity my.Genres
+CREATE TABLE my_Genres (
+  name NVARCHAR(5000) NOT NULL,
+  PRIMARY KEY(name)
+);
+"
+        );
+    }
+
+    #[test]
+    fn not_null_columns() {
+        let orders = r#""my.Orders": { "kind": "entity", "elements": { "ID": { "key": true, "type": "cds.UUID" } } }"#;
+        let old = get_test_csn(r#""ID": { "key": true, "type": "cds.Integer" }"#, orders);
+        let new = get_test_csn(
+            r#""ID": { "key": true, "type": "cds.Integer" },
+               "code": { "key": true, "type": "cds.String", "length": 3 },
+               "stock": { "type": "cds.Integer" }"#,
+            orders,
+        );
+        let migrations = old.migrations(&new, &Generic);
+        assert!(migrations[0].fails_on_rows() && !migrations[0].is_lossy());
+        assert!(migration_script(&migrations).starts_with(
+            "-- FAILS ON ROWS: Add column my.Books.code\n-- Add column my.Books.stock\n"
+        ));
+    }
+
+    #[cfg(feature = "dialect-postgres")]
+    #[test]
+    fn postgres_casts_types() {
+        let old = get_old_csn();
+        let new = get_test_csn(
+            r#""ID": { "key": true, "type": "cds.Int64" },
+               "title": { "type": "cds.Integer" },
+               "descr": { "type": "cds.LargeString" },
+               "price": { "type": "cds.Decimal", "precision": 9, "scale": 2 },
+               "stock": { "type": "cds.Integer" },
+               "author": { "type": "cds.Association", "target": "my.Authors" }"#,
+            r#""my.Orders": { "kind": "entity", "elements": { "ID": { "key": true, "type": "cds.UUID" } } }"#,
+        );
+        let migrations = old.migrations(&new, &crate::dialect::Postgres);
+        assert_eq!(
+            migrations[0].statements,
+            [
+                r#"ALTER TABLE "my_Books" ALTER COLUMN "ID" TYPE BIGINT"#,
+                r#"ALTER TABLE "my_Books" ALTER COLUMN "title" TYPE INTEGER USING "title"::INTEGER"#
+            ]
+        );
+    }
+
+    #[cfg(feature = "dialect-postgres")]
+    #[test]
+    fn postgres_column_types() {
+        let (old, new) = (get_old_csn(), get_new_csn());
+        let migrations = old.migrations(&new, &crate::dialect::Postgres);
+        assert_eq!(
+            migrations[1].statements[1],
+            r#"ALTER TABLE "my_Books" ALTER COLUMN "title" TYPE VARCHAR(50)"#
+        );
+        assert_eq!(
+            migrations[2].statements,
+            [r#"CREATE TABLE "my_Genres" (
+  "name" VARCHAR(5000) NOT NULL,
+  PRIMARY KEY("name")
+)"#]
+        );
+    }
+
+    #[cfg(feature = "dialect-mysql")]
+    #[test]
+    fn mysql_column_types() {
+        let old = get_old_csn();
+        let new = get_test_csn(
+            r#""ID": { "key": true, "type": "cds.Integer" },
+               "title": { "type": "cds.LargeString" },
+               "descr": { "type": "cds.LargeString" },
+               "price": { "type": "cds.Decimal", "precision": 9, "scale": 2 },
+               "stock": { "type": "cds.Integer" },
+               "author": { "type": "cds.Association", "target": "my.Authors" }"#,
+            r#""my.Orders": { "kind": "entity", "elements": { "ID": { "key": true, "type": "cds.UUID" } } }"#,
+        );
+        let migrations = old.migrations(&new, &crate::dialect::MySql);
+        assert_eq!(
+            migrations[0].statements,
+            ["ALTER TABLE `my_Books` MODIFY COLUMN `title` LONGTEXT"]
+        );
+    }
+
+    #[cfg(feature = "dialect-sqlite")]
+    #[test]
+    fn sqlite_rebuilds_tables() {
+        let (old, new) = (get_old_csn(), get_new_csn());
+        let migrations = old.migrations(&new, &crate::dialect::Sqlite);
+        assert_eq!(
+            migrations[1].statements[1..],
+            [
+                r#"INSERT INTO "my_Books__new" ("ID", "title", "price", "stock", "author_ID") SELECT "ID", "title", "price", "stock", "author_ID" FROM "my_Books""#,
+                r#"DROP TABLE "my_Books""#,
+                r#"ALTER TABLE "my_Books__new" RENAME TO "my_Books""#
+            ]
+        );
+    }
+}
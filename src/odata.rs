@@ -0,0 +1,834 @@
+// OData V4 service metadata (CSDL) of services, as served at `$metadata`.
+// The entities of a service are named like `CatalogService.Books`.
+use crate::annotations::{AnnotationName, AnnotationValue, Annotations};
+use crate::entities::*;
+use crate::json::Node;
+use serde_json::{json, Value};
+
+// The vocabularies annotations may refer to by their alias, e.g.
+// `@UI.LineItem`.
+const VOCABULARIES: [(&str, &str, &str); 10] = [
+    (
+        "Aggregation",
+        "Org.OData.Aggregation.V1",
+        "https://oasis-tcs.github.io/odata-vocabularies/vocabularies/Org.OData.Aggregation.V1.xml",
+    ),
+    (
+        "Capabilities",
+        "Org.OData.Capabilities.V1",
+        "https://oasis-tcs.github.io/odata-vocabularies/vocabularies/Org.OData.Capabilities.V1.xml",
+    ),
+    (
+        "Core",
+        "Org.OData.Core.V1",
+        "https://oasis-tcs.github.io/odata-vocabularies/vocabularies/Org.OData.Core.V1.xml",
+    ),
+    (
+        "Measures",
+        "Org.OData.Measures.V1",
+        "https://oasis-tcs.github.io/odata-vocabularies/vocabularies/Org.OData.Measures.V1.xml",
+    ),
+    (
+        "Validation",
+        "Org.OData.Validation.V1",
+        "https://oasis-tcs.github.io/odata-vocabularies/vocabularies/Org.OData.Validation.V1.xml",
+    ),
+    (
+        "Analytics",
+        "com.sap.vocabularies.Analytics.v1",
+        "https://sap.github.io/odata-vocabularies/vocabularies/Analytics.xml",
+    ),
+    (
+        "Common",
+        "com.sap.vocabularies.Common.v1",
+        "https://sap.github.io/odata-vocabularies/vocabularies/Common.xml",
+    ),
+    (
+        "Communication",
+        "com.sap.vocabularies.Communication.v1",
+        "https://sap.github.io/odata-vocabularies/vocabularies/Communication.xml",
+    ),
+    (
+        "PersonalData",
+        "com.sap.vocabularies.PersonalData.v1",
+        "https://sap.github.io/odata-vocabularies/vocabularies/PersonalData.xml",
+    ),
+    (
+        "UI",
+        "com.sap.vocabularies.UI.v1",
+        "https://sap.github.io/odata-vocabularies/vocabularies/UI.xml",
+    ),
+];
+
+// The term of an annotation, `None` for annotations without one. Some CDS
+// annotations are shortcuts for terms.
+fn term_of(name: &str) -> Option<String> {
+    match name {
+        "title" => Some("Common.Label".to_string()),
+        "description" => Some("Core.Description".to_string()),
+        name => {
+            let (alias, _) = name.split_once('.')?;
+            VOCABULARIES
+                .iter()
+                .any(|(vocabulary, _, _)| *vocabulary == alias)
+                .then(|| name.to_string())
+        }
+    }
+}
+
+struct Term<'a> {
+    term: String,
+    qualifier: Option<&'a str>,
+    value: &'a AnnotationValue,
+}
+
+fn terms_of(annotations: &Annotations) -> Vec<Term<'_>> {
+    annotations
+        .iter()
+        .filter_map(|(name, value)| {
+            let name = AnnotationName::parse(name);
+            Some(Term {
+                term: term_of(name.term)?,
+                qualifier: name.qualifier,
+                value,
+            })
+        })
+        .collect()
+}
+
+// `{"#": "TextOnly"}` of `@UI.TextArrangement` is the member
+// `UI.TextArrangementType/TextOnly`.
+fn enum_member(term: &str, symbol: &str) -> String {
+    format!("{}Type/{}", term, symbol)
+}
+
+struct Property<'a> {
+    name: String,
+    kind: &'a ElementKind,
+    key: bool,
+    annotations: Option<&'a Annotations>,
+}
+
+struct Navigation<'a> {
+    name: &'a str,
+    target: String,
+    many: bool,
+    // The foreign keys and the keys of the target they refer to.
+    constraints: Vec<(String, String)>,
+    annotations: &'a Annotations,
+}
+
+struct EntityType<'a> {
+    name: String,
+    entity: &'a Entity,
+    properties: Vec<Property<'a>>,
+    navigations: Vec<Navigation<'a>>,
+}
+
+impl<'a> EntityType<'a> {
+    fn keys(&self) -> impl Iterator<Item = &str> {
+        self.properties
+            .iter()
+            .filter(|property| property.key)
+            .map(|property| property.name.as_str())
+    }
+}
+
+// Structured elements are flattened like their columns, e.g. `address_city`.
+fn add_properties<'a>(
+    properties: &mut Vec<Property<'a>>,
+    name: String,
+    key: bool,
+    element: &'a Element,
+) {
+    match &element.kind {
+        ElementKind::Structured(structured) => {
+            for child in &structured.elements {
+                let child_name = format!("{}_{}", name, child.name);
+                add_properties(properties, child_name, key || child.key, child);
+            }
+        }
+        ElementKind::Association(_) | ElementKind::Composition(_) => {}
+        kind => properties.push(Property {
+            name,
+            kind,
+            key,
+            annotations: Some(&element.annotations),
+        }),
+    }
+}
+
+// `Edm` types and their facets.
+fn edm_type(kind: &ElementKind) -> (String, Vec<(&'static str, u64)>) {
+    if let ElementKind::Array(array) = kind {
+        let (items, facets) = edm_type(&array.items);
+        return (format!("Collection({})", items), facets);
+    }
+    let facet = |name, value: Option<u64>| value.map(|value| (name, value));
+    let (edm_type, facets) = match kind {
+        ElementKind::UUID(_) => ("Edm.Guid", vec![]),
+        ElementKind::Boolean(_) => ("Edm.Boolean", vec![]),
+        ElementKind::Integer(_) | ElementKind::Int32(_) => ("Edm.Int32", vec![]),
+        ElementKind::String(a) => (
+            "Edm.String",
+            facet("MaxLength", a.length).into_iter().collect(),
+        ),
+        ElementKind::LargeString(_) => ("Edm.String", vec![]),
+        ElementKind::Decimal(a) => {
+            let precision = facet("Precision", a.precision.map(u64::from));
+            let scale = facet("Scale", a.scale.map(u64::from));
+            ("Edm.Decimal", precision.into_iter().chain(scale).collect())
+        }
+        ElementKind::Double(_) => ("Edm.Double", vec![]),
+        ElementKind::Int16(_) => ("Edm.Int16", vec![]),
+        ElementKind::Int64(_) => ("Edm.Int64", vec![]),
+        ElementKind::UInt8(_) => ("Edm.Byte", vec![]),
+        ElementKind::Date(_) => ("Edm.Date", vec![]),
+        ElementKind::Time(_) => ("Edm.TimeOfDay", vec![]),
+        ElementKind::DateTime(_) => ("Edm.DateTimeOffset", vec![]),
+        ElementKind::Timestamp(_) => ("Edm.DateTimeOffset", vec![("Precision", 7)]),
+        ElementKind::Binary(a) => (
+            "Edm.Binary",
+            facet("MaxLength", a.length).into_iter().collect(),
+        ),
+        ElementKind::LargeBinary(_) => ("Edm.Binary", vec![]),
+        ElementKind::Association(_)
+        | ElementKind::Composition(_)
+        | ElementKind::Structured(_)
+        | ElementKind::Array(_) => ("Edm.String", vec![]),
+    };
+    (edm_type.to_string(), facets)
+}
+
+impl Service {
+    // The entities of the service, which are named after it, but not after a
+    // service within it.
+    fn entities<'a>(&self, definitions: &'a Definitions) -> Vec<(String, &'a Entity)> {
+        let services: Vec<&str> = definitions
+            .definitions()
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Service(service) => Some(service.name.as_str()),
+                _ => None,
+            })
+            .collect();
+        definitions
+            .entities()
+            .filter_map(|entity| {
+                let name = entity.name.strip_prefix(&self.name)?.strip_prefix('.')?;
+                let nested = services.iter().any(|service| {
+                    service.len() > self.name.len()
+                        && entity.name.starts_with(service)
+                        && entity.name[service.len()..].starts_with('.')
+                });
+                match nested {
+                    true => None,
+                    false => Some((name.replace('.', "_"), entity)),
+                }
+            })
+            .collect()
+    }
+
+    fn entity_types<'a>(&self, definitions: &'a Definitions) -> Vec<EntityType<'a>> {
+        let entities = self.entities(definitions);
+        // Associations to entities outside of the service are redirected to
+        // a projection on them.
+        let exposed = |target: &str| {
+            entities
+                .iter()
+                .find(|(_, entity)| entity.name == target)
+                .or_else(|| {
+                    entities.iter().find(|(_, entity)| match &entity.query {
+                        Some(query) => query.from == target && query.joins.is_empty(),
+                        None => false,
+                    })
+                })
+                .map(|(name, _)| name.clone())
+        };
+        let mut entity_types = vec![];
+        for (name, entity) in &entities {
+            let mut properties = vec![];
+            let mut navigations = vec![];
+            for element in &entity.elements {
+                let association = match &element.kind {
+                    ElementKind::Association(a) | ElementKind::Composition(a) => a,
+                    _ => {
+                        add_properties(&mut properties, element.name.clone(), element.key, element);
+                        continue;
+                    }
+                };
+                let mut constraints = vec![];
+                let target = definitions.entity(&association.target);
+                if let (true, false, Some(target)) =
+                    (association.is_managed(), association.is_to_many(), target)
+                {
+                    let keys: Vec<String> = match &association.keys {
+                        Some(keys) => keys.iter().map(|key| key.reference.join(".")).collect(),
+                        None => target.keys().map(|key| key.name.clone()).collect(),
+                    };
+                    for key in keys {
+                        let kind = match target.element(&key) {
+                            Some(Element {
+                                kind: ElementKind::Association(_) | ElementKind::Composition(_),
+                                ..
+                            })
+                            | None => continue,
+                            Some(element) => &element.kind,
+                        };
+                        let name = format!("{}_{}", element.name, key);
+                        properties.push(Property {
+                            name: name.clone(),
+                            kind,
+                            key: element.key,
+                            annotations: None,
+                        });
+                        constraints.push((name, key));
+                    }
+                }
+                if let Some(target) = exposed(&association.target) {
+                    navigations.push(Navigation {
+                        name: &element.name,
+                        target,
+                        many: association.is_to_many(),
+                        constraints,
+                        annotations: &element.annotations,
+                    });
+                }
+            }
+            entity_types.push(EntityType {
+                name: name.clone(),
+                entity,
+                properties,
+                navigations,
+            });
+        }
+        entity_types
+    }
+
+    // The vocabularies the annotations of the service and its entity types
+    // refer to.
+    fn vocabularies(&self, entity_types: &[EntityType]) -> Vec<(&str, &str, &str)> {
+        let mut annotations = vec![&self.annotations];
+        for entity_type in entity_types {
+            annotations.push(&entity_type.entity.annotations);
+            annotations.extend(entity_type.properties.iter().filter_map(|p| p.annotations));
+            annotations.extend(entity_type.navigations.iter().map(|n| n.annotations));
+        }
+        let aliases: Vec<String> = annotations
+            .into_iter()
+            .flat_map(terms_of)
+            .filter_map(|term| Some(term.term.split_once('.')?.0.to_string()))
+            .collect();
+        VOCABULARIES
+            .iter()
+            .filter(|(alias, _, _)| aliases.iter().any(|a| a == alias))
+            .copied()
+            .collect()
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// A constant annotation value, e.g. `String` and `Books`.
+fn xml_constant(term: &str, value: &AnnotationValue) -> Option<(&'static str, String)> {
+    let constant = match value {
+        AnnotationValue::Bool(b) => ("Bool", b.to_string()),
+        AnnotationValue::Integer(i) => ("Int", i.to_string()),
+        AnnotationValue::Number(n) => ("Decimal", n.to_string()),
+        AnnotationValue::String(s) => ("String", escape(s)),
+        AnnotationValue::Enum { symbol } => ("EnumMember", escape(&enum_member(term, symbol))),
+        AnnotationValue::Expression { path } => ("Path", escape(path)),
+        _ => return None,
+    };
+    Some(constant)
+}
+
+// Constants are written as attributes like `String="Books"`.
+fn xml_attribute(term: &str, value: &AnnotationValue) -> Option<String> {
+    let (name, text) = xml_constant(term, value)?;
+    Some(format!("{}=\"{}\"", name, text))
+}
+
+fn xml_value(lines: &mut Vec<String>, indent: usize, term: &str, value: &AnnotationValue) {
+    let pad = "  ".repeat(indent);
+    match value {
+        AnnotationValue::Null => lines.push(format!("{}<Null/>", pad)),
+        AnnotationValue::Array(items) => {
+            lines.push(format!("{}<Collection>", pad));
+            for item in items {
+                xml_value(lines, indent + 1, term, item);
+            }
+            lines.push(format!("{}</Collection>", pad));
+        }
+        AnnotationValue::Record(record) => {
+            let record_type = match record.get("$Type").and_then(AnnotationValue::as_str) {
+                Some(record_type) => format!(" Type=\"{}\"", escape(record_type)),
+                None => String::new(),
+            };
+            lines.push(format!("{}<Record{}>", pad, record_type));
+            for (property, value) in record {
+                if property.starts_with('$') || property.starts_with('@') {
+                    continue;
+                }
+                let property = format!("{}  <PropertyValue Property=\"{}\"", pad, escape(property));
+                match xml_attribute(term, value) {
+                    Some(attribute) => lines.push(format!("{} {}/>", property, attribute)),
+                    None => {
+                        lines.push(format!("{}>", property));
+                        xml_value(lines, indent + 2, term, value);
+                        lines.push(format!("{}  </PropertyValue>", pad));
+                    }
+                }
+            }
+            lines.push(format!("{}</Record>", pad));
+        }
+        value => {
+            // Constants in collections are elements like `<String>Books</String>`.
+            if let Some((name, text)) = xml_constant(term, value) {
+                lines.push(format!("{}<{}>{}</{}>", pad, name, text, name));
+            }
+        }
+    }
+}
+
+fn xml_annotations(lines: &mut Vec<String>, indent: usize, annotations: &Annotations) {
+    let pad = "  ".repeat(indent);
+    for term in terms_of(annotations) {
+        let mut annotation = format!("{}<Annotation Term=\"{}\"", pad, term.term);
+        if let Some(qualifier) = term.qualifier {
+            annotation.push_str(&format!(" Qualifier=\"{}\"", escape(qualifier)));
+        }
+        match xml_attribute(&term.term, term.value) {
+            Some(attribute) => lines.push(format!("{} {}/>", annotation, attribute)),
+            None => {
+                lines.push(format!("{}>", annotation));
+                xml_value(lines, indent + 1, &term.term, term.value);
+                lines.push(format!("{}</Annotation>", pad));
+            }
+        }
+    }
+}
+
+fn json_value(term: &str, value: &AnnotationValue) -> Value {
+    match value {
+        AnnotationValue::Enum { symbol } => json!({ "$EnumMember": enum_member(term, symbol) }),
+        AnnotationValue::Expression { path } => json!({ "$Path": path }),
+        AnnotationValue::Array(items) => {
+            Value::Array(items.iter().map(|item| json_value(term, item)).collect())
+        }
+        AnnotationValue::Record(record) => Value::Object(
+            record
+                .iter()
+                .filter(|(property, _)| !property.starts_with('@'))
+                .map(|(property, value)| (property.clone(), json_value(term, value)))
+                .collect(),
+        ),
+        value => serde_json::to_value(value).unwrap_or(Value::Null),
+    }
+}
+
+fn json_annotations(properties: &mut Vec<(String, Node)>, annotations: &Annotations) {
+    for term in terms_of(annotations) {
+        let name = match term.qualifier {
+            Some(qualifier) => format!("@{}#{}", term.term, qualifier),
+            None => format!("@{}", term.term),
+        };
+        properties.push((name, Node::Value(json_value(&term.term, term.value))));
+    }
+}
+
+impl Service {
+    // The `$metadata` document in the XML format of CSDL.
+    pub fn csdl_xml(&self, definitions: &Definitions) -> String {
+        let entity_types = self.entity_types(definitions);
+        let mut lines = vec![
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>".to_string(),
+            "<edmx:Edmx Version=\"4.0\" xmlns:edmx=\"http://docs.oasis-open.org/odata/ns/edmx\">"
+                .to_string(),
+        ];
+        for (alias, namespace, uri) in self.vocabularies(&entity_types) {
+            lines.push(format!("  <edmx:Reference Uri=\"{}\">", uri));
+            lines.push(format!(
+                "    <edmx:Include Alias=\"{}\" Namespace=\"{}\"/>",
+                alias, namespace
+            ));
+            lines.push("  </edmx:Reference>".to_string());
+        }
+        lines.push("  <edmx:DataServices>".to_string());
+        lines.push(format!(
+            "    <Schema Namespace=\"{}\" xmlns=\"http://docs.oasis-open.org/odata/ns/edm\">",
+            self.name
+        ));
+        lines.push("      <EntityContainer Name=\"EntityContainer\">".to_string());
+        for entity_type in &entity_types {
+            let entity_set = format!(
+                "        <EntitySet Name=\"{}\" EntityType=\"{}.{}\"",
+                entity_type.name, self.name, entity_type.name
+            );
+            if entity_type.navigations.is_empty() {
+                lines.push(format!("{}/>", entity_set));
+                continue;
+            }
+            lines.push(format!("{}>", entity_set));
+            for navigation in &entity_type.navigations {
+                lines.push(format!(
+                    "          <NavigationPropertyBinding Path=\"{}\" Target=\"{}\"/>",
+                    navigation.name, navigation.target
+                ));
+            }
+            lines.push("        </EntitySet>".to_string());
+        }
+        xml_annotations(&mut lines, 4, &self.annotations);
+        lines.push("      </EntityContainer>".to_string());
+        for entity_type in &entity_types {
+            lines.push(format!("      <EntityType Name=\"{}\">", entity_type.name));
+            let keys: Vec<&str> = entity_type.keys().collect();
+            if !keys.is_empty() {
+                lines.push("        <Key>".to_string());
+                for key in keys {
+                    lines.push(format!("          <PropertyRef Name=\"{}\"/>", key));
+                }
+                lines.push("        </Key>".to_string());
+            }
+            for property in &entity_type.properties {
+                let (edm_type, facets) = edm_type(property.kind);
+                let mut line = format!(
+                    "        <Property Name=\"{}\" Type=\"{}\"",
+                    property.name, edm_type
+                );
+                if property.key {
+                    line.push_str(" Nullable=\"false\"");
+                }
+                for (facet, value) in facets {
+                    line.push_str(&format!(" {}=\"{}\"", facet, value));
+                }
+                let mut annotations = vec![];
+                if let Some(property_annotations) = property.annotations {
+                    xml_annotations(&mut annotations, 5, property_annotations);
+                }
+                if annotations.is_empty() {
+                    lines.push(format!("{}/>", line));
+                } else {
+                    lines.push(format!("{}>", line));
+                    lines.extend(annotations);
+                    lines.push("        </Property>".to_string());
+                }
+            }
+            for navigation in &entity_type.navigations {
+                let target = format!("{}.{}", self.name, navigation.target);
+                let target = match navigation.many {
+                    true => format!("Collection({})", target),
+                    false => target,
+                };
+                let line = format!(
+                    "        <NavigationProperty Name=\"{}\" Type=\"{}\"",
+                    navigation.name, target
+                );
+                let mut children = vec![];
+                for (property, referenced) in &navigation.constraints {
+                    children.push(format!(
+                        "          <ReferentialConstraint Property=\"{}\" ReferencedProperty=\"{}\"/>",
+                        property, referenced
+                    ));
+                }
+                xml_annotations(&mut children, 5, navigation.annotations);
+                if children.is_empty() {
+                    lines.push(format!("{}/>", line));
+                } else {
+                    lines.push(format!("{}>", line));
+                    lines.extend(children);
+                    lines.push("        </NavigationProperty>".to_string());
+                }
+            }
+            xml_annotations(&mut lines, 4, &entity_type.entity.annotations);
+            lines.push("      </EntityType>".to_string());
+        }
+        lines.push("    </Schema>".to_string());
+        lines.push("  </edmx:DataServices>".to_string());
+        lines.push("</edmx:Edmx>".to_string());
+        lines.join("\n")
+    }
+
+    // The `$metadata` document in the JSON format of CSDL.
+    pub fn csdl_json(&self, definitions: &Definitions) -> String {
+        let entity_types = self.entity_types(definitions);
+        let mut document = vec![("$Version".to_string(), Node::Value(json!("4.0")))];
+        let references: Vec<(String, Node)> = self
+            .vocabularies(&entity_types)
+            .into_iter()
+            .map(|(alias, namespace, uri)| {
+                let include = json!([{ "$Namespace": namespace, "$Alias": alias }]);
+                let reference = vec![("$Include".to_string(), Node::Value(include))];
+                (uri.to_string(), Node::Object(reference))
+            })
+            .collect();
+        if !references.is_empty() {
+            document.push(("$Reference".to_string(), Node::Object(references)));
+        }
+        document.push((
+            "$EntityContainer".to_string(),
+            Node::Value(json!(format!("{}.EntityContainer", self.name))),
+        ));
+
+        let mut schema = vec![];
+        for entity_type in &entity_types {
+            let mut members = vec![("$Kind".to_string(), Node::Value(json!("EntityType")))];
+            let keys: Vec<&str> = entity_type.keys().collect();
+            if !keys.is_empty() {
+                members.push(("$Key".to_string(), Node::Value(json!(keys))));
+            }
+            for property in &entity_type.properties {
+                let (edm_type, facets) = edm_type(property.kind);
+                let mut node = vec![];
+                let edm_type = match edm_type.strip_prefix("Collection(") {
+                    Some(items) => {
+                        node.push(("$Collection".to_string(), Node::Value(json!(true))));
+                        items.trim_end_matches(')').to_string()
+                    }
+                    None => edm_type,
+                };
+                // `Edm.String` is the default type.
+                if edm_type != "Edm.String" {
+                    node.push(("$Type".to_string(), Node::Value(json!(edm_type))));
+                }
+                if property.key {
+                    node.push(("$Nullable".to_string(), Node::Value(json!(false))));
+                }
+                for (facet, value) in facets {
+                    node.push((format!("${}", facet), Node::Value(json!(value))));
+                }
+                if let Some(annotations) = property.annotations {
+                    json_annotations(&mut node, annotations);
+                }
+                members.push((property.name.clone(), Node::Object(node)));
+            }
+            for navigation in &entity_type.navigations {
+                let mut node = vec![
+                    (
+                        "$Kind".to_string(),
+                        Node::Value(json!("NavigationProperty")),
+                    ),
+                    (
+                        "$Type".to_string(),
+                        Node::Value(json!(format!("{}.{}", self.name, navigation.target))),
+                    ),
+                ];
+                if navigation.many {
+                    node.push(("$Collection".to_string(), Node::Value(json!(true))));
+                }
+                if !navigation.constraints.is_empty() {
+                    let constraints = navigation
+                        .constraints
+                        .iter()
+                        .map(|(property, referenced)| {
+                            (property.clone(), Node::Value(json!(referenced)))
+                        })
+                        .collect();
+                    node.push((
+                        "$ReferentialConstraint".to_string(),
+                        Node::Object(constraints),
+                    ));
+                }
+                json_annotations(&mut node, navigation.annotations);
+                members.push((navigation.name.to_string(), Node::Object(node)));
+            }
+            json_annotations(&mut members, &entity_type.entity.annotations);
+            schema.push((entity_type.name.clone(), Node::Object(members)));
+        }
+
+        let mut container = vec![("$Kind".to_string(), Node::Value(json!("EntityContainer")))];
+        for entity_type in &entity_types {
+            let mut entity_set = vec![
+                ("$Collection".to_string(), Node::Value(json!(true))),
+                (
+                    "$Type".to_string(),
+                    Node::Value(json!(format!("{}.{}", self.name, entity_type.name))),
+                ),
+            ];
+            if !entity_type.navigations.is_empty() {
+                let bindings = entity_type
+                    .navigations
+                    .iter()
+                    .map(|navigation| {
+                        (
+                            navigation.name.to_string(),
+                            Node::Value(json!(navigation.target)),
+                        )
+                    })
+                    .collect();
+                entity_set.push((
+                    "$NavigationPropertyBinding".to_string(),
+                    Node::Object(bindings),
+                ));
+            }
+            container.push((entity_type.name.clone(), Node::Object(entity_set)));
+        }
+        json_annotations(&mut container, &self.annotations);
+        schema.push(("EntityContainer".to_string(), Node::Object(container)));
+
+        document.push((self.name.clone(), Node::Object(schema)));
+        serde_json::to_string_pretty(&Node::Object(document)).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn get_test_csn() -> Definitions {
+        let input_str = r##"{"definitions": {
+            "my.Books": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.UUID" },
+                "title": { "type": "cds.String", "length": 111, "@title": "Title" },
+                "price": { "type": "cds.Decimal", "precision": 9, "scale": 2 },
+                "author": { "type": "cds.Association", "target": "my.Authors" }
+              }
+            },
+            "my.Authors": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "name": { "type": "cds.String", "@Core.Immutable": true },
+                "books": {
+                  "type": "cds.Association", "target": "my.Books", "cardinality": { "max": "*" },
+                  "on": [{ "ref": ["books", "author"] }, "=", { "ref": ["$self"] }]
+                }
+              }
+            },
+            "CatalogService": { "kind": "service", "@path": "/browse" },
+            "CatalogService.Books": {
+              "kind": "entity",
+              "@UI.LineItem": [{ "Value": { "=": "title" } }, { "$Type": "UI.DataField", "Value": { "=": "price" } }],
+              "@UI.TextArrangement#short": { "#": "TextOnly" },
+              "projection": { "from": { "ref": ["my.Books"] } }
+            },
+            "CatalogService.Authors": {
+              "kind": "entity",
+              "projection": { "from": { "ref": ["my.Authors"] } }
+            }
+          }}"##;
+        Definitions::from_str(input_str).unwrap()
+    }
+
+    #[test]
+    fn csdl_xml() {
+        let definitions = get_test_csn();
+        let service = definitions.service("CatalogService").unwrap();
+        assert_eq!(
+            service.csdl_xml(&definitions),
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<edmx:Edmx Version="4.0" xmlns:edmx="http://docs.oasis-open.org/odata/ns/edmx">
+  <edmx:Reference Uri="https://oasis-tcs.github.io/odata-vocabularies/vocabularies/Org.OData.Core.V1.xml">
+    <edmx:Include Alias="Core" Namespace="Org.OData.Core.V1"/>
+  </edmx:Reference>
+  <edmx:Reference Uri="https://sap.github.io/odata-vocabularies/vocabularies/Common.xml">
+    <edmx:Include Alias="Common" Namespace="com.sap.vocabularies.Common.v1"/>
+  </edmx:Reference>
+  <edmx:Reference Uri="https://sap.github.io/odata-vocabularies/vocabularies/UI.xml">
+    <edmx:Include Alias="UI" Namespace="com.sap.vocabularies.UI.v1"/>
+  </edmx:Reference>
+  <edmx:DataServices>
+    <Schema Namespace="CatalogService" xmlns="http://docs.oasis-open.org/odata/ns/edm">
+      <EntityContainer Name="EntityContainer">
+        <EntitySet Name="Books" EntityType="CatalogService.Books">
+          <NavigationPropertyBinding Path="author" Target="Authors"/>
+        </EntitySet>
+        <EntitySet Name="Authors" EntityType="CatalogService.Authors">
+          <NavigationPropertyBinding Path="books" Target="Books"/>
+        </EntitySet>
+      </EntityContainer>
+      <EntityType Name="Books">
+        <Key>
+          <PropertyRef Name="ID"/>
+        </Key>
+        <Property Name="ID" Type="Edm.Guid" Nullable="false"/>
+        <Property Name="title" Type="Edm.String" MaxLength="111">
+          <Annotation Term="Common.Label" String="Title"/>
+        </Property>
+        <Property Name="price" Type="Edm.Decimal" Precision="9" Scale="2"/>
+        <Property Name="author_ID" Type="Edm.Int32"/>
+        <NavigationProperty Name="author" Type="CatalogService.Authors">
+          <ReferentialConstraint Property="author_ID" ReferencedProperty="ID"/>
+        </NavigationProperty>
+        <Annotation Term="UI.LineItem">
+          <Collection>
+            <Record>
+              <PropertyValue Property="Value" Path="title"/>
+            </Record>
+            <Record Type="UI.DataField">
+              <PropertyValue Property="Value" Path="price"/>
+            </Record>
+          </Collection>
+        </Annotation>
+        <Annotation Term="UI.TextArrangement" Qualifier="short" EnumMember="UI.TextArrangementType/TextOnly"/>
+      </EntityType>
+      <EntityType Name="Authors">
+        <Key>
+          <PropertyRef Name="ID"/>
+        </Key>
+        <Property Name="ID" Type="Edm.Int32" Nullable="false"/>
+        <Property Name="name" Type="Edm.String">
+          <Annotation Term="Core.Immutable" Bool="true"/>
+        </Property>
+        <NavigationProperty Name="books" Type="Collection(CatalogService.Books)"/>
+      </EntityType>
+    </Schema>
+  </edmx:DataServices>
+</edmx:Edmx>"#
+        );
+    }
+
+    #[test]
+    fn csdl_json() {
+        let definitions = get_test_csn();
+        let service = definitions.service("CatalogService").unwrap();
+        let csdl: Value = serde_json::from_str(&service.csdl_json(&definitions)).unwrap();
+        assert_eq!(csdl["$EntityContainer"], "CatalogService.EntityContainer");
+        let schema = &csdl["CatalogService"];
+        assert_eq!(
+            schema["EntityContainer"]["Books"],
+            json!({
+                "$Collection": true,
+                "$Type": "CatalogService.Books",
+                "$NavigationPropertyBinding": { "author": "Authors" }
+            })
+        );
+        assert_eq!(schema["Books"]["$Key"], json!(["ID"]));
+        assert_eq!(
+            schema["Books"]["ID"],
+            json!({ "$Type": "Edm.Guid", "$Nullable": false })
+        );
+        assert_eq!(
+            schema["Books"]["title"],
+            json!({ "$MaxLength": 111, "@Common.Label": "Title" })
+        );
+        assert_eq!(
+            schema["Books"]["author"],
+            json!({
+                "$Kind": "NavigationProperty",
+                "$Type": "CatalogService.Authors",
+                "$ReferentialConstraint": { "author_ID": "ID" }
+            })
+        );
+        assert_eq!(
+            schema["Books"]["@UI.TextArrangement#short"],
+            json!({ "$EnumMember": "UI.TextArrangementType/TextOnly" })
+        );
+        assert_eq!(
+            schema["Books"]["@UI.LineItem"][1],
+            json!({ "$Type": "UI.DataField", "Value": { "$Path": "price" } })
+        );
+        assert_eq!(schema["Authors"]["books"]["$Collection"], true);
+        assert!(csdl["$Reference"]
+            .get("https://sap.github.io/odata-vocabularies/vocabularies/UI.xml")
+            .is_some());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::dialect::{Dialect, Generic};
+use crate::expr::Literal;
+use crate::resolve::{InvalidQuery, QueryErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaceholderStyle {
+    // `?`
+    Question,
+    // `$1`, `$2`, ...
+    Numbered,
+    // `:p1`, `:p2`, ...
+    Named,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub value: Literal,
+}
+
+// SQL text with its parameters in the order of their placeholders.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub sql: String,
+    pub params: Vec<Param>,
+}
+
+// Collects the values bound while rendering a query. Values which are not
+// safe to inline, like strings and dates, are always bound. `NULL` is always
+// inlined so that `IS NULL` keeps working. Parts which cannot be rendered
+// safely are rejected, see `CQN::to_statement_for`.
+#[derive(Debug, Clone)]
+pub struct Bindings {
+    style: Option<PlaceholderStyle>,
+    bind_all: bool,
+    params: Vec<Param>,
+    dialect: &'static dyn Dialect,
+    error: Option<InvalidQuery>,
+}
+
+impl Bindings {
+    pub fn new(style: PlaceholderStyle) -> Bindings {
+        Bindings {
+            style: Some(style),
+            bind_all: false,
+            params: vec![],
+            dialect: &Generic,
+            error: None,
+        }
+    }
+
+    // Renders all values inline.
+    pub fn inline() -> Bindings {
+        Bindings {
+            style: None,
+            bind_all: false,
+            params: vec![],
+            dialect: &Generic,
+            error: None,
+        }
+    }
+
+    // Binds values with the placeholders of `dialect`.
+    pub fn for_dialect(dialect: &'static dyn Dialect) -> Bindings {
+        Bindings::new(dialect.placeholder_style()).with_dialect(dialect)
+    }
+
+    pub fn with_dialect(mut self, dialect: &'static dyn Dialect) -> Bindings {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn dialect(&self) -> &'static dyn Dialect {
+        self.dialect
+    }
+
+    pub fn identifier(&self, name: &str) -> String {
+        self.dialect.quote(name)
+    }
+
+    // Also binds numbers and booleans, so that statements only differing in
+    // those can be reused.
+    pub fn bind_all(mut self) -> Bindings {
+        self.bind_all = true;
+        self
+    }
+
+    pub fn params(&self) -> &[Param] {
+        &self.params
+    }
+
+    pub fn into_statement(self, sql: String) -> Statement {
+        Statement {
+            sql,
+            params: self.params,
+        }
+    }
+
+    // Keeps the first problem found while rendering.
+    pub fn reject(&mut self, error: InvalidQuery) {
+        self.error.get_or_insert(error);
+    }
+
+    pub fn into_result(self, sql: String) -> Result<Statement, InvalidQuery> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(Statement {
+                sql,
+                params: self.params,
+            }),
+        }
+    }
+
+    pub fn literal(&mut self, literal: &Literal) -> String {
+        let inline = |literal: &Literal| match literal {
+            Literal::Bool(b) => self.dialect.boolean(*b).to_string(),
+            literal => literal.to_sql(),
+        };
+        let style = match self.style {
+            Some(style) => style,
+            None if matches!(literal, Literal::Double(d) if !d.is_finite()) => {
+                self.reject(InvalidQuery::new(
+                    QueryErrorKind::InvalidValue,
+                    format!(
+                        "Cannot inline the double {}, bind it instead",
+                        literal.to_sql()
+                    ),
+                ));
+                return literal.to_sql();
+            }
+            None => return inline(literal),
+        };
+        if *literal == Literal::Null || (literal.is_inline_safe() && !self.bind_all) {
+            return inline(literal);
+        }
+        let position = self.params.len() + 1;
+        let name = format!("p{}", position);
+        let placeholder = match style {
+            PlaceholderStyle::Question => "?".to_string(),
+            PlaceholderStyle::Numbered => format!("${}", position),
+            PlaceholderStyle::Named => format!(":{}", name),
+        };
+        self.params.push(Param {
+            name,
+            value: literal.clone(),
+        });
+        placeholder
+    }
+}
@@ -0,0 +1,964 @@
+use crate::dialect::{values, Dialect};
+use crate::expr::{Expr, Literal};
+use crate::params::{Bindings, PlaceholderStyle, Statement};
+use crate::resolve::{InvalidQuery, QueryErrorKind};
+use serde_json::{Map, Value};
+
+// A column, or with `expand` the nested rows of an association, see
+// `expand.rs`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub expr: Expr,
+    pub alias: Option<String>,
+    pub expand: Option<Vec<Column>>,
+    // The infix filter of an expanded association, `books[stock > 0]`.
+    pub filter: Option<Expr>,
+}
+
+// `{ * }` is kept as no columns, which also stand for all elements.
+pub(crate) fn all_columns(columns: Vec<Column>) -> Vec<Column> {
+    match columns.as_slice() {
+        [column] if *column == Column::new(Expr::Star) => vec![],
+        _ => columns,
+    }
+}
+
+impl Column {
+    pub fn new(expr: Expr) -> Column {
+        Column {
+            expr,
+            alias: None,
+            expand: None,
+            filter: None,
+        }
+    }
+
+    // Without columns all elements of the target are expanded.
+    pub fn expand(association: &str, columns: Vec<Column>) -> Column {
+        Column {
+            expand: Some(all_columns(columns)),
+            ..Column::new(Expr::col(association))
+        }
+    }
+
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.alias = Some(alias.to_string());
+        self
+    }
+
+    pub fn filter(mut self, filter: Expr) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn render(&self, bindings: &mut Bindings) -> String {
+        match &self.alias {
+            Some(alias) => format!(
+                "{} as {}",
+                self.expr.render(bindings),
+                bindings.identifier(alias)
+            ),
+            None => self.expr.render(bindings),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+// Without `sort` and `nulls` the database defaults apply.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBy {
+    pub expr: Expr,
+    pub sort: Option<SortOrder>,
+    pub nulls: Option<Nulls>,
+}
+
+impl OrderBy {
+    pub fn asc(expr: Expr) -> OrderBy {
+        OrderBy {
+            expr,
+            sort: Some(SortOrder::Asc),
+            nulls: None,
+        }
+    }
+
+    pub fn desc(expr: Expr) -> OrderBy {
+        OrderBy {
+            expr,
+            sort: Some(SortOrder::Desc),
+            nulls: None,
+        }
+    }
+
+    pub fn nulls_first(mut self) -> Self {
+        self.nulls = Some(Nulls::First);
+        self
+    }
+
+    pub fn nulls_last(mut self) -> Self {
+        self.nulls = Some(Nulls::Last);
+        self
+    }
+
+    // Without NULLS FIRST/LAST in the dialect, NULLs are sorted by a
+    // preceding `expr IS NULL`.
+    pub fn render(&self, bindings: &mut Bindings) -> String {
+        let mut sql = match (self.nulls, bindings.dialect().nulls_order()) {
+            (Some(nulls), false) => {
+                let is_null = format!("{} IS NULL", self.expr.render(bindings));
+                match nulls {
+                    Nulls::First => format!("{} DESC, ", is_null),
+                    Nulls::Last => format!("{}, ", is_null),
+                }
+            }
+            _ => String::new(),
+        };
+        sql.push_str(&self.expr.render(bindings));
+        match self.sort {
+            Some(SortOrder::Asc) => sql.push_str(" ASC"),
+            Some(SortOrder::Desc) => sql.push_str(" DESC"),
+            None => {}
+        }
+        match (self.nulls, bindings.dialect().nulls_order()) {
+            (Some(Nulls::First), true) => sql.push_str(" NULLS FIRST"),
+            (Some(Nulls::Last), true) => sql.push_str(" NULLS LAST"),
+            _ => {}
+        }
+        sql
+    }
+}
+
+// Without `rows` all rows after the offset are selected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limit {
+    pub rows: Option<u64>,
+    pub offset: u64,
+}
+
+// A join added when resolving path expressions, see `resolve.rs`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub table: String,
+    pub alias: String,
+    pub on: Expr,
+}
+
+impl Join {
+    pub fn render(&self, bindings: &mut Bindings) -> String {
+        format!(
+            "LEFT JOIN {} as {} ON {}",
+            bindings.identifier(&self.table),
+            bindings.identifier(&self.alias),
+            self.on.render(bindings)
+        )
+    }
+}
+
+// `one` selects a single row, like `SELECT.one` in CAP.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SELECT {
+    pub from: String,
+    pub alias: Option<String>,
+    pub joins: Vec<Join>,
+    pub one: bool,
+    pub distinct: bool,
+    pub columns: Vec<Column>,
+    pub excluding: Vec<String>,
+    pub filter: Option<Expr>,
+    pub group_by: Vec<Expr>,
+    pub having: Option<Expr>,
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<Limit>,
+}
+
+impl SELECT {
+    pub fn from(entity: &str) -> SELECT {
+        SELECT {
+            from: entity.to_string(),
+            alias: None,
+            joins: vec![],
+            one: false,
+            distinct: false,
+            columns: vec![],
+            excluding: vec![],
+            filter: None,
+            group_by: vec![],
+            having: None,
+            order_by: vec![],
+            limit: None,
+        }
+    }
+
+    pub fn one(entity: &str) -> SELECT {
+        SELECT {
+            one: true,
+            ..SELECT::from(entity)
+        }
+    }
+
+    pub fn distinct(mut self) -> Self {
+        self.distinct = true;
+        self
+    }
+
+    // Columns are `*` or element paths like `author.name`.
+    pub fn columns(mut self, columns: Vec<&str>) -> Self {
+        for column in columns {
+            let expr = match column {
+                "*" => Expr::Star,
+                path => Expr::col(path),
+            };
+            self.columns.push(Column::new(expr));
+        }
+        self
+    }
+
+    // Elements left out of `*`, like `excluding { stock }` in CDS.
+    pub fn excluding(mut self, elements: Vec<&str>) -> Self {
+        self.excluding = elements.into_iter().map(str::to_string).collect();
+        self
+    }
+
+    pub fn column(mut self, expr: Expr) -> Self {
+        self.columns.push(Column::new(expr));
+        self
+    }
+
+    pub fn column_as(mut self, expr: Expr, alias: &str) -> Self {
+        self.columns.push(Column::new(expr).alias(alias));
+        self
+    }
+
+    pub fn expand(mut self, association: &str, columns: Vec<Column>) -> Self {
+        self.columns.push(Column::expand(association, columns));
+        self
+    }
+
+    // Repeated filters are combined with `and`.
+    pub fn filter(mut self, filter: Expr) -> Self {
+        and_filter(&mut self.filter, filter);
+        self
+    }
+
+    pub fn group_by(mut self, expr: Expr) -> Self {
+        self.group_by.push(expr);
+        self
+    }
+
+    // Repeated conditions are combined with `and`.
+    pub fn having(mut self, having: Expr) -> Self {
+        and_filter(&mut self.having, having);
+        self
+    }
+
+    pub fn order_by(mut self, order_by: OrderBy) -> Self {
+        self.order_by.push(order_by);
+        self
+    }
+
+    pub fn limit(mut self, rows: u64, offset: u64) -> Self {
+        self.limit = Some(Limit {
+            rows: Some(rows),
+            offset,
+        });
+        self
+    }
+
+    // Skips rows, keeping the number of rows of an existing limit.
+    pub fn offset(mut self, offset: u64) -> Self {
+        let rows = self.limit.and_then(|limit| limit.rows);
+        self.limit = Some(Limit { rows, offset });
+        self
+    }
+}
+
+fn and_filter(existing: &mut Option<Expr>, filter: Expr) {
+    *existing = Some(match existing.take() {
+        Some(existing) => existing.and(filter),
+        None => filter,
+    });
+}
+
+// Rows are given either as `entries`, objects with the same column names as
+// keys, or as `columns` with `rows` of values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct INSERT {
+    pub into: String,
+    pub entries: Vec<Map<String, Value>>,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Literal>>,
+}
+
+impl INSERT {
+    pub fn into(entity: &str) -> INSERT {
+        INSERT {
+            into: entity.to_string(),
+            entries: vec![],
+            columns: vec![],
+            rows: vec![],
+        }
+    }
+
+    // Values which are not objects are ignored.
+    pub fn entries(mut self, entries: Vec<Value>) -> Self {
+        self.entries
+            .extend(entries.into_iter().filter_map(|entry| match entry {
+                Value::Object(entry) => Some(entry),
+                _ => None,
+            }));
+        self
+    }
+
+    pub fn columns(mut self, columns: Vec<&str>) -> Self {
+        self.columns
+            .extend(columns.iter().map(|col| col.to_string()));
+        self
+    }
+
+    pub fn values(mut self, values: Vec<Literal>) -> Self {
+        self.rows.push(values);
+        self
+    }
+
+    pub fn rows(mut self, rows: Vec<Vec<Literal>>) -> Self {
+        self.rows.extend(rows);
+        self
+    }
+
+    // The columns and rows of all entries. All entries must have the same
+    // columns, as a missing column must keep its default or, on upserts,
+    // its stored value instead of being set to NULL.
+    fn columns_and_rows(&self) -> Result<(Vec<String>, Vec<Vec<Literal>>), InvalidQuery> {
+        let first = match self.entries.first() {
+            Some(first) => first,
+            None => return Ok((self.columns.clone(), self.rows.clone())),
+        };
+        let columns: Vec<String> = first.keys().cloned().collect();
+        let mut rows = vec![];
+        for entry in &self.entries {
+            if entry.len() != columns.len() || !columns.iter().all(|c| entry.contains_key(c)) {
+                return Err(InvalidQuery::new(
+                    QueryErrorKind::UnsupportedQuery,
+                    format!(
+                        "Cannot insert entries with different columns into {}",
+                        self.into
+                    ),
+                ));
+            }
+            rows.push(
+                columns
+                    .iter()
+                    .map(|c| Literal::from_json(&entry[c]))
+                    .collect(),
+            );
+        }
+        Ok((columns, rows))
+    }
+
+    // The table, columns and rows of values, rendered for `bindings`.
+    fn render_rows(&self, bindings: &mut Bindings) -> (String, Vec<String>, Vec<Vec<String>>) {
+        let (columns, rows) = match self.columns_and_rows() {
+            Ok(columns_and_rows) => columns_and_rows,
+            Err(err) => {
+                bindings.reject(err);
+                (vec![], vec![])
+            }
+        };
+        if columns.is_empty() || rows.is_empty() {
+            bindings.reject(InvalidQuery::new(
+                QueryErrorKind::UnsupportedQuery,
+                format!("Cannot insert into {} without rows", self.into),
+            ));
+        }
+        if let Some(row) = rows.iter().find(|row| row.len() != columns.len()) {
+            bindings.reject(InvalidQuery::new(
+                QueryErrorKind::UnsupportedQuery,
+                format!(
+                    "Cannot insert {} values into the {} columns of {}",
+                    row.len(),
+                    columns.len(),
+                    self.into
+                ),
+            ));
+        }
+        let columns = columns
+            .iter()
+            .map(|column| bindings.identifier(column))
+            .collect();
+        let rows = rows
+            .iter()
+            .map(|row| row.iter().map(|val| bindings.literal(val)).collect())
+            .collect();
+        (bindings.identifier(&self.into), columns, rows)
+    }
+}
+
+// Inserts rows or updates the existing rows with the same keys. Columns
+// which are not given keep their values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UPSERT {
+    pub insert: INSERT,
+    pub keys: Vec<String>,
+}
+
+impl UPSERT {
+    pub fn into(entity: &str) -> UPSERT {
+        UPSERT {
+            insert: INSERT::into(entity),
+            keys: vec![],
+        }
+    }
+
+    pub fn entries(self, entries: Vec<Value>) -> Self {
+        UPSERT {
+            insert: self.insert.entries(entries),
+            ..self
+        }
+    }
+
+    pub fn columns(self, columns: Vec<&str>) -> Self {
+        UPSERT {
+            insert: self.insert.columns(columns),
+            ..self
+        }
+    }
+
+    pub fn values(self, values: Vec<Literal>) -> Self {
+        UPSERT {
+            insert: self.insert.values(values),
+            ..self
+        }
+    }
+
+    pub fn rows(self, rows: Vec<Vec<Literal>>) -> Self {
+        UPSERT {
+            insert: self.insert.rows(rows),
+            ..self
+        }
+    }
+
+    // The key columns identifying existing rows.
+    pub fn keys(mut self, keys: Vec<&str>) -> Self {
+        self.keys = keys.into_iter().map(str::to_string).collect();
+        self
+    }
+}
+
+// `data` holds plain values, `with` holds expressions like `stock - 1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UPDATE {
+    pub entity: String,
+    pub data: Vec<(String, Literal)>,
+    pub with: Vec<(String, Expr)>,
+    pub filter: Option<Expr>,
+}
+
+impl UPDATE {
+    pub fn entity(entity: &str) -> UPDATE {
+        UPDATE {
+            entity: entity.to_string(),
+            data: vec![],
+            with: vec![],
+            filter: None,
+        }
+    }
+
+    pub fn set<T: Into<Literal>>(mut self, column: &str, value: T) -> Self {
+        self.data.push((column.to_string(), value.into()));
+        self
+    }
+
+    pub fn with(mut self, column: &str, expr: Expr) -> Self {
+        self.with.push((column.to_string(), expr));
+        self
+    }
+
+    // Repeated filters are combined with `and`.
+    pub fn filter(mut self, filter: Expr) -> Self {
+        and_filter(&mut self.filter, filter);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DELETE {
+    pub from: String,
+    pub filter: Option<Expr>,
+}
+
+impl DELETE {
+    pub fn from(entity: &str) -> DELETE {
+        DELETE {
+            from: entity.to_string(),
+            filter: None,
+        }
+    }
+
+    // Repeated filters are combined with `and`.
+    pub fn filter(mut self, filter: Expr) -> Self {
+        and_filter(&mut self.filter, filter);
+        self
+    }
+}
+
+// Any query, e.g. as read from CQN JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Select(SELECT),
+    Insert(INSERT),
+    Upsert(UPSERT),
+    Update(UPDATE),
+    Delete(DELETE),
+}
+
+pub trait CQN {
+    fn render(&self, bindings: &mut Bindings) -> String;
+
+    // Renders the query with all values inlined. Fails on parts which cannot
+    // be rendered, e.g. function names which are not identifiers or an
+    // INSERT without rows.
+    fn to_sql(&self) -> Result<String, InvalidQuery> {
+        let mut bindings = Bindings::inline();
+        let sql = self.render(&mut bindings);
+        bindings.into_result(sql).map(|statement| statement.sql)
+    }
+
+    // Renders the query with placeholders for values which must be bound.
+    fn to_statement(&self, style: PlaceholderStyle) -> Result<Statement, InvalidQuery> {
+        let mut bindings = Bindings::new(style);
+        let sql = self.render(&mut bindings);
+        bindings.into_result(sql)
+    }
+
+    // Renders the query for `dialect` with all values inlined.
+    fn to_sql_for(&self, dialect: &'static dyn Dialect) -> Result<String, InvalidQuery> {
+        let mut bindings = Bindings::inline().with_dialect(dialect);
+        let sql = self.render(&mut bindings);
+        bindings.into_result(sql).map(|statement| statement.sql)
+    }
+
+    // Renders the query for `dialect` with its placeholders.
+    fn to_statement_for(&self, dialect: &'static dyn Dialect) -> Result<Statement, InvalidQuery> {
+        let mut bindings = Bindings::for_dialect(dialect);
+        let sql = self.render(&mut bindings);
+        bindings.into_result(sql)
+    }
+}
+
+impl CQN for SELECT {
+    fn render(&self, bindings: &mut Bindings) -> String {
+        // Expanded associations are read by separate queries.
+        let columns: Vec<String> = self
+            .columns
+            .iter()
+            .filter(|column| column.expand.is_none())
+            .map(|column| column.render(bindings))
+            .collect();
+        let columns = match !columns.is_empty() {
+            true => columns.join(","),
+            false => "*".to_string(),
+        };
+        // The elements of `*` are only known to `resolve`.
+        let star = self.columns.is_empty() || self.columns.iter().any(|c| c.expr == Expr::Star);
+        if star && !self.excluding.is_empty() {
+            bindings.reject(InvalidQuery::new(
+                QueryErrorKind::UnsupportedQuery,
+                format!(
+                    "Cannot exclude {} from * of {} without definitions, resolve the query first",
+                    self.excluding.join(", "),
+                    self.from
+                ),
+            ));
+        }
+        let from = match &self.alias {
+            Some(alias) => format!(
+                "{} as {}",
+                bindings.identifier(&self.from),
+                bindings.identifier(alias)
+            ),
+            None => bindings.identifier(&self.from),
+        };
+        let mut res = match self.distinct {
+            true => format!("SELECT DISTINCT {} FROM {}", columns, from),
+            false => format!("SELECT {} FROM {}", columns, from),
+        };
+        for join in &self.joins {
+            res = format!("{}\n  {}", res, join.render(bindings));
+        }
+        if let Some(filter) = &self.filter {
+            res = format!("{}\n  WHERE {}", res, filter.render(bindings));
+        }
+        if !self.group_by.is_empty() {
+            let group_by: Vec<String> = self
+                .group_by
+                .iter()
+                .map(|expr| expr.render(bindings))
+                .collect();
+            res = format!("{}\n  GROUP BY {}", res, group_by.join(", "));
+        }
+        if let Some(having) = &self.having {
+            res = format!("{}\n  HAVING {}", res, having.render(bindings));
+        }
+        if !self.order_by.is_empty() {
+            let order_by: Vec<String> = self
+                .order_by
+                .iter()
+                .map(|order_by| order_by.render(bindings))
+                .collect();
+            res = format!("{}\n  ORDER BY {}", res, order_by.join(", "));
+        }
+        // `one` only limits the rows, an offset is kept.
+        let limit = match (self.one, self.limit) {
+            (true, limit) => Some(Limit {
+                rows: Some(1),
+                offset: limit.map_or(0, |limit| limit.offset),
+            }),
+            (false, limit) => limit,
+        };
+        if let Some(limit) = limit.filter(|limit| limit.rows.is_some() || limit.offset > 0) {
+            let limit = bindings.dialect().limit(limit.rows, limit.offset);
+            res = format!("{}\n  {}", res, limit);
+        }
+        res
+    }
+}
+
+impl CQN for INSERT {
+    fn render(&self, bindings: &mut Bindings) -> String {
+        let (table, columns, rows) = self.render_rows(bindings);
+        format!("INSERT INTO {}", values(&table, &columns, &rows))
+    }
+}
+
+impl CQN for UPSERT {
+    fn render(&self, bindings: &mut Bindings) -> String {
+        let (table, columns, rows) = self.insert.render_rows(bindings);
+        let keys: Vec<String> = self
+            .keys
+            .iter()
+            .map(|key| bindings.identifier(key))
+            .collect();
+        if keys.is_empty() {
+            bindings.reject(InvalidQuery::new(
+                QueryErrorKind::UnsupportedQuery,
+                format!("Cannot upsert into {} without keys", self.insert.into),
+            ));
+        }
+        bindings.dialect().upsert(&table, &columns, &rows, &keys)
+    }
+}
+
+impl CQN for UPDATE {
+    fn render(&self, bindings: &mut Bindings) -> String {
+        let mut assignments: Vec<String> = vec![];
+        for (column, value) in &self.data {
+            let value = bindings.literal(value);
+            assignments.push(format!("{} = {}", bindings.identifier(column), value));
+        }
+        for (column, expr) in &self.with {
+            let expr = expr.render(bindings);
+            assignments.push(format!("{} = {}", bindings.identifier(column), expr));
+        }
+        if assignments.is_empty() {
+            bindings.reject(InvalidQuery::new(
+                QueryErrorKind::UnsupportedQuery,
+                format!("Cannot update {} without columns to set", self.entity),
+            ));
+        }
+        let mut res = format!(
+            "UPDATE {} SET {}",
+            bindings.identifier(&self.entity),
+            assignments.join(", ")
+        );
+        if let Some(filter) = &self.filter {
+            res = format!("{}\n  WHERE {}", res, filter.render(bindings));
+        }
+        res
+    }
+}
+
+impl CQN for DELETE {
+    fn render(&self, bindings: &mut Bindings) -> String {
+        let mut res = format!("DELETE FROM {}", bindings.identifier(&self.from));
+        if let Some(filter) = &self.filter {
+            res = format!("{}\n  WHERE {}", res, filter.render(bindings));
+        }
+        res
+    }
+}
+
+impl CQN for Query {
+    fn render(&self, bindings: &mut Bindings) -> String {
+        match self {
+            Query::Select(select) => select.render(bindings),
+            Query::Insert(insert) => insert.render(bindings),
+            Query::Upsert(upsert) => upsert.render(bindings),
+            Query::Update(update) => update.render(bindings),
+            Query::Delete(delete) => delete.render(bindings),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::BinaryOp;
+
+    #[test]
+    fn select_with_col_to_sql() {
+        let select = SELECT::from("example_entity").columns(vec!["col1", "col2"]);
+        assert_eq!(
+            select.to_sql().unwrap(),
+            "SELECT col1,col2 FROM example_entity"
+        )
+    }
+
+    #[test]
+    fn select_without_col_to_sql() {
+        let select = SELECT::from("example_entity");
+        assert_eq!(select.to_sql().unwrap(), "SELECT * FROM example_entity")
+    }
+
+    #[test]
+    fn reject_function_names() {
+        let select = SELECT::from("Books").column(Expr::func("lower", vec![Expr::col("title")]));
+        assert_eq!(select.to_sql().unwrap(), "SELECT lower(title) FROM Books");
+
+        let select = SELECT::from("Books").column(Expr::func("1; DROP TABLE Books; --", vec![]));
+        assert_eq!(
+            select.to_sql().unwrap_err().to_string(),
+            "Invalid function name \"1; DROP TABLE Books; --\""
+        );
+    }
+
+    #[test]
+    fn bind_non_finite_doubles() {
+        let select = SELECT::from("Books").filter(Expr::col("rating").lt(Expr::val(f64::INFINITY)));
+        assert_eq!(
+            select.to_sql().unwrap_err().to_string(),
+            "Cannot inline the double inf, bind it instead"
+        );
+        let statement = select.to_statement(PlaceholderStyle::Question).unwrap();
+        assert_eq!(statement.sql, "SELECT * FROM Books\n  WHERE rating < ?");
+        assert_eq!(statement.params[0].value, Literal::Double(f64::INFINITY));
+    }
+
+    #[test]
+    fn quote_identifiers() {
+        let select = SELECT::from("my.Books")
+            .column(Expr::col("title; DROP TABLE Books --"))
+            .column_as(Expr::col("author_ID"), "a FROM t; --");
+        let expected =
+            "SELECT \"title; DROP TABLE Books --\",author_ID as \"a FROM t; --\" FROM my.Books";
+        assert_eq!(select.to_sql().unwrap(), expected);
+        let select = SELECT::from("Books").columns(vec!["say \"hi\""]);
+        assert_eq!(
+            select.to_statement(PlaceholderStyle::Question).unwrap().sql,
+            "SELECT \"say \"\"hi\"\"\" FROM Books"
+        );
+    }
+
+    #[test]
+    fn select_with_filter_to_sql() {
+        let select = SELECT::from("example_entity").filter(
+            Expr::col("a")
+                .gt(Expr::val(2))
+                .and(Expr::col("b").lt(Expr::val(9)))
+                .or(Expr::col("c").lt(Expr::val(4))),
+        );
+        assert_eq!(
+            select.to_sql().unwrap(),
+            "SELECT * FROM example_entity\n  WHERE a > 2 AND b < 9 OR c < 4"
+        );
+
+        let select = select.filter(Expr::col("d").is_null());
+        assert_eq!(
+            select.to_sql().unwrap(),
+            "SELECT * FROM example_entity\n  WHERE (a > 2 AND b < 9 OR c < 4) AND d IS NULL"
+        )
+    }
+
+    #[test]
+    fn select_with_order_by_and_limit_to_sql() {
+        let select = SELECT::from("Books")
+            .distinct()
+            .columns(vec!["title", "stock"])
+            .filter(Expr::col("stock").gt(Expr::val(0)))
+            .order_by(OrderBy::desc(Expr::col("stock")).nulls_last())
+            .order_by(OrderBy::asc(Expr::col("title")))
+            .limit(10, 20);
+        assert_eq!(
+            select.to_sql().unwrap(),
+            "SELECT DISTINCT title,stock FROM Books\n  WHERE stock > 0\n  ORDER BY stock DESC NULLS LAST, title ASC\n  LIMIT 10 OFFSET 20"
+        );
+
+        let select = SELECT::one("Books").filter(Expr::col("ID").eq(Expr::val(1)));
+        assert_eq!(
+            select.to_sql().unwrap(),
+            "SELECT * FROM Books\n  WHERE ID = 1\n  LIMIT 1"
+        );
+    }
+
+    #[test]
+    fn select_with_group_by_to_sql() {
+        let select = SELECT::from("Books")
+            .column(Expr::col("author_ID"))
+            .column_as(Expr::count(Expr::Star), "books")
+            .column_as(Expr::avg(Expr::col("price")), "price")
+            .group_by(Expr::col("author_ID"))
+            .having(Expr::sum(Expr::col("stock")).gt(Expr::val(10)))
+            .order_by(OrderBy::desc(Expr::col("books")));
+        assert_eq!(
+            select.to_sql().unwrap(),
+            "SELECT author_ID,COUNT(*) as books,AVG(price) as price FROM Books\n  GROUP BY author_ID\n  HAVING SUM(stock) > 10\n  ORDER BY books DESC"
+        );
+    }
+
+    #[test]
+    fn select_to_statement() {
+        let select = SELECT::from("Books").filter(
+            Expr::col("title")
+                .eq(Expr::val("it's"))
+                .and(Expr::col("stock").gt(Expr::val(10)))
+                .and(Expr::col("descr").is_not_null()),
+        );
+        let statement = select.to_statement(PlaceholderStyle::Question).unwrap();
+        assert_eq!(
+            statement.sql,
+            "SELECT * FROM Books\n  WHERE title = ? AND stock > 10 AND descr IS NOT NULL"
+        );
+        assert_eq!(statement.params.len(), 1);
+        assert_eq!(
+            statement.params[0].value,
+            Literal::String("it's".to_string())
+        );
+
+        let statement = select.to_statement(PlaceholderStyle::Named).unwrap();
+        assert!(statement.sql.contains("title = :p1"));
+        assert_eq!(statement.params[0].name, "p1");
+
+        let mut bindings = Bindings::new(PlaceholderStyle::Numbered).bind_all();
+        let sql = select.render(&mut bindings);
+        assert_eq!(
+            sql,
+            "SELECT * FROM Books\n  WHERE title = $1 AND stock > $2 AND descr IS NOT NULL"
+        );
+        assert_eq!(bindings.params()[1].value, Literal::Integer(10));
+    }
+
+    #[test]
+    fn insert_to_sql() {
+        let insert = INSERT::into("Books").entries(vec![
+            serde_json::json!({ "ID": 1, "title": "Wuthering Heights" }),
+            serde_json::json!({ "ID": 2, "title": "Emma" }),
+        ]);
+        assert_eq!(
+            insert.to_sql().unwrap(),
+            "INSERT INTO Books (ID, title) VALUES (1, 'Wuthering Heights'), (2, 'Emma')"
+        );
+
+        // Missing columns are not set to NULL, which would drop their defaults.
+        let mixed = INSERT::into("Books").entries(vec![
+            serde_json::json!({ "ID": 1, "title": "Emma" }),
+            serde_json::json!({ "ID": 2, "stock": 12 }),
+        ]);
+        assert_eq!(
+            mixed.to_sql().unwrap_err().to_string(),
+            "Cannot insert entries with different columns into Books"
+        );
+        assert!(UPSERT {
+            insert: mixed,
+            keys: vec!["ID".to_string()]
+        }
+        .to_sql()
+        .is_err());
+
+        let insert = INSERT::into("Books")
+            .columns(vec!["ID", "title"])
+            .rows(vec![
+                vec![Literal::from(1), Literal::from("Jane Eyre")],
+                vec![Literal::from(2), Literal::from("Emma")],
+            ]);
+        let statement = insert.to_statement(PlaceholderStyle::Numbered).unwrap();
+        assert_eq!(
+            statement.sql,
+            "INSERT INTO Books (ID, title) VALUES (1, $1), (2, $2)"
+        );
+        assert_eq!(statement.params.len(), 2);
+
+        let upsert = UPSERT::into("Books")
+            .columns(vec!["ID", "stock"])
+            .values(vec![Literal::from(1), Literal::from(5)]);
+        assert_eq!(
+            upsert.to_sql().unwrap_err().to_string(),
+            "Cannot upsert into Books without keys"
+        );
+        assert_eq!(
+            upsert.clone().keys(vec!["ID"]).to_sql().unwrap(),
+            "INSERT INTO Books (ID, stock) VALUES (1, 5) ON CONFLICT (ID) DO UPDATE SET stock = excluded.stock"
+        );
+
+        #[cfg(feature = "dialect-postgres")]
+        assert_eq!(
+            upsert
+                .to_statement_for(&crate::dialect::Postgres)
+                .unwrap_err()
+                .to_string(),
+            "Cannot upsert into Books without keys"
+        );
+
+        let insert = INSERT::into("Books").columns(vec!["ID"]);
+        assert_eq!(
+            insert.to_sql().unwrap_err().to_string(),
+            "Cannot insert into Books without rows"
+        );
+        assert!(INSERT::into("Books")
+            .to_statement(PlaceholderStyle::Question)
+            .is_err());
+
+        let insert = INSERT::into("Books")
+            .columns(vec!["ID", "title"])
+            .values(vec![Literal::from(1)]);
+        assert_eq!(
+            insert.to_sql().unwrap_err().to_string(),
+            "Cannot insert 1 values into the 2 columns of Books"
+        );
+    }
+
+    #[test]
+    fn update_and_delete_to_sql() {
+        let update = UPDATE::entity("Books")
+            .set("title", "Persuasion")
+            .with(
+                "stock",
+                Expr::col("stock").binary(BinaryOp::Sub, Expr::val(1)),
+            )
+            .filter(Expr::col("ID").eq(Expr::val(1)));
+        assert_eq!(
+            update.to_sql().unwrap(),
+            "UPDATE Books SET title = 'Persuasion', stock = stock - 1\n  WHERE ID = 1"
+        );
+
+        let update = UPDATE::entity("Books").filter(Expr::col("ID").eq(Expr::val(1)));
+        assert_eq!(
+            update.to_sql().unwrap_err().to_string(),
+            "Cannot update Books without columns to set"
+        );
+
+        let delete = DELETE::from("Books").filter(Expr::col("stock").eq(Expr::val(0)));
+        assert_eq!(
+            delete.to_sql().unwrap(),
+            "DELETE FROM Books\n  WHERE stock = 0"
+        );
+    }
+}
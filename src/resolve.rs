@@ -0,0 +1,606 @@
+// Resolution of queries against `Definitions`: entities become tables,
+// elements columns, and association paths like `author.name` LEFT JOINs.
+use crate::ddl::table_name;
+use crate::entities::*;
+use crate::expr::{BinaryOp, Expr, Literal, Token};
+use crate::query::{Column, Join, OrderBy, SELECT};
+use crate::validate::did_you_mean;
+use std::fmt;
+
+// The kinds of problems of queries, also of the queries of views while
+// loading CSN, see `ErrorKind::InvalidQuery`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryErrorKind {
+    InvalidValue,
+    InvalidType,
+    CyclicReference,
+    UnknownEntity,
+    UnknownElement,
+    UnsupportedQuery,
+    TypeMismatch,
+    InvalidKey,
+}
+
+// A problem of a query. `path` points to the offending part of the query,
+// like `columns[1]` or `where`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidQuery {
+    pub kind: QueryErrorKind,
+    pub path: String,
+    pub description: String,
+}
+
+impl InvalidQuery {
+    pub fn new(kind: QueryErrorKind, description: String) -> InvalidQuery {
+        InvalidQuery {
+            kind,
+            path: String::new(),
+            description,
+        }
+    }
+
+    // Prefixes the path with the path of the enclosing part.
+    pub(crate) fn at(mut self, prefix: &str) -> InvalidQuery {
+        self.path = match self.path.chars().next() {
+            None => prefix.to_string(),
+            Some('[') => format!("{}{}", prefix, self.path),
+            Some(_) => format!("{}.{}", prefix, self.path),
+        };
+        self
+    }
+}
+
+impl fmt::Display for InvalidQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.path.is_empty() {
+            true => f.write_str(&self.description),
+            false => write!(f, "{}: {}", self.path, self.description),
+        }
+    }
+}
+
+impl std::error::Error for InvalidQuery {}
+
+type Result<T> = std::result::Result<T, InvalidQuery>;
+
+pub(crate) fn find_entity<'a>(definitions: &'a Definitions, name: &str) -> Result<&'a Entity> {
+    definitions.entity(name).ok_or_else(|| {
+        let entities = definitions
+            .definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Entity(entity) => Some(entity.name.as_str()),
+                _ => None,
+            });
+        InvalidQuery::new(
+            QueryErrorKind::UnknownEntity,
+            format!(
+                "Cannot find entity {}{}",
+                name,
+                did_you_mean(name, entities)
+            ),
+        )
+    })
+}
+
+pub(crate) fn find_element<'a>(entity: &'a Entity, name: &str) -> Result<&'a Element> {
+    entity.element(name).ok_or_else(|| {
+        let elements = entity.elements.iter().map(|element| element.name.as_str());
+        InvalidQuery::new(
+            QueryErrorKind::UnknownElement,
+            format!(
+                "Cannot find element {} of {}{}",
+                name,
+                entity.name,
+                did_you_mean(name, elements)
+            ),
+        )
+    })
+}
+
+// The default alias of an entity, e.g. `Books` for `my.Books`.
+pub(crate) fn entity_alias(name: &str) -> String {
+    name.rsplit('.').next().unwrap_or(name).to_string()
+}
+
+// Paths may start with the alias of the query or the name of its entity,
+// like `B.title` in `SELECT from my.Books as B { B.title }`.
+pub(crate) fn without_alias<'p>(path: &'p [String], alias: &str, entity: &Entity) -> &'p [String] {
+    match path {
+        [first, rest @ ..] if !rest.is_empty() && (first == alias || *first == entity.name) => rest,
+        _ => path,
+    }
+}
+
+pub(crate) fn association_of(element: &Element) -> Option<&AssociationKind> {
+    match &element.kind {
+        ElementKind::Association(a) | ElementKind::Composition(a) => Some(a),
+        _ => None,
+    }
+}
+
+// The foreign key columns of a managed association, each with the column of
+// the target it refers to, e.g. `author_ID` and `ID`.
+fn foreign_keys(element: &Element, definitions: &Definitions) -> Vec<(String, String)> {
+    let prefix = format!("{}_", element.name);
+    element
+        .columns(definitions)
+        .into_iter()
+        .map(|column| {
+            let target = column.name.trim_start_matches(&prefix).to_string();
+            (column.name, target)
+        })
+        .collect()
+}
+
+fn on_tokens(tokens: &[OnToken]) -> Result<Expr> {
+    let tokens = tokens
+        .iter()
+        .map(|token| {
+            let token = match token {
+                OnToken::Ref { reference } => Token::Expr(Expr::Ref(reference.clone())),
+                OnToken::Val { val } => Token::Expr(Expr::Val(Literal::from_json(val))),
+                OnToken::Xpr { xpr } => Token::Expr(on_tokens(xpr)?),
+                OnToken::Operator(operator) => Token::Keyword(operator.clone()),
+            };
+            Ok(token)
+        })
+        .collect::<Result<Vec<Token>>>()?;
+    Expr::from_tokens(tokens)
+        .map_err(|err| InvalidQuery::new(QueryErrorKind::UnsupportedQuery, err.description))
+}
+
+fn is_self(expr: &Expr) -> bool {
+    matches!(expr, Expr::Ref(path) if path.len() == 1 && path[0] == "$self")
+}
+
+// The source of a join: the alias and entity of the table it is joined to,
+// and the association.
+struct Source<'a> {
+    alias: String,
+    entity: &'a Entity,
+    element: &'a Element,
+    target: &'a Entity,
+}
+
+pub(crate) struct Resolver<'a> {
+    definitions: &'a Definitions,
+    entity: &'a Entity,
+    alias: String,
+    joins: Vec<(Vec<String>, Join)>,
+}
+
+impl<'a> Resolver<'a> {
+    pub(crate) fn new(definitions: &'a Definitions, entity: &'a Entity, alias: &str) -> Self {
+        Resolver {
+            definitions,
+            entity,
+            alias: alias.to_string(),
+            joins: vec![],
+        }
+    }
+
+    fn unique_alias(&self, name: &str) -> String {
+        let taken = |alias: &str| {
+            alias == self.alias || self.joins.iter().any(|(_, join)| join.alias == alias)
+        };
+        let mut alias = name.to_string();
+        let mut n = 1;
+        while taken(&alias) {
+            n += 1;
+            alias = format!("{}{}", name, n);
+        }
+        alias
+    }
+
+    // The condition of a join. Managed associations compare their foreign
+    // keys, unmanaged ones use their `on` condition.
+    fn join_condition(&self, source: &Source, alias: &str) -> Result<Expr> {
+        let association = association_of(source.element).unwrap();
+        let on = match &association.on {
+            Some(on) => on_tokens(on)?,
+            None => {
+                return Ok(conjunction(
+                    foreign_keys(source.element, self.definitions)
+                        .into_iter()
+                        .map(|(column, target)| {
+                            Expr::Ref(vec![alias.to_string(), target])
+                                .eq(Expr::Ref(vec![source.alias.clone(), column]))
+                        })
+                        .collect(),
+                ))
+            }
+        };
+        self.on_condition(&on, source, alias)
+    }
+
+    fn on_condition(&self, on: &Expr, source: &Source, alias: &str) -> Result<Expr> {
+        match on {
+            Expr::Binary(left, op @ (BinaryOp::And | BinaryOp::Or), right) => Ok(Expr::Binary(
+                Box::new(self.on_condition(left, source, alias)?),
+                *op,
+                Box::new(self.on_condition(right, source, alias)?),
+            )),
+            // A backlink like `books.author = $self` compares the foreign keys
+            // of the backlink with the keys of the source.
+            Expr::Binary(left, BinaryOp::Eq, right) if is_self(left) || is_self(right) => {
+                let backlink = match (left.as_ref(), right.as_ref()) {
+                    (Expr::Ref(path), _) | (_, Expr::Ref(path))
+                        if path.len() == 2 && path[0] == source.element.name =>
+                    {
+                        find_element(source.target, &path[1])?
+                    }
+                    _ => return Err(self.unsupported_on(source)),
+                };
+                Ok(conjunction(
+                    foreign_keys(backlink, self.definitions)
+                        .into_iter()
+                        .map(|(column, key)| {
+                            Expr::Ref(vec![alias.to_string(), column])
+                                .eq(Expr::Ref(vec![source.alias.clone(), key]))
+                        })
+                        .collect(),
+                ))
+            }
+            on => on.try_map_refs(&mut |path| match path {
+                [association, name] if association == &source.element.name => {
+                    find_element(source.target, name)?;
+                    Ok(Expr::Ref(vec![alias.to_string(), name.clone()]))
+                }
+                [name] => {
+                    find_element(source.entity, name)?;
+                    Ok(Expr::Ref(vec![source.alias.clone(), name.clone()]))
+                }
+                _ => Err(self.unsupported_on(source)),
+            }),
+        }
+    }
+
+    fn unsupported_on(&self, source: &Source) -> InvalidQuery {
+        InvalidQuery::new(
+            QueryErrorKind::UnsupportedQuery,
+            format!(
+                "Unsupported on condition of {}.{}",
+                source.entity.name, source.element.name
+            ),
+        )
+    }
+
+    // The alias and entity of the join along `path`, which ends with an
+    // association of `entity`.
+    fn join(
+        &mut self,
+        path: &[String],
+        alias: &str,
+        entity: &'a Entity,
+        element: &'a Element,
+    ) -> Result<(String, &'a Entity)> {
+        let association = association_of(element).unwrap();
+        let target = find_entity(self.definitions, &association.target)?;
+        if let Some((_, join)) = self.joins.iter().find(|(prefix, _)| prefix == path) {
+            return Ok((join.alias.clone(), target));
+        }
+        let join_alias = self.unique_alias(&element.name);
+        let source = Source {
+            alias: alias.to_string(),
+            entity,
+            element,
+            target,
+        };
+        let on = self.join_condition(&source, &join_alias)?;
+        self.joins.push((
+            path.to_vec(),
+            Join {
+                table: table_name(&target.name),
+                alias: join_alias.clone(),
+                on,
+            },
+        ));
+        Ok((join_alias, target))
+    }
+
+    // Resolves a path to a column of the root entity or of a join.
+    pub(crate) fn resolve_ref(&mut self, path: &[String]) -> Result<Expr> {
+        if path.is_empty() || path[0].starts_with('$') {
+            return Ok(Expr::Ref(path.to_vec()));
+        }
+        let path = without_alias(path, &self.alias, self.entity);
+        let mut entity = self.entity;
+        let mut alias = self.alias.clone();
+        for (i, name) in path.iter().enumerate() {
+            let element = find_element(entity, name)?;
+            let last = i == path.len() - 1;
+            let association = match (association_of(element), last) {
+                (None, true) => return Ok(Expr::Ref(vec![alias, name.clone()])),
+                (None, false) => {
+                    return Err(InvalidQuery::new(
+                        QueryErrorKind::InvalidType,
+                        format!("{}.{} is not an association", entity.name, name),
+                    ))
+                }
+                (Some(_), true) => {
+                    return Err(InvalidQuery::new(
+                        QueryErrorKind::InvalidType,
+                        format!("Association {}.{} is not a value", entity.name, name),
+                    ))
+                }
+                (Some(association), false) => association,
+            };
+            // Foreign keys are read from the source without a join.
+            if i == path.len() - 2 && association.is_managed() && !association.is_to_many() {
+                let column = format!("{}_{}", name, path[i + 1]);
+                let foreign_keys = foreign_keys(element, self.definitions);
+                if foreign_keys.iter().any(|(name, _)| name == &column) {
+                    return Ok(Expr::Ref(vec![alias, column]));
+                }
+            }
+            let (join_alias, target) = self.join(&path[..=i], &alias, entity, element)?;
+            alias = join_alias;
+            entity = target;
+        }
+        unreachable!()
+    }
+
+    pub(crate) fn resolve_expr(&mut self, expr: &Expr) -> Result<Expr> {
+        expr.try_map_refs(&mut |path| self.resolve_ref(path))
+    }
+
+    // `*` selects all columns of the entity except those of `excluding`.
+    // Paths are named like `author_name` unless they have an alias. Expanded
+    // associations are skipped, see `expand.rs`.
+    pub(crate) fn resolve_columns(
+        &mut self,
+        columns: &[Column],
+        excluding: &[String],
+    ) -> Result<Vec<Column>> {
+        let mut resolved = vec![];
+        for column in columns {
+            if column.expand.is_some() {
+                continue;
+            }
+            if column.expr == Expr::Star {
+                let elements = self
+                    .entity
+                    .elements
+                    .iter()
+                    .filter(|element| !excluding.contains(&element.name));
+                for table_column in elements.flat_map(|e| e.columns(self.definitions)) {
+                    resolved.push(Column::new(Expr::Ref(vec![
+                        self.alias.clone(),
+                        table_column.name,
+                    ])));
+                }
+                continue;
+            }
+            let alias = match (&column.alias, &column.expr) {
+                (Some(alias), _) => Some(alias.clone()),
+                (None, Expr::Ref(path)) => {
+                    let path = without_alias(path, &self.alias, self.entity);
+                    match path.len() {
+                        1 => None,
+                        _ => Some(path.join("_")),
+                    }
+                }
+                _ => None,
+            };
+            resolved.push(Column {
+                expr: self.resolve_expr(&column.expr)?,
+                alias,
+                expand: None,
+                filter: None,
+            });
+        }
+        Ok(resolved)
+    }
+
+    pub(crate) fn into_joins(self) -> Vec<Join> {
+        self.joins.into_iter().map(|(_, join)| join).collect()
+    }
+}
+
+// The pairs of source and target columns which link the rows of an
+// association, e.g. `author_ID` and `ID`. Conditions other than equalities
+// of columns are not supported.
+pub(crate) fn link_columns(
+    definitions: &Definitions,
+    entity: &Entity,
+    element: &Element,
+) -> Result<Vec<(String, String)>> {
+    fn collect(on: &Expr, links: &mut Vec<(String, String)>) -> bool {
+        match on {
+            Expr::Binary(left, BinaryOp::And, right) => {
+                collect(left, links) && collect(right, links)
+            }
+            Expr::Binary(left, BinaryOp::Eq, right) => match (left.as_ref(), right.as_ref()) {
+                (Expr::Ref(a), Expr::Ref(b)) | (Expr::Ref(b), Expr::Ref(a))
+                    if a[0] == "$source" && b[0] == "$target" =>
+                {
+                    links.push((a[1].clone(), b[1].clone()));
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    let association = association_of(element).ok_or_else(|| {
+        InvalidQuery::new(
+            QueryErrorKind::InvalidType,
+            format!("{}.{} is not an association", entity.name, element.name),
+        )
+    })?;
+    let resolver = Resolver::new(definitions, entity, "$source");
+    let source = Source {
+        alias: "$source".to_string(),
+        entity,
+        element,
+        target: find_entity(definitions, &association.target)?,
+    };
+    let on = resolver.join_condition(&source, "$target")?;
+    let mut links = vec![];
+    match collect(&on, &mut links) {
+        true => Ok(links),
+        false => Err(resolver.unsupported_on(&source)),
+    }
+}
+
+fn conjunction(exprs: Vec<Expr>) -> Expr {
+    exprs
+        .into_iter()
+        .reduce(Expr::and)
+        .unwrap_or_else(|| Expr::val(true))
+}
+
+impl SELECT {
+    // The query on tables and columns for `definitions`. Paths through
+    // associations are resolved into LEFT JOINs with generated aliases.
+    pub fn resolve(&self, definitions: &Definitions) -> Result<SELECT> {
+        let entity = find_entity(definitions, &self.from)?;
+        let alias = self
+            .alias
+            .clone()
+            .unwrap_or_else(|| entity_alias(&entity.name));
+        let mut resolver = Resolver::new(definitions, entity, &alias);
+        // A projection without columns but with `excluding` selects `*`.
+        let columns = match self.columns.is_empty() && !self.excluding.is_empty() {
+            true => resolver.resolve_columns(&[Column::new(Expr::Star)], &self.excluding)?,
+            false => resolver.resolve_columns(&self.columns, &self.excluding)?,
+        };
+        let filter = match &self.filter {
+            Some(filter) => Some(resolver.resolve_expr(filter)?),
+            None => None,
+        };
+        let group_by = self
+            .group_by
+            .iter()
+            .map(|expr| resolver.resolve_expr(expr))
+            .collect::<Result<Vec<Expr>>>()?;
+        let having = match &self.having {
+            Some(having) => Some(resolver.resolve_expr(having)?),
+            None => None,
+        };
+        // Orders may refer to the aliases of columns.
+        let mut order_by = vec![];
+        for order in &self.order_by {
+            let expr = order.expr.try_map_refs(&mut |path| match path {
+                [name] if columns.iter().any(|c| c.alias.as_ref() == Some(name)) => {
+                    Ok(Expr::Ref(path.to_vec()))
+                }
+                _ => resolver.resolve_ref(path),
+            })?;
+            order_by.push(OrderBy { expr, ..*order });
+        }
+        Ok(SELECT {
+            from: table_name(&entity.name),
+            alias: Some(alias),
+            joins: resolver.into_joins(),
+            columns,
+            excluding: vec![],
+            filter,
+            group_by,
+            having,
+            order_by,
+            ..self.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::CQN;
+    use std::str::FromStr;
+
+    fn get_test_csn() -> Definitions {
+        let input_str = r#"{"definitions": {
+            "my.Authors": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "name": { "type": "cds.String" },
+                "books": {
+                  "type": "cds.Association",
+                  "cardinality": { "max": "*" },
+                  "target": "my.Books",
+                  "on": [{ "ref": ["books", "author"] }, "=", { "ref": ["$self"] }]
+                }
+              }
+            },
+            "my.Books": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "title": { "type": "cds.String" },
+                "author": { "type": "cds.Association", "target": "my.Authors" },
+                "genre": { "type": "cds.Association", "target": "my.Genres" }
+              }
+            },
+            "my.Genres": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "name": { "type": "cds.String" },
+                "parent": { "type": "cds.Association", "target": "my.Genres" }
+              }
+            }
+          }}"#;
+        Definitions::from_str(input_str).unwrap()
+    }
+
+    #[test]
+    fn resolve_to_one_paths() {
+        let definitions = get_test_csn();
+        let select = SELECT::from("my.Books")
+            .columns(vec!["title", "author.name", "author.ID"])
+            .filter(Expr::col("genre.parent.name").eq(Expr::val("Fiction")))
+            .order_by(OrderBy::asc(Expr::col("author_name")));
+        assert_eq!(
+            select.resolve(&definitions).unwrap().to_sql().unwrap(),
+            "SELECT Books.title,author.name as author_name,Books.author_ID as author_ID FROM my_Books as Books
+  LEFT JOIN my_Authors as author ON author.ID = Books.author_ID
+  LEFT JOIN my_Genres as genre ON genre.ID = Books.genre_ID
+  LEFT JOIN my_Genres as parent ON parent.ID = genre.parent_ID
+  WHERE parent.name = 'Fiction'
+  ORDER BY author_name ASC"
+        );
+    }
+
+    #[test]
+    fn resolve_aliases() {
+        let definitions = get_test_csn();
+        let select = crate::cql::parse(
+            "SELECT from my.Books as B { B.title, B.author.name } where B.ID = 1",
+        )
+        .unwrap();
+        assert_eq!(
+            select.resolve(&definitions).unwrap().to_sql().unwrap(),
+            "SELECT B.title,author.name as author_name FROM my_Books as B\n  LEFT JOIN my_Authors as author ON author.ID = B.author_ID\n  WHERE B.ID = 1"
+        );
+    }
+
+    #[test]
+    fn resolve_to_many_paths() {
+        let definitions = get_test_csn();
+        let select = SELECT::from("my.Authors")
+            .columns(vec!["*", "books.genre.name"])
+            .filter(Expr::col("books.title").like(Expr::val("%Heights")));
+        assert_eq!(
+            select.resolve(&definitions).unwrap().to_sql().unwrap(),
+            "SELECT Authors.ID,Authors.name,genre.name as books_genre_name FROM my_Authors as Authors
+  LEFT JOIN my_Books as books ON books.author_ID = Authors.ID
+  LEFT JOIN my_Genres as genre ON genre.ID = books.genre_ID
+  WHERE books.title LIKE '%Heights'"
+        );
+
+        let err = SELECT::from("my.Authors")
+            .columns(vec!["books.titel"])
+            .resolve(&definitions)
+            .unwrap_err();
+        assert_eq!(err.kind, QueryErrorKind::UnknownElement);
+        assert_eq!(
+            err.description,
+            "Cannot find element titel of my.Books, did you mean title?"
+        );
+    }
+}
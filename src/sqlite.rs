@@ -0,0 +1,861 @@
+// Runs queries against a SQLite database, with the `sqlite` feature. Result
+// values are converted by the types of the elements they are read from.
+use crate::annotations::Annotations;
+use crate::ddl::table_name;
+use crate::dialect::Sqlite;
+use crate::entities::{
+    AssociationKind, Definition, Definitions, Element, ElementKind, Entity, ForeignKey, OnToken,
+    PrimitiveKind, PrimitiveKindBinary, PrimitiveKindDecimal, PrimitiveKindString,
+};
+use crate::expand::Row;
+use crate::expr::{Expr, Literal};
+use crate::migration::type_parts;
+use crate::query::{Query, CQN, INSERT, SELECT, UPSERT};
+use crate::resolve::InvalidQuery;
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::Connection;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidQuery(InvalidQuery),
+    Sqlite(rusqlite::Error),
+    Io(std::io::Error),
+    // The result does not match the requested type.
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidQuery(err) => err.fmt(f),
+            Error::Sqlite(err) => err.fmt(f),
+            Error::Io(err) => err.fmt(f),
+            Error::Json(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<InvalidQuery> for Error {
+    fn from(err: InvalidQuery) -> Error {
+        Error::InvalidQuery(err)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Error {
+        Error::Sqlite(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Json(err)
+    }
+}
+
+fn sql_value(literal: &Literal) -> SqlValue {
+    match literal {
+        Literal::Null => SqlValue::Null,
+        Literal::Bool(b) => SqlValue::Integer(*b as i64),
+        Literal::Integer(i) => SqlValue::Integer(*i),
+        Literal::Double(d) => SqlValue::Real(*d),
+        Literal::Decimal(d) => SqlValue::Text(d.to_string()),
+        Literal::String(s) => SqlValue::Text(s.clone()),
+        Literal::Date(d) => SqlValue::Text(d.to_string()),
+        Literal::Time(t) => SqlValue::Text(t.to_string()),
+        Literal::DateTime(dt) => SqlValue::Text(dt.to_string()),
+        Literal::Timestamp(ts) => SqlValue::Text(ts.to_string()),
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
+
+// Pads the digits of a decimal with zeros up to its scale, `12` becomes
+// `12.00` for a scale of 2. More digits are kept.
+fn with_scale(mut digits: String, scale: Option<u32>) -> String {
+    let scale = scale.unwrap_or(0) as usize;
+    let fraction = match digits.find('.') {
+        Some(pos) => digits.len() - pos - 1,
+        None if scale > 0 => {
+            digits.push('.');
+            0
+        }
+        None => return digits,
+    };
+    for _ in fraction..scale {
+        digits.push('0');
+    }
+    digits
+}
+
+// Booleans are stored as integers, decimals are read as strings like in CQN
+// JSON, binaries are base64 encoded and arrays are stored as JSON text.
+fn json_value(value: ValueRef, kind: Option<&ElementKind>) -> Value {
+    match (value, kind) {
+        (ValueRef::Null, _) => Value::Null,
+        (ValueRef::Integer(i), Some(ElementKind::Boolean(_))) => json!(i != 0),
+        (ValueRef::Integer(i), Some(ElementKind::Double(_))) => json!(i as f64),
+        (ValueRef::Integer(i), Some(ElementKind::Decimal(decimal))) => {
+            json!(with_scale(i.to_string(), decimal.scale))
+        }
+        (ValueRef::Real(r), Some(ElementKind::Decimal(decimal))) => {
+            json!(with_scale(r.to_string(), decimal.scale))
+        }
+        (ValueRef::Integer(i), _) => json!(i),
+        (ValueRef::Real(r), _) => json!(r),
+        (ValueRef::Text(text), Some(ElementKind::Array(_))) => {
+            serde_json::from_slice(text).unwrap_or_else(|_| json!(String::from_utf8_lossy(text)))
+        }
+        (ValueRef::Text(text), _) => json!(String::from_utf8_lossy(text)),
+        (ValueRef::Blob(bytes), _) => json!(base64(bytes)),
+    }
+}
+
+// The element types of the result columns of a resolved query, by the names
+// of the columns. `*` and a query without columns read the elements of
+// `from`. Computed columns have no type.
+fn column_kinds<'a>(
+    definitions: &'a Definitions,
+    query: &SELECT,
+) -> HashMap<String, &'a ElementKind> {
+    let mut entities = HashMap::new();
+    if let Some(entity) = definitions.table_entity(&query.from) {
+        entities.insert(query.alias.clone().unwrap_or_default(), entity);
+    }
+    for join in &query.joins {
+        if let Some(entity) = definitions.table_entity(&join.table) {
+            entities.insert(join.alias.clone(), entity);
+        }
+    }
+    let mut kinds = HashMap::new();
+    for column in &query.columns {
+        if let Expr::Ref(path) = &column.expr {
+            let (alias, name) = match path.as_slice() {
+                [alias, name] => (alias.clone(), name),
+                [name] => (query.alias.clone().unwrap_or_default(), name),
+                _ => continue,
+            };
+            let element = entities.get(&alias).and_then(|entity| entity.element(name));
+            if let Some(element) = element {
+                kinds.insert(
+                    column.alias.clone().unwrap_or_else(|| name.clone()),
+                    &element.kind,
+                );
+            }
+        }
+    }
+    let star = query.columns.is_empty()
+        || query
+            .columns
+            .iter()
+            .any(|column| matches!(column.expr, Expr::Star));
+    if let (true, Some(entity)) = (star, entities.get(&query.alias.clone().unwrap_or_default())) {
+        for element in &entity.elements {
+            kinds.entry(element.name.clone()).or_insert(&element.kind);
+        }
+    }
+    kinds
+}
+
+// Entities are stored in tables named by `table_name`.
+fn with_table_names(definitions: &Definitions, query: &Query) -> Query {
+    let table = |entity: &String| match definitions.entity(entity) {
+        Some(entity) => entity.table_name(),
+        None => table_name(entity),
+    };
+    let mut query = query.clone();
+    match &mut query {
+        Query::Select(_) => {}
+        Query::Insert(insert) => insert.into = table(&insert.into),
+        Query::Upsert(upsert) => {
+            if let (Some(entity), true) = (
+                definitions.entity(&upsert.insert.into),
+                upsert.keys.is_empty(),
+            ) {
+                upsert.keys = entity.key_columns(definitions);
+            }
+            upsert.insert.into = table(&upsert.insert.into)
+        }
+        Query::Update(update) => update.entity = table(&update.entity),
+        Query::Delete(delete) => delete.from = table(&delete.from),
+    }
+    query
+}
+
+// A connection to a SQLite database holding the entities of `definitions`.
+pub struct Database {
+    connection: Connection,
+    definitions: Definitions,
+}
+
+impl Database {
+    pub fn open<P: AsRef<Path>>(path: P, definitions: Definitions) -> Result<Database, Error> {
+        Ok(Database {
+            connection: Connection::open(path)?,
+            definitions,
+        })
+    }
+
+    pub fn open_in_memory(definitions: Definitions) -> Result<Database, Error> {
+        Ok(Database {
+            connection: Connection::open_in_memory()?,
+            definitions,
+        })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    pub fn definitions(&self) -> &Definitions {
+        &self.definitions
+    }
+
+    // Runs a resolved query, see `resolve.rs`.
+    fn fetch(&self, query: &SELECT) -> Result<Vec<Row>, Error> {
+        let statement = query.to_statement_for(&Sqlite)?;
+        let kinds = column_kinds(&self.definitions, query);
+        let mut prepared = self.connection.prepare(&statement.sql)?;
+        let names: Vec<String> = prepared
+            .column_names()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let params = statement.params.iter().map(|param| sql_value(&param.value));
+        let mut rows = prepared.query(rusqlite::params_from_iter(params))?;
+        let mut result = vec![];
+        while let Some(row) = rows.next()? {
+            let mut object = Row::new();
+            for (i, name) in names.iter().enumerate() {
+                let value = json_value(row.get_ref(i)?, kinds.get(name).copied());
+                object.insert(name.clone(), value);
+            }
+            result.push(object);
+        }
+        Ok(result)
+    }
+
+    // The rows of the query with their expanded associations, or with
+    // `SELECT.one` the first row or `null`.
+    pub fn read(&self, select: &SELECT) -> Result<Value, Error> {
+        let expansion = select.expansion(&self.definitions)?;
+        let rows = expansion.execute(&mut |query: &SELECT| self.fetch(query))?;
+        Ok(match select.one {
+            true => rows.into_iter().next().unwrap_or(Value::Null),
+            false => Value::Array(rows),
+        })
+    }
+
+    // Reads the result into `T`, e.g. a `Vec` of structs or an `Option` of a
+    // struct for `SELECT.one`.
+    pub fn read_as<T: DeserializeOwned>(&self, select: &SELECT) -> Result<T, Error> {
+        Ok(serde_json::from_value(self.read(select)?)?)
+    }
+
+    // Runs any query. SELECTs return their result like `read`, other queries
+    // the number of affected rows.
+    pub fn execute(&self, query: &Query) -> Result<Value, Error> {
+        let query = match with_table_names(&self.definitions, query) {
+            Query::Select(select) => return self.read(&select),
+            query => query,
+        };
+        let statement = query.to_statement_for(&Sqlite)?;
+        let params = statement.params.iter().map(|param| sql_value(&param.value));
+        let changed = self
+            .connection
+            .execute(&statement.sql, rusqlite::params_from_iter(params))?;
+        Ok(json!(changed))
+    }
+
+    fn exists(&self, table: &str) -> Result<bool, Error> {
+        let sql = "SELECT count(*) FROM sqlite_master WHERE name = ?1";
+        let count: i64 = self.connection.query_row(sql, [table], |row| row.get(0))?;
+        Ok(count > 0)
+    }
+
+    // Creates the missing tables and views of the model and upserts the data
+    // of the CSV files in `data`, e.g. `db/data`. Deploying again only adds
+    // what is missing. The rows of entities without keys are only inserted
+    // into the tables created by this deployment.
+    pub fn deploy(&self, data: Option<&Path>) -> Result<(), Error> {
+        let transaction = self.connection.unchecked_transaction()?;
+        let mut created = vec![];
+        for entity in self.definitions.deploy_order()? {
+            if self.exists(&entity.table_name())? {
+                continue;
+            }
+            let sql = match entity.query {
+                Some(_) => entity.create_view_for(&self.definitions, &Sqlite)?,
+                None => entity.create_table_for(&self.definitions, &Sqlite),
+            };
+            self.connection.execute(&sql, [])?;
+            created.push(entity.name.as_str());
+        }
+        let mut files = match data {
+            Some(data) => fs::read_dir(data)?.collect::<Result<Vec<_>, _>>()?,
+            None => vec![],
+        };
+        files.sort_by_key(|file| file.file_name());
+        for file in files {
+            let name = file.file_name().to_string_lossy().to_string();
+            let entity = match self.definitions.data_entity(&name) {
+                Some(entity) if entity.query.is_none() && entity.is_deployed() => entity,
+                _ => continue,
+            };
+            let csv = fs::read_to_string(file.path())?;
+            let (insert, keys) = match entity.data_from_csv(&self.definitions, &csv) {
+                Ok(Query::Upsert(upsert)) => (upsert.insert, Some(upsert.keys)),
+                Ok(Query::Insert(insert)) if created.contains(&entity.name.as_str()) => {
+                    (insert, None)
+                }
+                Ok(_) => continue,
+                Err(err) => return Err(err.at(&name).into()),
+            };
+            // Keeps the number of parameters per statement small.
+            for rows in insert.rows.chunks(100) {
+                let mut chunk = INSERT::into(&entity.name).rows(rows.to_vec());
+                chunk.columns = insert.columns.clone();
+                let query = match &keys {
+                    Some(keys) => Query::Upsert(UPSERT {
+                        insert: chunk,
+                        keys: keys.clone(),
+                    }),
+                    None => Query::Insert(chunk),
+                };
+                self.execute(&query)?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+// The element type of a declared column type. Unknown types are mapped by
+// the type affinity rules of SQLite.
+fn element_kind(declared: &str) -> ElementKind {
+    let declared = declared.to_ascii_uppercase();
+    let (base, args) = type_parts(&declared);
+    let length = args.first().copied();
+    match base.trim() {
+        "BOOLEAN" => ElementKind::Boolean(PrimitiveKind { default: None }),
+        "INTEGER" | "INT" => ElementKind::Integer(PrimitiveKind { default: None }),
+        "SMALLINT" => ElementKind::Int16(PrimitiveKind { default: None }),
+        "TINYINT" => ElementKind::UInt8(PrimitiveKind { default: None }),
+        "BIGINT" => ElementKind::Int64(PrimitiveKind { default: None }),
+        "NVARCHAR" | "VARCHAR" | "NCHAR" | "CHAR" | "CHARACTER" => {
+            ElementKind::String(PrimitiveKindString {
+                default: None,
+                length,
+            })
+        }
+        "DECIMAL" | "NUMERIC" => ElementKind::Decimal(PrimitiveKindDecimal {
+            default: None,
+            precision: length.map(|precision| precision as u32),
+            scale: args.get(1).map(|scale| *scale as u32),
+        }),
+        "DATE" => ElementKind::Date(PrimitiveKind { default: None }),
+        "TIME" => ElementKind::Time(PrimitiveKind { default: None }),
+        "DATETIME" => ElementKind::DateTime(PrimitiveKind { default: None }),
+        "TIMESTAMP" => ElementKind::Timestamp(PrimitiveKind { default: None }),
+        "VARBINARY" | "BINARY" => ElementKind::Binary(PrimitiveKindBinary {
+            default: None,
+            length,
+        }),
+        base if base.contains("INT") => ElementKind::Integer(PrimitiveKind { default: None }),
+        base if ["CHAR", "CLOB", "TEXT"].iter().any(|s| base.contains(s)) => {
+            ElementKind::LargeString(PrimitiveKind { default: None })
+        }
+        base if base.is_empty() || base.contains("BLOB") => {
+            ElementKind::LargeBinary(PrimitiveKind { default: None })
+        }
+        base if ["REAL", "FLOA", "DOUB"].iter().any(|s| base.contains(s)) => {
+            ElementKind::Double(PrimitiveKind { default: None })
+        }
+        _ => ElementKind::Decimal(PrimitiveKindDecimal {
+            default: None,
+            precision: None,
+            scale: None,
+        }),
+    }
+}
+
+fn primary_key(connection: &Connection, table: &str) -> Result<Vec<String>, Error> {
+    let sql = "SELECT name FROM pragma_table_info(?1) WHERE pk > 0 ORDER BY pk";
+    let mut statement = connection.prepare(sql)?;
+    let names = statement.query_map([table], |row| row.get(0))?;
+    Ok(names.collect::<Result<_, _>>()?)
+}
+
+// Pairs of referencing and referenced columns.
+type ForeignKeyColumns<T> = Vec<(String, T)>;
+
+// The foreign keys of a table with the referenced table.
+fn foreign_keys(
+    connection: &Connection,
+    table: &str,
+) -> Result<Vec<(String, ForeignKeyColumns<String>)>, Error> {
+    let sql =
+        "SELECT id, \"table\", \"from\", \"to\" FROM pragma_foreign_key_list(?1) ORDER BY id, seq";
+    let mut statement = connection.prepare(sql)?;
+    let rows = statement.query_map([table], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<String>>(3)?,
+        ))
+    })?;
+    let mut foreign_keys: Vec<(i64, String, ForeignKeyColumns<Option<String>>)> = vec![];
+    for row in rows {
+        let (id, target, from, to) = row?;
+        match foreign_keys.last_mut() {
+            Some((last, _, columns)) if *last == id => columns.push((from, to)),
+            _ => foreign_keys.push((id, target, vec![(from, to)])),
+        }
+    }
+    let mut result = vec![];
+    for (_, target, columns) in foreign_keys {
+        // Without referenced columns the primary key is referenced.
+        let keys = match columns.iter().any(|(_, to)| to.is_none()) {
+            true => primary_key(connection, &target)?,
+            false => vec![],
+        };
+        let columns = columns
+            .into_iter()
+            .enumerate()
+            .map(|(i, (from, to))| {
+                let to = to.or_else(|| keys.get(i).cloned()).unwrap_or_default();
+                (from, to)
+            })
+            .collect();
+        result.push((target, columns));
+    }
+    Ok(result)
+}
+
+// A foreign key `author_ID` referencing `ID` becomes the managed association
+// `author`, other foreign keys an unmanaged association like `to_Authors`
+// next to their columns, or `to_Authors_translator` for another foreign key
+// `translator` to the same table.
+fn add_association(elements: &mut Vec<Element>, target: &str, columns: &[(String, String)]) {
+    let prefixes: Vec<Option<&str>> = columns
+        .iter()
+        .map(|(from, to)| from.strip_suffix(to.as_str())?.strip_suffix('_'))
+        .collect();
+    let managed = match prefixes.first() {
+        Some(Some(prefix))
+            if !prefix.is_empty() && prefixes.iter().all(|p| p == &Some(*prefix)) =>
+        {
+            Some(prefix.to_string())
+        }
+        _ => None,
+    }
+    .filter(|name| elements.iter().all(|element| &element.name != name));
+    let position =
+        |elements: &[Element], name: &str| elements.iter().position(|element| element.name == name);
+    match managed {
+        Some(name) => {
+            let key = columns
+                .iter()
+                .any(|(from, _)| position(elements, from).is_some_and(|i| elements[i].key));
+            let index = position(elements, &columns[0].0).unwrap_or(elements.len());
+            elements.retain(|element| columns.iter().all(|(from, _)| &element.name != from));
+            let keys = columns
+                .iter()
+                .map(|(_, to)| ForeignKey {
+                    reference: vec![to.clone()],
+                    alias: None,
+                })
+                .collect();
+            let association = Element {
+                name,
+                key,
+                kind: ElementKind::Association(AssociationKind {
+                    target: target.to_string(),
+                    cardinality: None,
+                    keys: Some(keys),
+                    on: None,
+                }),
+                type_name: None,
+                annotations: Annotations::new(),
+            };
+            elements.insert(index.min(elements.len()), association);
+        }
+        None => {
+            let from: Vec<&str> = columns.iter().map(|(from, _)| from.as_str()).collect();
+            let mut name = format!("to_{}", target);
+            if position(elements, &name).is_some() {
+                name = format!("to_{}_{}", target, from.join("_"));
+            }
+            let base = name.clone();
+            for i in 2.. {
+                if position(elements, &name).is_none() {
+                    break;
+                }
+                name = format!("{}{}", base, i);
+            }
+            let mut on = vec![];
+            for (from, to) in columns {
+                if !on.is_empty() {
+                    on.push(OnToken::Operator("and".to_string()));
+                }
+                on.push(OnToken::Ref {
+                    reference: vec![name.clone(), to.clone()],
+                });
+                on.push(OnToken::Operator("=".to_string()));
+                on.push(OnToken::Ref {
+                    reference: vec![from.clone()],
+                });
+            }
+            elements.push(Element {
+                name,
+                key: false,
+                kind: ElementKind::Association(AssociationKind {
+                    target: target.to_string(),
+                    cardinality: None,
+                    keys: None,
+                    on: Some(on),
+                }),
+                type_name: None,
+                annotations: Annotations::new(),
+            });
+        }
+    }
+}
+
+// Infers the definitions of the tables of a database, as entities named like
+// their tables. Views are not included.
+pub fn definitions_of(connection: &Connection) -> Result<Definitions, Error> {
+    let sql = "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\'";
+    let mut statement = connection.prepare(sql)?;
+    let tables = statement
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    let mut definitions = vec![];
+    for table in tables {
+        let sql = "SELECT name, type, pk FROM pragma_table_info(?1)";
+        let mut statement = connection.prepare(sql)?;
+        let columns = statement.query_map([&table], |row| {
+            Ok(Element {
+                name: row.get(0)?,
+                key: row.get::<_, i64>(2)? > 0,
+                kind: element_kind(&row.get::<_, String>(1)?),
+                type_name: None,
+                annotations: Annotations::new(),
+            })
+        })?;
+        let mut elements = columns.collect::<Result<Vec<_>, _>>()?;
+        for (target, columns) in foreign_keys(connection, &table)? {
+            add_association(&mut elements, &target, &columns);
+        }
+        definitions.push(Definition::Entity(Entity {
+            name: table,
+            elements,
+            annotations: Annotations::new(),
+            query: None,
+            actions: vec![],
+        }));
+    }
+    Ok(Definitions { definitions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{Column, INSERT, UPDATE};
+    use serde::Deserialize;
+    use std::str::FromStr;
+
+    fn get_test_csn() -> Definitions {
+        let input_str = r#"{"definitions": {
+            "my.Authors": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "name": { "type": "cds.String" },
+                "books": {
+                  "type": "cds.Association",
+                  "cardinality": { "max": "*" },
+                  "target": "my.Books",
+                  "on": [{ "ref": ["books", "author"] }, "=", { "ref": ["$self"] }]
+                }
+              }
+            },
+            "my.Books": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "title": { "type": "cds.String" },
+                "price": { "type": "cds.Decimal", "precision": 9, "scale": 2 },
+                "available": { "type": "cds.Boolean" },
+                "author": { "type": "cds.Association", "target": "my.Authors" }
+              }
+            },
+            "my.AvailableBooks": {
+              "kind": "entity",
+              "projection": {
+                "from": { "ref": ["my.Books"] },
+                "where": [{ "ref": ["available"] }, "=", { "val": true }]
+              },
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "title": { "type": "cds.String" }
+              }
+            },
+            "my.Logs": {
+              "kind": "entity",
+              "elements": { "message": { "type": "cds.String" } }
+            }
+          }}"#;
+        Definitions::from_str(input_str).unwrap()
+    }
+
+    fn get_test_database() -> Database {
+        let database = Database::open_in_memory(get_test_csn()).unwrap();
+        database.deploy(None).unwrap();
+        let authors = INSERT::into("my.Authors").entries(vec![
+            json!({ "ID": 1, "name": "Emily" }),
+            json!({ "ID": 2, "name": "Jane" }),
+        ]);
+        let books = INSERT::into("my.Books").entries(vec![
+            json!({ "ID": 10, "title": "Wuthering Heights", "price": "12.5", "available": true, "author_ID": 1 }),
+            json!({ "ID": 11, "title": "Jane Eyre", "price": 9, "available": false, "author_ID": 2 }),
+        ]);
+        for query in [Query::Insert(authors), Query::Insert(books)] {
+            database.execute(&query).unwrap();
+        }
+        database
+    }
+
+    #[test]
+    fn read_json() {
+        let database = get_test_database();
+        let select = SELECT::from("my.Books")
+            .columns(vec!["title", "price", "available", "author.name"])
+            .filter(Expr::col("available").eq(Expr::val(true)));
+        assert_eq!(
+            database.read(&select).unwrap(),
+            json!([{ "title": "Wuthering Heights", "price": "12.50", "available": true, "author_name": "Emily" }])
+        );
+
+        let select = SELECT::one("my.Books").filter(Expr::col("ID").eq(Expr::val(11)));
+        assert_eq!(
+            database.read(&select).unwrap(),
+            json!({ "ID": 11, "title": "Jane Eyre", "price": "9.00", "available": false, "author_ID": 2 })
+        );
+        let select = SELECT::one("my.Books")
+            .column(Expr::Star)
+            .filter(Expr::col("ID").eq(Expr::val(10)));
+        assert_eq!(database.read(&select).unwrap()["available"], json!(true));
+
+        let select = SELECT::one("my.Authors")
+            .columns(vec!["name"])
+            .expand("books", vec![Column::new(Expr::col("title"))])
+            .filter(Expr::col("ID").eq(Expr::val(2)));
+        assert_eq!(
+            database.read(&select).unwrap(),
+            json!({ "name": "Jane", "books": [{ "title": "Jane Eyre" }] })
+        );
+    }
+
+    #[test]
+    fn read_decimals() {
+        let decimal = |scale| {
+            ElementKind::Decimal(PrimitiveKindDecimal {
+                default: None,
+                precision: Some(38),
+                scale,
+            })
+        };
+        let value = json_value(ValueRef::Integer(9007199254740993), Some(&decimal(Some(2))));
+        assert_eq!(value, json!("9007199254740993.00"));
+        let value = json_value(ValueRef::Real(0.1), Some(&decimal(Some(3))));
+        assert_eq!(value, json!("0.100"));
+        let value = json_value(ValueRef::Integer(-7), Some(&decimal(None)));
+        assert_eq!(value, json!("-7"));
+        let text = "12345678901234567890.1234";
+        let value = json_value(ValueRef::Text(text.as_bytes()), Some(&decimal(Some(4))));
+        assert_eq!(value, json!(text));
+    }
+
+    #[test]
+    fn read_structs_and_update() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Book {
+            title: String,
+            available: bool,
+            author: Option<Author>,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Author {
+            name: String,
+        }
+
+        let database = get_test_database();
+        let update = UPDATE::entity("my.Books")
+            .set("available", true)
+            .filter(Expr::col("ID").eq(Expr::val(11)));
+        assert_eq!(database.execute(&Query::Update(update)).unwrap(), json!(1));
+
+        let select = SELECT::from("my.Books")
+            .columns(vec!["title", "available"])
+            .expand("author", vec![Column::new(Expr::col("name"))])
+            .order_by(crate::query::OrderBy::asc(Expr::col("title")));
+        let books: Vec<Book> = database.read_as(&select).unwrap();
+        assert_eq!(
+            books[0],
+            Book {
+                title: "Jane Eyre".to_string(),
+                available: true,
+                author: Some(Author {
+                    name: "Jane".to_string()
+                }),
+            }
+        );
+        assert_eq!(books.len(), 2);
+        assert!(matches!(
+            database.read(&SELECT::from("my.Reviews")),
+            Err(Error::InvalidQuery(_))
+        ));
+    }
+
+    #[test]
+    fn deploy_with_data() {
+        let data = std::env::temp_dir().join(format!("cqn-deploy-{}", std::process::id()));
+        fs::create_dir_all(&data).unwrap();
+        fs::write(data.join("my-Authors.csv"), "ID;name\n1;Emily\n2;Jane\n").unwrap();
+        fs::write(
+            data.join("my.Books.csv"),
+            "ID,title,price,available,author_ID\n10,Wuthering Heights,12.5,true,1\n11,Jane Eyre,9,false,2\n",
+        )
+        .unwrap();
+        fs::write(data.join("my.Logs.csv"), "message\nstarted\n").unwrap();
+        let database = Database::open_in_memory(get_test_csn()).unwrap();
+        let deployed = (0..2)
+            .map(|_| database.deploy(Some(&data)))
+            .collect::<Vec<_>>();
+        fs::remove_dir_all(&data).unwrap();
+        for result in deployed {
+            result.unwrap();
+        }
+
+        let select = SELECT::from("my.AvailableBooks").columns(vec!["title"]);
+        assert_eq!(
+            database.read(&select).unwrap(),
+            json!([{ "title": "Wuthering Heights" }])
+        );
+        let select = SELECT::from("my.Books").column(Expr::count(Expr::Star));
+        let count = database.read(&select).unwrap();
+        assert_eq!(
+            count[0].as_object().unwrap().values().next(),
+            Some(&json!(2))
+        );
+        // Rows without keys are inserted once.
+        let logs = database.read(&SELECT::from("my.Logs")).unwrap();
+        assert_eq!(logs, json!([{ "message": "started" }]));
+
+        // Columns which are not upserted keep their values.
+        let upsert = UPSERT::into("my.Books").entries(vec![json!({ "ID": 10, "price": 15 })]);
+        database.execute(&Query::Upsert(upsert)).unwrap();
+        let select = SELECT::one("my.Books")
+            .columns(vec!["title", "price"])
+            .filter(Expr::col("ID").eq(Expr::val(10)));
+        assert_eq!(
+            database.read(&select).unwrap(),
+            json!({ "title": "Wuthering Heights", "price": "15.00" })
+        );
+    }
+
+    #[test]
+    fn definitions_from_schema() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE Authors (ID INTEGER PRIMARY KEY, name NVARCHAR(100));
+                CREATE TABLE Books (
+                  ID INTEGER NOT NULL,
+                  title TEXT,
+                  author_ID INTEGER REFERENCES Authors(ID),
+                  price DECIMAL(9, 2),
+                  translator INTEGER REFERENCES Authors,
+                  editor INTEGER REFERENCES Authors(ID),
+                  PRIMARY KEY(ID)
+                );",
+            )
+            .unwrap();
+        let definitions = definitions_of(&connection).unwrap();
+        let csn: Value = serde_json::from_str(&definitions.to_csn()).unwrap();
+        assert_eq!(
+            csn["definitions"]["Books"],
+            json!({
+                "kind": "entity",
+                "elements": {
+                    "ID": { "key": true, "type": "cds.Integer" },
+                    "title": { "type": "cds.LargeString" },
+                    "author": { "type": "cds.Association", "target": "Authors", "keys": [{ "ref": ["ID"] }] },
+                    "price": { "type": "cds.Decimal", "precision": 9, "scale": 2 },
+                    "translator": { "type": "cds.Integer" },
+                    "to_Authors": {
+                        "type": "cds.Association",
+                        "target": "Authors",
+                        "on": [{ "ref": ["to_Authors", "ID"] }, "=", { "ref": ["editor"] }]
+                    },
+                    "editor": { "type": "cds.Integer" },
+                    "to_Authors_translator": {
+                        "type": "cds.Association",
+                        "target": "Authors",
+                        "on": [{ "ref": ["to_Authors_translator", "ID"] }, "=", { "ref": ["translator"] }]
+                    }
+                }
+            })
+        );
+
+        let definitions = Definitions::from_str(&definitions.to_csn()).unwrap();
+        assert_eq!(
+            definitions
+                .entity("Books")
+                .unwrap()
+                .create_table(&definitions),
+            "CREATE TABLE Books (
+  ID INTEGER NOT NULL,
+  title NCLOB,
+  author_ID INTEGER,
+  price DECIMAL(9, 2),
+  translator INTEGER,
+  editor INTEGER,
+  PRIMARY KEY(ID)
+)"
+        );
+    }
+}
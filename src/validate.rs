@@ -0,0 +1,496 @@
+// Checks of queries against `Definitions`, reporting all problems at once.
+use crate::entities::*;
+use crate::expr::{invalid_function, is_function_name, BinaryOp, Expr, Literal};
+use crate::query::{Column, SELECT};
+use crate::resolve::{
+    association_of, entity_alias, find_element, find_entity, without_alias, InvalidQuery,
+    QueryErrorKind,
+};
+
+// The edit distance of two names, counting transpositions as one edit.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+// A hint like `, did you mean title?` if one of the candidates is close to
+// `name`. Qualified candidates also match by their last segment.
+pub(crate) fn did_you_mean<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> String {
+    let max = (name.chars().count() / 3).max(1);
+    let best = candidates
+        .map(|candidate| {
+            let short = candidate.rsplit('.').next().unwrap_or(candidate);
+            (
+                distance(name, candidate).min(distance(name, short)),
+                candidate,
+            )
+        })
+        .filter(|(distance, _)| *distance <= max)
+        .min_by_key(|(distance, _)| *distance);
+    match best {
+        Some((_, candidate)) => format!(", did you mean {}?", candidate),
+        None => String::new(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Category {
+    Text,
+    Number,
+    Boolean,
+    Temporal,
+    Binary,
+    Association,
+    Structured,
+}
+
+fn kind_category(kind: &ElementKind) -> Category {
+    match kind {
+        ElementKind::UUID(_) | ElementKind::String(_) | ElementKind::LargeString(_) => {
+            Category::Text
+        }
+        ElementKind::Boolean(_) => Category::Boolean,
+        ElementKind::Integer(_)
+        | ElementKind::Decimal(_)
+        | ElementKind::Double(_)
+        | ElementKind::Int16(_)
+        | ElementKind::Int32(_)
+        | ElementKind::Int64(_)
+        | ElementKind::UInt8(_) => Category::Number,
+        ElementKind::Date(_)
+        | ElementKind::Time(_)
+        | ElementKind::DateTime(_)
+        | ElementKind::Timestamp(_) => Category::Temporal,
+        ElementKind::Binary(_) | ElementKind::LargeBinary(_) => Category::Binary,
+        ElementKind::Association(_) | ElementKind::Composition(_) => Category::Association,
+        ElementKind::Structured(_) | ElementKind::Array(_) => Category::Structured,
+    }
+}
+
+fn literal_category(literal: &Literal) -> Option<Category> {
+    match literal {
+        Literal::Null => None,
+        Literal::Bool(_) => Some(Category::Boolean),
+        Literal::Integer(_) | Literal::Double(_) | Literal::Decimal(_) => Some(Category::Number),
+        Literal::String(_) => Some(Category::Text),
+        Literal::Date(_) | Literal::Time(_) | Literal::DateTime(_) | Literal::Timestamp(_) => {
+            Some(Category::Temporal)
+        }
+    }
+}
+
+// Dates, times and binaries may be given as strings.
+fn compatible(a: Category, b: Category) -> bool {
+    let text = |c| c == Category::Temporal || c == Category::Binary;
+    a == b || (a == Category::Text && text(b)) || (b == Category::Text && text(a))
+}
+
+fn element_at<'a>(
+    definitions: &'a Definitions,
+    entity: &'a Entity,
+    path: &[String],
+) -> Result<&'a Element, InvalidQuery> {
+    let mut entity = entity;
+    for (i, name) in path.iter().enumerate() {
+        let element = find_element(entity, name)?;
+        if i == path.len() - 1 {
+            return Ok(element);
+        }
+        let association = association_of(element).ok_or_else(|| {
+            InvalidQuery::new(
+                QueryErrorKind::InvalidType,
+                format!("{}.{} is not an association", entity.name, name),
+            )
+        })?;
+        entity = find_entity(definitions, &association.target)?;
+    }
+    unreachable!()
+}
+
+fn is_variable(path: &[String]) -> bool {
+    path.first().is_some_and(|name| name.starts_with('$'))
+}
+
+// The paths of a filter like `ID = 1 and version = 2`.
+fn equalities<'f>(filter: &'f Expr, paths: &mut Vec<&'f [String]>) -> bool {
+    match filter {
+        Expr::Binary(left, BinaryOp::And, right) => {
+            equalities(left, paths) && equalities(right, paths)
+        }
+        Expr::Binary(left, BinaryOp::Eq, right) => match (left.as_ref(), right.as_ref()) {
+            (Expr::Ref(path), Expr::Val(_)) | (Expr::Val(_), Expr::Ref(path)) => {
+                paths.push(path);
+                true
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// Paths of the queried entity may start with the alias of the query.
+struct Validator<'a> {
+    definitions: &'a Definitions,
+    entity: &'a Entity,
+    alias: String,
+    problems: Vec<InvalidQuery>,
+}
+
+impl<'a> Validator<'a> {
+    fn report(&mut self, problem: InvalidQuery, path: &str) {
+        self.problems.push(problem.at(path));
+    }
+
+    fn element_at(&self, entity: &'a Entity, path: &[String]) -> Result<&'a Element, InvalidQuery> {
+        let path = match entity.name == self.entity.name {
+            true => without_alias(path, &self.alias, entity),
+            false => path,
+        };
+        element_at(self.definitions, entity, path)
+    }
+
+    // The category of an operand with its description for messages.
+    fn operand(&self, entity: &'a Entity, expr: &Expr) -> Option<(Category, String)> {
+        match expr {
+            Expr::Ref(path) if !is_variable(path) => {
+                let element = self.element_at(entity, path).ok()?;
+                let description =
+                    format!("{} of type {}", path.join("."), element.kind.type_name());
+                Some((kind_category(&element.kind), description))
+            }
+            Expr::Val(literal) => Some((literal_category(literal)?, literal.to_sql())),
+            _ => None,
+        }
+    }
+
+    fn compare(&mut self, entity: &'a Entity, left: &Expr, right: &Expr, path: &str) {
+        let operands = (self.operand(entity, left), self.operand(entity, right));
+        if let (Some((a, left)), Some((b, right))) = operands {
+            if !compatible(a, b) {
+                let description = format!("Cannot compare {} with {}", left, right);
+                self.report(
+                    InvalidQuery::new(QueryErrorKind::TypeMismatch, description),
+                    path,
+                );
+            }
+        }
+    }
+
+    fn check_expr(&mut self, entity: &'a Entity, expr: &Expr, path: &str) {
+        match expr {
+            Expr::Ref(ref_path) if !is_variable(ref_path) => {
+                if let Err(err) = self.element_at(entity, ref_path) {
+                    self.report(err, path);
+                }
+            }
+            Expr::Func(name, _) if !is_function_name(name) => {
+                self.report(invalid_function(name), path);
+            }
+            Expr::Func(_, exprs) | Expr::List(exprs) => {
+                for expr in exprs {
+                    self.check_expr(entity, expr, path);
+                }
+            }
+            Expr::Unary(_, operand) => self.check_expr(entity, operand, path),
+            Expr::Binary(left, op, right) => {
+                self.check_expr(entity, left, path);
+                self.check_expr(entity, right, path);
+                match (op, right.as_ref()) {
+                    (BinaryOp::In, Expr::List(items)) => {
+                        for item in items {
+                            self.compare(entity, left, item, path);
+                        }
+                    }
+                    (BinaryOp::Eq, _)
+                    | (BinaryOp::Ne, _)
+                    | (BinaryOp::Lt, _)
+                    | (BinaryOp::Le, _)
+                    | (BinaryOp::Gt, _)
+                    | (BinaryOp::Ge, _)
+                    | (BinaryOp::Like, _) => self.compare(entity, left, right, path),
+                    _ => {}
+                }
+            }
+            Expr::Between(expr, low, high) => {
+                for operand in [expr, low, high] {
+                    self.check_expr(entity, operand, path);
+                }
+                self.compare(entity, expr, low, path);
+                self.compare(entity, expr, high, path);
+            }
+            _ => {}
+        }
+    }
+
+    fn check_columns(&mut self, entity: &'a Entity, columns: &[Column], path: &str) {
+        for (i, column) in columns.iter().enumerate() {
+            let path = format!("{}[{}]", path, i);
+            let expand = match &column.expand {
+                Some(expand) => expand,
+                None => {
+                    self.check_expr(entity, &column.expr, &path);
+                    continue;
+                }
+            };
+            let element = match &column.expr {
+                Expr::Ref(ref_path) => match self.element_at(entity, ref_path) {
+                    Ok(element) => element,
+                    Err(err) => {
+                        self.report(err, &path);
+                        continue;
+                    }
+                },
+                _ => continue,
+            };
+            let target = match association_of(element) {
+                Some(association) => find_entity(self.definitions, &association.target),
+                None => Err(InvalidQuery::new(
+                    QueryErrorKind::InvalidType,
+                    format!(
+                        "Cannot expand {}, which is not an association",
+                        element.name
+                    ),
+                )),
+            };
+            match target {
+                Ok(target) => {
+                    if let Some(filter) = &column.filter {
+                        self.check_expr(target, filter, &format!("{}.where", path));
+                    }
+                    self.check_columns(target, expand, &format!("{}.expand", path))
+                }
+                Err(err) => self.report(err, &path),
+            }
+        }
+    }
+
+    // A filter of `SELECT.one` by equalities must use exactly the keys, no
+    // filter misses all keys.
+    fn check_one(&mut self, entity: &Entity, filter: Option<&Expr>) {
+        let mut paths = vec![];
+        if filter.is_some_and(|filter| !equalities(filter, &mut paths)) {
+            return;
+        }
+        let mut names = vec![];
+        for path in paths {
+            match without_alias(path, &self.alias, entity) {
+                [name] => names.push(name.clone()),
+                _ => return,
+            }
+        }
+        let keys: Vec<&str> = entity.keys().map(|key| key.name.as_str()).collect();
+        let non_keys: Vec<&str> = names
+            .iter()
+            .map(String::as_str)
+            .filter(|name| entity.element(name).is_some() && !keys.contains(name))
+            .collect();
+        let missing: Vec<&str> = keys
+            .iter()
+            .copied()
+            .filter(|key| !names.iter().any(|name| name == key))
+            .collect();
+        let description = match (non_keys.is_empty(), missing.is_empty()) {
+            (true, true) => return,
+            (false, _) => format!(
+                "SELECT.one from {} by {}, which is not a key, the keys are {}",
+                entity.name,
+                non_keys.join(", "),
+                keys.join(", ")
+            ),
+            (true, false) => format!(
+                "SELECT.one from {} misses the keys {}",
+                entity.name,
+                missing.join(", ")
+            ),
+        };
+        self.report(
+            InvalidQuery::new(QueryErrorKind::InvalidKey, description),
+            "where",
+        );
+    }
+}
+
+impl SELECT {
+    // Checks the query against `definitions`. Unknown entities and elements
+    // come with suggestions for similar names.
+    pub fn validate(&self, definitions: &Definitions) -> Result<(), Vec<InvalidQuery>> {
+        let entity = find_entity(definitions, &self.from).map_err(|err| vec![err.at("from")])?;
+        let mut validator = Validator {
+            definitions,
+            entity,
+            alias: self
+                .alias
+                .clone()
+                .unwrap_or_else(|| entity_alias(&entity.name)),
+            problems: vec![],
+        };
+        validator.check_columns(entity, &self.columns, "columns");
+        if let Some(filter) = &self.filter {
+            validator.check_expr(entity, filter, "where");
+        }
+        if self.one {
+            validator.check_one(entity, self.filter.as_ref());
+        }
+        for (i, expr) in self.group_by.iter().enumerate() {
+            validator.check_expr(entity, expr, &format!("groupBy[{}]", i));
+        }
+        if let Some(having) = &self.having {
+            validator.check_expr(entity, having, "having");
+        }
+        // Orders may refer to the aliases of columns.
+        for (i, order_by) in self.order_by.iter().enumerate() {
+            let alias = match &order_by.expr {
+                Expr::Ref(path) if path.len() == 1 => self
+                    .columns
+                    .iter()
+                    .any(|c| c.alias.as_ref() == Some(&path[0])),
+                _ => false,
+            };
+            if !alias {
+                validator.check_expr(entity, &order_by.expr, &format!("orderBy[{}]", i));
+            }
+        }
+        match validator.problems.is_empty() {
+            true => Ok(()),
+            false => Err(validator.problems),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn get_test_csn() -> Definitions {
+        let input_str = r#"{"definitions": {
+            "my.Authors": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.Integer" },
+                "name": { "type": "cds.String" }
+              }
+            },
+            "my.Books": {
+              "kind": "entity",
+              "elements": {
+                "ID": { "key": true, "type": "cds.UUID" },
+                "title": { "type": "cds.String" },
+                "stock": { "type": "cds.Integer" },
+                "published": { "type": "cds.Date" },
+                "author": { "type": "cds.Association", "target": "my.Authors" }
+              }
+            }
+          }}"#;
+        Definitions::from_str(input_str).unwrap()
+    }
+
+    fn problems(select: SELECT) -> Vec<String> {
+        match select.validate(&get_test_csn()) {
+            Ok(()) => vec![],
+            Err(problems) => problems.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn validate_names() {
+        let select = SELECT::from("my.Books")
+            .columns(vec!["titel", "author.nmae", "stock"])
+            .expand("author", vec![Column::new(Expr::col("ID"))])
+            .filter(Expr::col("published").ge(Expr::val("2020-01-01")));
+        assert_eq!(
+            problems(select),
+            vec![
+                "columns[0]: Cannot find element titel of my.Books, did you mean title?",
+                "columns[1]: Cannot find element nmae of my.Authors, did you mean name?",
+            ]
+        );
+
+        let err = SELECT::from("Book").validate(&get_test_csn()).unwrap_err();
+        assert_eq!(err[0].kind, QueryErrorKind::UnknownEntity);
+        assert_eq!(
+            err[0].to_string(),
+            "from: Cannot find entity Book, did you mean my.Books?"
+        );
+
+        let select = SELECT::from("my.Books").filter(Expr::func("f(1) or 1", vec![]));
+        assert_eq!(
+            problems(select),
+            vec!["where: Invalid function name \"f(1) or 1\""]
+        );
+    }
+
+    #[test]
+    fn validate_types_and_keys() {
+        let select = SELECT::from("my.Books").filter(
+            Expr::col("stock")
+                .gt(Expr::val("ten"))
+                .and(Expr::col("title").in_list(Expr::list(vec![1, 2])))
+                .and(Expr::col("author.ID").eq(Expr::col("stock"))),
+        );
+        assert_eq!(
+            problems(select),
+            vec![
+                "where: Cannot compare stock of type cds.Integer with 'ten'",
+                "where: Cannot compare title of type cds.String with 1",
+                "where: Cannot compare title of type cds.String with 2",
+            ]
+        );
+
+        let select = SELECT::from("my.Books").filter(Expr::col("stock").gt(Expr::val("ten")));
+        let err = select.validate(&get_test_csn()).unwrap_err();
+        assert_eq!(err[0].kind, QueryErrorKind::TypeMismatch);
+
+        let select = SELECT::one("my.Books").filter(Expr::col("title").eq(Expr::val("Emma")));
+        let err = select.clone().validate(&get_test_csn()).unwrap_err();
+        assert_eq!(err[0].kind, QueryErrorKind::InvalidKey);
+        assert_eq!(
+            problems(select),
+            vec!["where: SELECT.one from my.Books by title, which is not a key, the keys are ID"]
+        );
+        let select = SELECT::one("my.Books").filter(Expr::col("ID").eq(Expr::val("1")));
+        assert!(problems(select).is_empty());
+        assert_eq!(
+            problems(SELECT::one("my.Books")),
+            vec!["where: SELECT.one from my.Books misses the keys ID"]
+        );
+    }
+
+    #[test]
+    fn validate_aliases() {
+        let select = crate::cql::parse("SELECT from my.Books as B { B.title } where B.stock > 1");
+        assert!(problems(select.unwrap()).is_empty());
+        let select = SELECT::from("my.Books").columns(vec!["Books.title"]);
+        assert!(problems(select).is_empty());
+
+        let select = crate::cql::parse("SELECT one from my.Books as B where B.title = 'Emma'");
+        assert_eq!(
+            problems(select.unwrap()),
+            vec!["where: SELECT.one from my.Books by title, which is not a key, the keys are ID"]
+        );
+        let select = crate::cql::parse("SELECT from my.Books as B { B.titel }");
+        assert_eq!(
+            problems(select.unwrap()),
+            vec!["columns[0]: Cannot find element titel of my.Books, did you mean title?"]
+        );
+    }
+}
@@ -0,0 +1,386 @@
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::value::RawValue;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidLiteral {
+    pub kind: &'static str,
+    pub input: String,
+}
+
+impl fmt::Display for InvalidLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid {} literal \"{}\"", self.kind, self.input)
+    }
+}
+
+impl std::error::Error for InvalidLiteral {}
+
+fn invalid(kind: &'static str, input: &str) -> InvalidLiteral {
+    InvalidLiteral {
+        kind,
+        input: input.to_string(),
+    }
+}
+
+// Decimals keep their textual representation so that no precision is lost
+// between the model and the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decimal(String);
+
+impl Decimal {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.0.parse().unwrap_or(f64::NAN)
+    }
+
+    pub fn scale(&self) -> usize {
+        match self.0.find('.') {
+            Some(pos) => self.0.len() - pos - 1,
+            None => 0,
+        }
+    }
+
+    pub fn precision(&self) -> usize {
+        self.0
+            .trim_start_matches('-')
+            .trim_start_matches(['0', '.'])
+            .chars()
+            .filter(|c| c.is_ascii_digit())
+            .count()
+            .max(1)
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = InvalidLiteral;
+
+    fn from_str(s: &str) -> Result<Decimal, InvalidLiteral> {
+        let digits = s.strip_prefix('-').unwrap_or(s);
+        let (int, frac) = match digits.find('.') {
+            Some(pos) => (&digits[..pos], Some(&digits[pos + 1..])),
+            None => (digits, None),
+        };
+        let all_digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+        if !all_digits(int) || !frac.is_none_or(all_digits) {
+            return Err(invalid("decimal", s));
+        }
+        Ok(Decimal(s.to_string()))
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(v: i64) -> Decimal {
+        Decimal(v.to_string())
+    }
+}
+
+impl From<u64> for Decimal {
+    fn from(v: u64) -> Decimal {
+        Decimal(v.to_string())
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+// JSON numbers are taken from their literal text to avoid a detour via f64.
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+        let raw = Box::<RawValue>::deserialize(deserializer)?;
+        match raw.get().parse() {
+            Ok(decimal) => Ok(decimal),
+            Err(_) => {
+                let text: String = serde_json::from_str(raw.get()).map_err(D::Error::custom)?;
+                text.parse().map_err(D::Error::custom)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    pub fn new(year: i32, month: u8, day: u8) -> Option<Date> {
+        let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+        let days = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => return None,
+        };
+        if day == 0 || day > days {
+            return None;
+        }
+        Some(Date { year, month, day })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Time {
+    pub fn new(hour: u8, minute: u8, second: u8) -> Option<Time> {
+        if hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        Some(Time {
+            hour,
+            minute,
+            second,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub date: Date,
+    pub time: Time,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp {
+    pub date: Date,
+    pub time: Time,
+    pub nanos: u32,
+}
+
+fn parse_number<T: FromStr>(s: &str, len: usize) -> Option<T> {
+    if s.len() != len || !s.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn parse_date(s: &str) -> Option<Date> {
+    let mut parts = s.splitn(3, '-');
+    let year = parse_number(parts.next()?, 4)?;
+    let month = parse_number(parts.next()?, 2)?;
+    let day = parse_number(parts.next()?, 2)?;
+    Date::new(year, month, day)
+}
+
+fn parse_time(s: &str) -> Option<Time> {
+    let mut parts = s.splitn(3, ':');
+    let hour = parse_number(parts.next()?, 2)?;
+    let minute = parse_number(parts.next()?, 2)?;
+    let second = parse_number(parts.next()?, 2)?;
+    Time::new(hour, minute, second)
+}
+
+fn split_date_time(s: &str) -> Option<(Date, &str)> {
+    let s = s.strip_suffix('Z').unwrap_or(s);
+    if s.len() < 11 || !matches!(s.as_bytes()[10], b'T' | b' ') {
+        return None;
+    }
+    Some((parse_date(&s[..10])?, &s[11..]))
+}
+
+// Splits `10:20:30.123` into the time and up to nine fractional digits.
+fn split_fraction(s: &str) -> Option<(&str, &str)> {
+    let (time, fraction) = match s.find('.') {
+        Some(pos) => (&s[..pos], &s[pos + 1..]),
+        None => (s, ""),
+    };
+    if fraction.len() > 9 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((time, fraction))
+}
+
+impl FromStr for Date {
+    type Err = InvalidLiteral;
+
+    fn from_str(s: &str) -> Result<Date, InvalidLiteral> {
+        parse_date(s).ok_or_else(|| invalid("date", s))
+    }
+}
+
+impl FromStr for Time {
+    type Err = InvalidLiteral;
+
+    fn from_str(s: &str) -> Result<Time, InvalidLiteral> {
+        parse_time(s).ok_or_else(|| invalid("time", s))
+    }
+}
+
+// Fractional seconds are accepted and dropped.
+impl FromStr for DateTime {
+    type Err = InvalidLiteral;
+
+    fn from_str(s: &str) -> Result<DateTime, InvalidLiteral> {
+        let parsed = split_date_time(s).and_then(|(date, rest)| {
+            let (time, _) = split_fraction(rest)?;
+            Some(DateTime {
+                date,
+                time: parse_time(time)?,
+            })
+        });
+        parsed.ok_or_else(|| invalid("datetime", s))
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = InvalidLiteral;
+
+    fn from_str(s: &str) -> Result<Timestamp, InvalidLiteral> {
+        let parsed = split_date_time(s).and_then(|(date, rest)| {
+            let (time, fraction) = split_fraction(rest)?;
+            let nanos = format!("{:0<9}", fraction).parse().ok()?;
+            Some(Timestamp {
+                date,
+                time: parse_time(time)?,
+                nanos,
+            })
+        });
+        parsed.ok_or_else(|| invalid("timestamp", s))
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}T{}Z", self.date, self.time)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fraction = format!("{:09}", self.nanos);
+        let fraction = fraction.trim_end_matches('0');
+        write!(f, "{}T{}.{:0<3}Z", self.date, self.time, fraction)
+    }
+}
+
+macro_rules! string_serde {
+    ($($ty:ty => $expecting:expr),*) => {
+        $(
+            impl Serialize for $ty {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $ty {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<$ty, D::Error> {
+                    struct StrVisitor;
+
+                    impl<'de> serde::de::Visitor<'de> for StrVisitor {
+                        type Value = $ty;
+
+                        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                            f.write_str($expecting)
+                        }
+
+                        fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<$ty, E> {
+                            v.parse().map_err(E::custom)
+                        }
+                    }
+
+                    deserializer.deserialize_str(StrVisitor)
+                }
+            }
+        )*
+    };
+}
+
+string_serde!(
+    Date => "a date string like 2020-12-31",
+    Time => "a time string like 23:59:59",
+    DateTime => "a datetime string like 2020-12-31T23:59:59Z",
+    Timestamp => "a timestamp string like 2020-12-31T23:59:59.999Z"
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_decimal() {
+        let decimal: Decimal = "-12.340".parse().unwrap();
+        assert_eq!(decimal.as_str(), "-12.340");
+        assert_eq!(decimal.scale(), 3);
+        assert_eq!(decimal.precision(), 5);
+        assert!("1.2.3".parse::<Decimal>().is_err());
+        assert!("12.".parse::<Decimal>().is_err());
+        assert!("abc".parse::<Decimal>().is_err());
+    }
+
+    #[test]
+    fn deserialize_decimal() {
+        let from_str: Decimal = serde_json::from_str(r#""1234567890.1234567890123""#).unwrap();
+        assert_eq!(from_str.as_str(), "1234567890.1234567890123");
+        let from_number: Decimal = serde_json::from_str("9.99").unwrap();
+        assert_eq!(from_number.as_str(), "9.99");
+        let from_int: Decimal = serde_json::from_str("-42").unwrap();
+        assert_eq!(from_int.as_str(), "-42");
+        let precise: Decimal = serde_json::from_str("12345678901234567890.123456789").unwrap();
+        assert_eq!(precise.as_str(), "12345678901234567890.123456789");
+        let from_reader: Decimal = serde_json::from_reader("0.10".as_bytes()).unwrap();
+        assert_eq!(from_reader.as_str(), "0.10");
+        let from_value: Decimal = serde_json::from_value(serde_json::json!("1.50")).unwrap();
+        assert_eq!(from_value.as_str(), "1.50");
+        assert!(serde_json::from_str::<Decimal>("true").is_err());
+    }
+
+    #[test]
+    fn parse_date_and_time() {
+        assert_eq!(
+            "2020-02-29".parse::<Date>().unwrap(),
+            Date::new(2020, 2, 29).unwrap()
+        );
+        assert!("2019-02-29".parse::<Date>().is_err());
+        assert!("2020-13-01".parse::<Date>().is_err());
+        assert_eq!("23:59:01".parse::<Time>().unwrap().to_string(), "23:59:01");
+        assert!("24:00:00".parse::<Time>().is_err());
+    }
+
+    #[test]
+    fn parse_datetime_and_timestamp() {
+        let datetime: DateTime = "2020-01-31T10:20:30Z".parse().unwrap();
+        assert_eq!(datetime.time, Time::new(10, 20, 30).unwrap());
+        assert_eq!(datetime.to_string(), "2020-01-31T10:20:30Z");
+        let timestamp: Timestamp = "2020-01-31 10:20:30.5".parse().unwrap();
+        assert_eq!(timestamp.nanos, 500_000_000);
+        assert_eq!(timestamp.to_string(), "2020-01-31T10:20:30.500Z");
+        let datetime: DateTime = "2024-01-01T00:00:00.123Z".parse().unwrap();
+        assert_eq!(datetime.to_string(), "2024-01-01T00:00:00Z");
+        assert!("2024-01-01T00:00:00.1x".parse::<DateTime>().is_err());
+        assert!("2020-01-31".parse::<DateTime>().is_err());
+    }
+}